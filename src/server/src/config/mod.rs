@@ -1,5 +1,6 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -8,12 +9,19 @@ pub struct Config {
     pub server: ServerConfig,
     pub software: SoftwareConfig,
     pub config_files: ConfigFilesSettings,
+    #[serde(default)]
+    pub profile: ProfileConfig,
+    #[serde(default)]
+    pub command_policy: CommandPolicyConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    // 允许跨域访问接口的来源，为空时只接受同源请求
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -27,6 +35,198 @@ pub struct SoftwareConfig {
 pub struct ConfigFilesSettings {
     pub backup_dir: String,
     pub default_config_path: String,
+    // 配置文件记录等持久化数据的存放目录
+    #[serde(default = "default_data_dir")]
+    pub data_dir: String,
+    #[serde(default)]
+    pub backup_retention: BackupRetention,
+    // 生成文本差异的文件大小上限（字节）
+    #[serde(default = "default_max_diff_size")]
+    pub max_diff_size: u64,
+    // 通过接口读写源文件内容的大小上限（字节）
+    #[serde(default = "default_max_content_size")]
+    pub max_content_size: u64,
+    #[serde(default)]
+    pub secrets: SecretSettings,
+    #[serde(default)]
+    pub git: GitStoreSettings,
+    #[serde(default)]
+    pub drift: DriftSettings,
+    #[serde(default)]
+    pub target_policy: TargetPolicySettings,
+}
+
+/// 部署目标路径的限制，目标路径在解析 `..` 和符号链接之后检查
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TargetPolicySettings {
+    // 允许部署到的目录，可以使用环境变量和 `~`，为空时不限制
+    #[serde(default = "default_allowed_roots")]
+    pub allowed_roots: Vec<String>,
+    // 除内置的系统目录外，额外禁止部署到的位置
+    #[serde(default)]
+    pub denied_paths: Vec<String>,
+}
+
+fn default_allowed_roots() -> Vec<String> {
+    vec!["~".to_string()]
+}
+
+impl Default for TargetPolicySettings {
+    fn default() -> Self {
+        TargetPolicySettings {
+            allowed_roots: default_allowed_roots(),
+            denied_paths: Vec::new(),
+        }
+    }
+}
+
+/// 部署后变化检测，设置间隔后在后台定期扫描并缓存结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DriftSettings {
+    // 定期扫描的间隔（分钟），未设置时只在请求时扫描
+    #[serde(default)]
+    pub interval_minutes: Option<u64>,
+}
+
+/// 托管源文件目录的 git 版本管理，启用后通过接口的修改都会提交到仓库
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitStoreSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    // 保存托管源文件的目录，不存在仓库时自动初始化
+    #[serde(default = "default_git_repo_dir")]
+    pub repo_dir: String,
+    // 推送和拉取使用的远程仓库地址，可以是本地裸仓库路径
+    #[serde(default)]
+    pub remote: Option<String>,
+    #[serde(default = "default_git_branch")]
+    pub branch: String,
+    // 每次提交后自动推送到远程仓库
+    #[serde(default)]
+    pub auto_push: bool,
+    #[serde(default = "default_git_author_name")]
+    pub author_name: String,
+    #[serde(default = "default_git_author_email")]
+    pub author_email: String,
+}
+
+fn default_git_repo_dir() -> String {
+    ".\\configs".to_string()
+}
+
+fn default_git_branch() -> String {
+    "main".to_string()
+}
+
+fn default_git_author_name() -> String {
+    "System Install Helper".to_string()
+}
+
+fn default_git_author_email() -> String {
+    "installer@localhost".to_string()
+}
+
+impl Default for GitStoreSettings {
+    fn default() -> Self {
+        GitStoreSettings {
+            enabled: false,
+            repo_dir: default_git_repo_dir(),
+            remote: None,
+            branch: default_git_branch(),
+            auto_push: false,
+            author_name: default_git_author_name(),
+            author_email: default_git_author_email(),
+        }
+    }
+}
+
+/// 加密配置文件使用的密钥来源，`key_file` 优先于口令
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SecretSettings {
+    // 密钥文件路径，文件内容即为密钥材料
+    #[serde(default)]
+    pub key_file: Option<String>,
+    // 保存口令的环境变量名
+    #[serde(default)]
+    pub passphrase_env: Option<String>,
+}
+
+fn default_max_diff_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_content_size() -> u64 {
+    1024 * 1024
+}
+
+/// 备份保留策略，每次产生新备份后自动清理
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupRetention {
+    // 每个被备份的文件最多保留的备份数量
+    #[serde(default = "default_keep_last")]
+    pub keep_last: Option<usize>,
+    // 备份最长保留天数
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+    // 所有备份的总大小上限（字节），超出时删除最旧的备份
+    #[serde(default)]
+    pub max_total_size: Option<u64>,
+}
+
+fn default_keep_last() -> Option<usize> {
+    Some(10)
+}
+
+impl Default for BackupRetention {
+    fn default() -> Self {
+        BackupRetention {
+            keep_last: default_keep_last(),
+            max_age_days: None,
+            max_total_size: None,
+        }
+    }
+}
+
+fn default_data_dir() -> String {
+    ".\\data".to_string()
+}
+
+/// 用户配置档案，其中的变量可在软件配置的命令和路径模板中引用
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProfileConfig {
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// 命令执行策略，在执行安装命令前校验
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandPolicyConfig {
+    // 允许执行的程序名，为空时不限制
+    #[serde(default)]
+    pub allowed_executables: Vec<String>,
+    // 代入命令的用户输入中禁止出现的字符
+    #[serde(default = "default_forbidden_metacharacters")]
+    pub forbidden_metacharacters: Vec<String>,
+    // 同时检查软件配置中的 shell 命令，命令由管理员编写，默认不检查
+    #[serde(default)]
+    pub check_shell_commands: bool,
+}
+
+fn default_forbidden_metacharacters() -> Vec<String> {
+    [";", "&", "|", "`", "$", ">", "<", "\n", "\r"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect()
+}
+
+impl Default for CommandPolicyConfig {
+    fn default() -> Self {
+        CommandPolicyConfig {
+            allowed_executables: Vec::new(),
+            forbidden_metacharacters: default_forbidden_metacharacters(),
+            check_shell_commands: false,
+        }
+    }
 }
 
 impl Default for Config {
@@ -35,6 +235,7 @@ impl Default for Config {
             server: ServerConfig {
                 host: "127.0.0.1".to_string(),
                 port: 8080,
+                allowed_origins: Vec::new(),
             },
             software: SoftwareConfig {
                 repositories: vec![
@@ -47,7 +248,17 @@ impl Default for Config {
             config_files: ConfigFilesSettings {
                 backup_dir: ".\\backups".to_string(),
                 default_config_path: "%USERPROFILE%".to_string(),
+                data_dir: default_data_dir(),
+                backup_retention: BackupRetention::default(),
+                max_diff_size: default_max_diff_size(),
+                max_content_size: default_max_content_size(),
+                secrets: SecretSettings::default(),
+                git: GitStoreSettings::default(),
+                drift: DriftSettings::default(),
+                target_policy: TargetPolicySettings::default(),
             },
+            profile: ProfileConfig::default(),
+            command_policy: CommandPolicyConfig::default(),
         }
     }
 }
@@ -68,6 +279,7 @@ pub fn load_config() -> Result<Config> {
     Ok(config)
 }
 
+#[allow(dead_code)]
 pub fn save_config(config: &Config) -> Result<()> {
     let config_path = "config.toml";
     let toml_string = toml::to_string_pretty(config)?;
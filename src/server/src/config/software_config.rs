@@ -2,9 +2,9 @@ use std::fs;
 use std::path::Path;
 use log::{info, error};
 use crate::models::software::{
-    Software, SoftwareCategory, SoftwareToml, SoftwareInfo, 
-    Tags, Dependencies, Paths
+    InstallerType, Software, SoftwareCategory, SoftwareToml
 };
+use crate::utils::template::TemplateContext;
 use chrono::Utc;
 
 /// 从TOML文件加载软件信息
@@ -26,14 +26,20 @@ pub fn load_software_from_toml(file_path: &Path) -> Result<Software, anyhow::Err
     
     let now = Utc::now();
     
+    // 未声明安装程序类型时根据安装命令推断
+    let installer = software_toml.software.installer
+        .unwrap_or_else(|| InstallerType::detect(&software_toml.software.install_command));
+    
     // 创建Software对象
-    let software = Software {
+    let mut software = Software {
         id: software_toml.software.id,
         name: software_toml.software.name,
         version: software_toml.software.version,
         description: software_toml.software.description,
         install_command: software_toml.software.install_command,
         uninstall_command: software_toml.software.uninstall_command,
+        hooks: software_toml.hooks,
+        installer,
         category,
         tags: software_toml.tags.tags,
         dependencies: software_toml.dependencies.dependencies,
@@ -43,9 +49,76 @@ pub fn load_software_from_toml(file_path: &Path) -> Result<Software, anyhow::Err
         updated_at: now,
     };
     
+    validate_templates(&software)?;
+    software.config_files = render_config_paths(&software)?;
+    
     Ok(software)
 }
 
+/// 构建软件的模板变量上下文，包括内置变量、用户配置档案中的变量和软件版本
+pub fn template_context(software: &Software) -> TemplateContext {
+    let mut context = TemplateContext::new();
+    context.extend(&crate::CONFIG.profile.variables);
+    context.set("version", software.version.clone());
+    context
+}
+
+/// 校验软件配置中所有模板引用的变量均已定义
+pub fn validate_templates(software: &Software) -> Result<(), anyhow::Error> {
+    let context = template_context(software);
+    // 安装路径在执行安装时才确定
+    let deferred = ["install_path"];
+    
+    let mut templates: Vec<(&str, &str)> = software.install_command
+        .parts()
+        .into_iter()
+        .map(|part| ("install_command", part))
+        .collect();
+    if let Some(command) = &software.uninstall_command {
+        templates.extend(command.parts().into_iter().map(|part| ("uninstall_command", part)));
+    }
+    let hooks = [("hooks.pre_install", &software.hooks.pre_install), ("hooks.post_install", &software.hooks.post_install)];
+    for (field, command) in hooks {
+        if let Some(command) = command {
+            templates.extend(command.parts().into_iter().map(|part| (field, part)));
+        }
+    }
+    if let Some(path) = &software.install_path {
+        templates.push(("install_path", path));
+    }
+    
+    for (field, template) in templates {
+        context
+            .validate(template, &deferred)
+            .map_err(|e| anyhow::anyhow!("软件 {} 的 {} 模板无效: {}", software.id, field, e))?;
+    }
+    
+    // 配置文件路径在加载时渲染，只能引用软件配置中的默认安装路径
+    let deferred: &[&str] = if software.install_path.is_some() { &deferred } else { &[] };
+    for path in &software.config_files {
+        context
+            .validate(path, deferred)
+            .map_err(|e| anyhow::anyhow!("软件 {} 的 config_files 模板无效: {}", software.id, e))?;
+    }
+    
+    Ok(())
+}
+
+/// 渲染配置文件路径中的模板变量，`{{install_path}}` 为软件配置中的默认安装路径
+///
+/// 路径中的环境变量保持原样，在使用路径时再展开
+pub fn render_config_paths(software: &Software) -> Result<Vec<String>, anyhow::Error> {
+    let mut context = template_context(software);
+    if let Some(path) = &software.install_path {
+        context.set("install_path", context.render(path)?);
+    }
+    software
+        .config_files
+        .iter()
+        .map(|path| context.render(path))
+        .collect()
+}
+
 /// 从配置目录加载所有软件信息
 pub fn load_all_software() -> Vec<Software> {
     let config_dir = Path::new("config/software");
@@ -58,18 +131,16 @@ pub fn load_all_software() -> Vec<Software> {
     
     match fs::read_dir(config_dir) {
         Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() && path.extension().map_or(false, |ext| ext == "toml") {
-                        info!("加载软件配置文件: {:?}", path);
-                        match load_software_from_toml(&path) {
-                            Ok(software) => {
-                                software_list.push(software);
-                            },
-                            Err(err) => {
-                                error!("加载软件配置文件失败 {:?}: {}", path, err);
-                            }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "toml") {
+                    info!("加载软件配置文件: {:?}", path);
+                    match load_software_from_toml(&path) {
+                        Ok(software) => {
+                            software_list.push(software);
+                        },
+                        Err(err) => {
+                            error!("加载软件配置文件失败 {:?}: {}", path, err);
                         }
                     }
                 }
@@ -1,8 +1,9 @@
 #[cfg(test)]
-mod tests {
-    use super::*;
+mod config_tests {
+    use super::super::*;
     
     #[test]
+    #[allow(clippy::len_zero)]
     fn test_default_config() {
         let config = Config::default();
         
@@ -17,6 +18,7 @@ mod tests {
     }
     
     #[test]
+    #[allow(unused_variables)]
     fn test_save_and_load_config() {
         // 创建一个临时配置文件路径
         let temp_path = std::env::temp_dir().join("test_config.toml");
@@ -1,75 +1,273 @@
+use actix_multipart::Multipart;
 use actix_web::{web, HttpResponse, Responder, get, post, put, delete};
+use futures::StreamExt;
 use crate::models::config_file::{ConfigFile, ConfigFileRequest};
+use crate::services::archive::{ArchiveFormat, ConflictPolicy, InvalidArchiveError};
+use crate::services::config_files::{self, ConfigFileService, ConfigFileServiceImpl, NotApplicableError};
+use crate::services::content::{ContentError, ContentUpdate, UploadedFile};
+use crate::services::deployment::TemplateRenderError;
+use crate::services::target_policy::{UnsafeSourceError, UnsafeTargetError};
+use crate::utils::path::UndefinedVariableError;
+use crate::utils::syntax::InvalidFormatError;
 use log::info;
+use serde::Deserialize;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
+    // 固定路径需要在 /config-files/{id} 之前注册
     cfg.service(get_all_config_files)
+        .service(get_config_drift)
+        .service(get_config_audit_log)
+        .service(export_config_files)
+        .service(import_config_files)
         .service(get_config_file_by_id)
         .service(create_config_file)
         .service(update_config_file)
         .service(delete_config_file)
-        .service(deploy_config_file);
+        .service(deploy_config_file)
+        .service(undeploy_config_file)
+        .service(diff_config_file)
+        .service(get_config_file_content)
+        .service(put_config_file_content)
+        .service(upload_config_file_content)
+        .service(capture_config_file)
+        .service(get_config_file_backups)
+        .service(restore_config_file_backup)
+        .service(encrypt_config_file)
+        .service(get_config_file_history)
+        .service(revert_config_file)
+        .service(push_config_store)
+        .service(pull_config_store);
+}
+
+fn config_file_service() -> ConfigFileServiceImpl {
+    ConfigFileServiceImpl::new(&crate::CONFIG.config_files)
+}
+
+fn not_found(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({
+        "error": format!("配置文件不存在: {}", id)
+    }))
+}
+
+fn internal_error(action: &str, e: anyhow::Error) -> HttpResponse {
+    log::error!("{}失败: {}", action, e);
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": format!("{}失败: {}", action, e)
+    }))
+}
+
+// 目标路径违反部署限制或源路径不在托管目录中时返回 403，其他错误按服务器错误处理
+fn target_or_internal_error(action: &str, e: anyhow::Error) -> HttpResponse {
+    if let Some(unsafe_source) = e.downcast_ref::<UnsafeSourceError>() {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("{}失败: {}", action, e),
+            "source": unsafe_source.path
+        }));
+    }
+    let Some(unsafe_target) = e.downcast_ref::<UnsafeTargetError>() else {
+        return internal_error(action, e);
+    };
+    HttpResponse::Forbidden().json(serde_json::json!({
+        "error": format!("{}失败: {}", action, e),
+        "target": unsafe_target.target,
+        "reason": unsafe_target.reason
+    }))
+}
+
+// 模板变量或目标路径中的环境变量未定义时返回 422 和缺失变量列表，源文件语法错误时返回 422 和错误位置，
+// 当前系统不适用时返回 409，目标路径违反部署限制时返回 403，其他错误按服务器错误处理
+fn render_or_internal_error(action: &str, e: anyhow::Error) -> HttpResponse {
+    if let Some(not_applicable) = e.downcast_ref::<NotApplicableError>() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "status": "not_applicable",
+            "error": format!("{}失败: {}", action, e),
+            "os": not_applicable.os
+        }));
+    }
+    if let Some(invalid) = e.downcast_ref::<InvalidFormatError>() {
+        return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+            "error": format!("{}失败: {}", action, e),
+            "format_errors": invalid.errors
+        }));
+    }
+    let missing = match (e.downcast_ref::<TemplateRenderError>(), e.downcast_ref::<UndefinedVariableError>()) {
+        (Some(render_error), _) => &render_error.missing,
+        (_, Some(undefined)) => &undefined.missing,
+        _ => return target_or_internal_error(action, e),
+    };
+    HttpResponse::UnprocessableEntity().json(serde_json::json!({
+        "error": format!("{}失败: {}", action, e),
+        "missing_variables": missing
+    }))
+}
+
+// 源文件内容超过大小上限时返回 413，读取后被修改时返回 409 和当前哈希，
+// 文件不存在时返回 404，路径无效时返回 400，源路径不在托管目录中时返回 403
+fn content_or_internal_error(action: &str, e: anyhow::Error) -> HttpResponse {
+    let Some(error) = e.downcast_ref::<ContentError>() else {
+        return target_or_internal_error(action, e);
+    };
+    let message = format!("{}失败: {}", action, e);
+    match error {
+        ContentError::TooLarge { .. } => HttpResponse::PayloadTooLarge().json(serde_json::json!({ "error": message })),
+        ContentError::Conflict { current } => HttpResponse::Conflict().json(serde_json::json!({
+            "error": message,
+            "current_sha256": current
+        })),
+        ContentError::Missing(_) => HttpResponse::NotFound().json(serde_json::json!({ "error": message })),
+        ContentError::Invalid(_) => HttpResponse::BadRequest().json(serde_json::json!({ "error": message })),
+    }
+}
+
+// 导入归档的大小上限
+const MAX_IMPORT_SIZE: usize = 64 * 1024 * 1024;
+
+fn bad_request(e: anyhow::Error) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": e.to_string()
+    }))
 }
 
 #[get("/config-files")]
 async fn get_all_config_files() -> impl Responder {
     info!("获取所有配置文件");
-    
-    // 这里将来会从数据库或文件中获取配置文件列表
-    // 目前返回一些示例数据
-    let config_files = vec![
-        ConfigFile::new(
-            "VSCode 设置".to_string(),
-            "./configs/vscode/settings.json".to_string(),
-            "%APPDATA%/Code/User/settings.json".to_string(),
-            Some("vscode-id".to_string()),
-            false,
-        ),
-        ConfigFile::new(
-            "Git 配置".to_string(),
-            "./configs/git/.gitconfig".to_string(),
-            "%USERPROFILE%/.gitconfig".to_string(),
-            Some("git-id".to_string()),
-            false,
-        ),
-    ];
-    
-    HttpResponse::Ok().json(config_files)
+
+    match config_file_service().get_all_config_files().await {
+        Ok(config_files) => HttpResponse::Ok().json(config_files),
+        Err(e) => internal_error("获取配置文件列表", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DriftQuery {
+    // 为 true 时重新扫描，不使用定期检测缓存的结果
+    #[serde(default)]
+    refresh: bool,
+}
+
+#[get("/config-files/drift")]
+async fn get_config_drift(query: web::Query<DriftQuery>) -> impl Responder {
+    info!("检测配置文件变化，重新扫描: {}", query.refresh);
+
+    match config_file_service().check_drift(query.refresh).await {
+        Ok(scan) => HttpResponse::Ok().json(scan),
+        Err(e) => internal_error("检测配置文件变化", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    // 只返回指定配置文件的记录
+    #[serde(default)]
+    config_id: Option<String>,
+}
+
+#[get("/config-files/audit")]
+async fn get_config_audit_log(query: web::Query<AuditQuery>) -> impl Responder {
+    info!("获取审计日志，配置文件: {:?}", query.config_id);
+
+    match config_file_service().get_audit_log(query.config_id.as_deref()).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => internal_error("获取审计日志", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ArchiveFormat,
+    // 以逗号分隔的记录 ID，为空时导出全部
+    ids: Option<String>,
+}
+
+#[get("/config-files/export")]
+async fn export_config_files(query: web::Query<ExportQuery>) -> impl Responder {
+    info!("导出配置文件，格式: {}, ID: {:?}", query.format.extension(), query.ids);
+
+    let ids: Option<Vec<String>> = query
+        .ids
+        .as_deref()
+        .map(|ids| ids.split(',').map(str::trim).filter(|id| !id.is_empty()).map(String::from).collect());
+    match config_file_service().export_config_files(ids.as_deref(), query.format).await {
+        Ok(Some(archive)) => {
+            let file_name = format!(
+                "config-files-{}.{}",
+                chrono::Local::now().format("%Y%m%d_%H%M%S"),
+                query.format.extension()
+            );
+            HttpResponse::Ok()
+                .content_type(query.format.content_type())
+                .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", file_name)))
+                .body(archive)
+        }
+        Ok(None) => not_found(query.ids.as_deref().unwrap_or_default()),
+        Err(e) => target_or_internal_error("导出配置文件", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    conflict: ConflictPolicy,
+    // 为 true 时只返回导入会带来的变化，不写入文件
+    #[serde(default)]
+    dry_run: bool,
+}
+
+// 请求体为导出的 tar.zst 或 zip 归档
+#[post("/config-files/import")]
+async fn import_config_files(query: web::Query<ImportQuery>, mut payload: web::Payload) -> impl Responder {
+    info!("导入配置文件，冲突处理: {:?}, 预演: {}", query.conflict, query.dry_run);
+
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return bad_request(anyhow::anyhow!("读取请求内容失败: {}", e)),
+        };
+        if body.len() + chunk.len() > MAX_IMPORT_SIZE {
+            return HttpResponse::PayloadTooLarge().json(serde_json::json!({
+                "error": format!("归档超过 {} MB", MAX_IMPORT_SIZE / 1024 / 1024)
+            }));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    match config_file_service().import_config_files(&body, query.conflict, query.dry_run).await {
+        Ok(report) => HttpResponse::Ok().json(serde_json::json!({
+            "status": if report.dry_run { "preview" } else { "completed" },
+            "entries": report.entries
+        })),
+        Err(e) if e.is::<InvalidArchiveError>() => bad_request(e),
+        Err(e) => internal_error("导入配置文件", e),
+    }
 }
 
 #[get("/config-files/{id}")]
 async fn get_config_file_by_id(path: web::Path<String>) -> impl Responder {
     let id = path.into_inner();
     info!("获取配置文件详情，ID: {}", id);
-    
-    // 这里将来会从数据库或文件中获取特定配置文件
-    // 目前返回一个示例数据
-    let config_file = ConfigFile::new(
-        "VSCode 设置".to_string(),
-        "./configs/vscode/settings.json".to_string(),
-        "%APPDATA%/Code/User/settings.json".to_string(),
-        Some("vscode-id".to_string()),
-        false,
-    );
-    
-    HttpResponse::Ok().json(config_file)
+
+    match config_file_service().get_config_file_by_id(&id).await {
+        Ok(Some(config_file)) => HttpResponse::Ok().json(config_file),
+        Ok(None) => not_found(&id),
+        Err(e) => internal_error("获取配置文件", e),
+    }
 }
 
 #[post("/config-files")]
 async fn create_config_file(request: web::Json<ConfigFileRequest>) -> impl Responder {
     info!("创建配置文件: {:?}", request);
-    
-    // 这里将来会实际创建配置文件
-    // 目前返回一个示例响应
-    let config_file = ConfigFile::new(
-        request.name.clone(),
-        request.source_path.clone(),
-        request.target_path.clone(),
-        request.software_id.clone(),
-        request.is_directory,
-    );
-    
-    HttpResponse::Created().json(config_file)
+
+    let config_file = ConfigFile::from_request(request.into_inner());
+    if let Err(e) = config_files::validate_config_file(&config_file) {
+        return bad_request(e);
+    }
+
+    match config_file_service().create_config_file(config_file).await {
+        Ok(config_file) => HttpResponse::Created().json(config_file),
+        Err(e) => target_or_internal_error("创建配置文件", e),
+    }
 }
 
 #[put("/config-files/{id}")]
@@ -79,41 +277,324 @@ async fn update_config_file(
 ) -> impl Responder {
     let id = path.into_inner();
     info!("更新配置文件，ID: {}, 数据: {:?}", id, request);
-    
-    // 这里将来会实际更新配置文件
-    // 目前返回一个示例响应
-    let mut config_file = ConfigFile::new(
-        request.name.clone(),
-        request.source_path.clone(),
-        request.target_path.clone(),
-        request.software_id.clone(),
-        request.is_directory,
-    );
-    config_file.id = id;
-    
-    HttpResponse::Ok().json(config_file)
+
+    let service = config_file_service();
+    let mut config_file = match service.get_config_file_by_id(&id).await {
+        Ok(Some(config_file)) => config_file,
+        Ok(None) => return not_found(&id),
+        Err(e) => return internal_error("获取配置文件", e),
+    };
+
+    config_file.apply_request(request.into_inner());
+    if let Err(e) = config_files::validate_config_file(&config_file) {
+        return bad_request(e);
+    }
+
+    match service.update_config_file(&id, config_file).await {
+        Ok(Some(config_file)) => HttpResponse::Ok().json(config_file),
+        Ok(None) => not_found(&id),
+        Err(e) => target_or_internal_error("更新配置文件", e),
+    }
 }
 
 #[delete("/config-files/{id}")]
 async fn delete_config_file(path: web::Path<String>) -> impl Responder {
     let id = path.into_inner();
     info!("删除配置文件，ID: {}", id);
-    
-    // 这里将来会实际删除配置文件
-    // 目前返回一个示例响应
-    HttpResponse::NoContent().finish()
+
+    match config_file_service().delete_config_file(&id).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => not_found(&id),
+        Err(e) => internal_error("删除配置文件", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DeployQuery {
+    // 为 true 时只返回部署会带来的变化，不写入文件
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[post("/config-files/{id}/deploy")]
-async fn deploy_config_file(path: web::Path<String>) -> impl Responder {
+async fn deploy_config_file(path: web::Path<String>, query: web::Query<DeployQuery>) -> impl Responder {
     let id = path.into_inner();
-    info!("部署配置文件，ID: {}", id);
-    
-    // 这里将来会实际部署配置文件
-    // 目前返回一个示例响应
-    HttpResponse::Accepted().json(serde_json::json!({
-        "task_id": uuid::Uuid::new_v4().to_string(),
-        "status": "pending",
-        "message": "配置文件部署任务已创建"
-    }))
+    info!("部署配置文件，ID: {}, 预演: {}", id, query.dry_run);
+
+    let service = config_file_service();
+    if query.dry_run {
+        return match service.diff_config_file(&id, None).await {
+            Ok(Some(report)) => HttpResponse::Ok().json(serde_json::json!({
+                "status": "preview",
+                "diff": report
+            })),
+            Ok(None) => not_found(&id),
+            Err(e) => render_or_internal_error("预演部署", e),
+        };
+    }
+
+    // 部署在后台任务中执行，通过 GET /api/tasks/{task_id} 查询结果
+    match service.start_deploy_task(&id).await {
+        Ok(Some(task_id)) => HttpResponse::Accepted().json(serde_json::json!({
+            "task_id": task_id,
+            "status": "pending",
+            "message": "配置文件部署任务已创建"
+        })),
+        Ok(None) => not_found(&id),
+        Err(e) => internal_error("创建部署任务", e),
+    }
+}
+
+#[post("/config-files/{id}/undeploy")]
+async fn undeploy_config_file(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("撤销配置文件部署，ID: {}", id);
+
+    match config_file_service().undeploy_config_file(&id).await {
+        Ok(Some(summary)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "completed",
+            "message": "配置文件部署已撤销",
+            "summary": summary
+        })),
+        Ok(None) => not_found(&id),
+        Err(e) if e.is::<UnsafeTargetError>() => target_or_internal_error("撤销部署", e),
+        Err(e) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("撤销部署失败: {}", e)
+        })),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffQuery {
+    // 目录中某个文件的相对路径
+    path: Option<String>,
+}
+
+#[get("/config-files/{id}/diff")]
+async fn diff_config_file(path: web::Path<String>, query: web::Query<DiffQuery>) -> impl Responder {
+    let id = path.into_inner();
+    info!("获取配置文件差异，ID: {}", id);
+
+    match config_file_service().diff_config_file(&id, query.path.as_deref()).await {
+        Ok(Some(report)) => HttpResponse::Ok().json(report),
+        Ok(None) => not_found(&id),
+        Err(e) => render_or_internal_error("获取配置文件差异", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentQuery {
+    // 目录中文件的相对路径，单个文件可以省略
+    path: Option<String>,
+}
+
+#[get("/config-files/{id}/content")]
+async fn get_config_file_content(path: web::Path<String>, query: web::Query<ContentQuery>) -> impl Responder {
+    let id = path.into_inner();
+    info!("读取配置文件源文件内容，ID: {}, 路径: {:?}", id, query.path);
+
+    match config_file_service().get_content(&id, query.path.as_deref()).await {
+        Ok(Some(content)) => HttpResponse::Ok().json(content),
+        Ok(None) => not_found(&id),
+        Err(e) => content_or_internal_error("读取源文件", e),
+    }
+}
+
+#[put("/config-files/{id}/content")]
+async fn put_config_file_content(path: web::Path<String>, request: web::Json<ContentUpdate>) -> impl Responder {
+    let id = path.into_inner();
+    info!("写入配置文件源文件内容，ID: {}, 路径: {:?}", id, request.path);
+
+    match config_file_service().put_content(&id, request.into_inner()).await {
+        Ok(Some(content)) => HttpResponse::Ok().json(content),
+        Ok(None) => not_found(&id),
+        Err(e) => content_or_internal_error("写入源文件", e),
+    }
+}
+
+// 表单中的文件字段以文件名作为目录中的相对路径；只上传一个文件时可以用 `path` 字段指定路径，
+// 用 `sha256` 字段检查并发修改
+#[post("/config-files/{id}/content")]
+async fn upload_config_file_content(path: web::Path<String>, mut payload: Multipart) -> impl Responder {
+    let id = path.into_inner();
+    info!("上传配置文件源文件，ID: {}", id);
+
+    let limit = crate::CONFIG.config_files.max_content_size;
+    let mut files = Vec::new();
+    let mut target_path = None;
+    let mut expected = None;
+    while let Some(field) = payload.next().await {
+        let mut field = match field {
+            Ok(field) => field,
+            Err(e) => return bad_request(anyhow::anyhow!("读取上传内容失败: {}", e)),
+        };
+        let name = field.name().unwrap_or_default().to_string();
+        let file_name = field
+            .content_disposition()
+            .and_then(|disposition| disposition.get_filename())
+            .map(str::to_string);
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => return bad_request(anyhow::anyhow!("读取上传内容失败: {}", e)),
+            };
+            let size = (data.len() + chunk.len()) as u64;
+            if size > limit {
+                return content_or_internal_error("上传源文件", ContentError::TooLarge { size, limit }.into());
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        let text = || String::from_utf8_lossy(&data).trim().to_string();
+        match (name.as_str(), file_name) {
+            ("path", _) => target_path = Some(text()),
+            ("sha256", _) => expected = Some(text()),
+            (_, Some(file_name)) => files.push(UploadedFile { path: file_name, content: data }),
+            _ => {}
+        }
+    }
+    if let Some(target_path) = target_path {
+        match files.as_mut_slice() {
+            [file] => file.path = target_path,
+            _ => return bad_request(anyhow::anyhow!("指定 path 时只能上传一个文件")),
+        }
+    }
+
+    match config_file_service().upload_content(&id, files, expected).await {
+        Ok(Some(contents)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "completed",
+            "files": contents
+        })),
+        Ok(None) => not_found(&id),
+        Err(e) => content_or_internal_error("上传源文件", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CaptureQuery {
+    // 为 false 时只返回差异预览
+    #[serde(default)]
+    confirm: bool,
+}
+
+#[post("/config-files/{id}/capture")]
+async fn capture_config_file(path: web::Path<String>, query: web::Query<CaptureQuery>) -> impl Responder {
+    let id = path.into_inner();
+    info!("捕获配置文件，ID: {}, 确认: {}", id, query.confirm);
+
+    match config_file_service().capture_config_file(&id, query.confirm).await {
+        Ok(Some(result)) => {
+            let status = if result.summary.is_some() { "completed" } else { "preview" };
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": status,
+                "changes": result.changes,
+                "summary": result.summary
+            }))
+        }
+        Ok(None) => not_found(&id),
+        Err(e) => render_or_internal_error("捕获配置文件", e),
+    }
+}
+
+#[get("/config-files/{id}/backups")]
+async fn get_config_file_backups(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("获取配置文件备份列表，ID: {}", id);
+
+    match config_file_service().get_backups(&id).await {
+        Ok(Some(backups)) => HttpResponse::Ok().json(backups),
+        Ok(None) => not_found(&id),
+        Err(e) => internal_error("获取备份列表", e),
+    }
+}
+
+#[post("/config-files/{id}/backups/{backup_id}/restore")]
+async fn restore_config_file_backup(path: web::Path<(String, String)>) -> impl Responder {
+    let (id, backup_id) = path.into_inner();
+    info!("恢复配置文件备份，ID: {}, 备份ID: {}", id, backup_id);
+
+    match config_file_service().restore_backup(&id, &backup_id).await {
+        Ok(Some(backup)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "completed",
+            "message": "备份已恢复",
+            "backup": backup
+        })),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("配置文件或备份不存在: {}/{}", id, backup_id)
+        })),
+        Err(e) => target_or_internal_error("恢复备份", e),
+    }
+}
+
+#[post("/config-files/{id}/encrypt")]
+async fn encrypt_config_file(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("加密配置文件，ID: {}", id);
+
+    match config_file_service().encrypt_config_file(&id).await {
+        Ok(Some(result)) => HttpResponse::Ok().json(result),
+        Ok(None) => not_found(&id),
+        Err(e) => target_or_internal_error("加密配置文件", e),
+    }
+}
+
+#[get("/config-files/{id}/history")]
+async fn get_config_file_history(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("获取配置文件历史版本，ID: {}", id);
+
+    match config_file_service().get_history(&id).await {
+        Ok(Some(revisions)) => HttpResponse::Ok().json(revisions),
+        Ok(None) => not_found(&id),
+        Err(e) => internal_error("获取历史版本", e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RevertRequest {
+    // 要恢复到的版本，可以是完整或缩写的提交号
+    revision: String,
+}
+
+#[post("/config-files/{id}/revert")]
+async fn revert_config_file(path: web::Path<String>, request: web::Json<RevertRequest>) -> impl Responder {
+    let id = path.into_inner();
+    info!("还原配置文件，ID: {}, 版本: {}", id, request.revision);
+
+    match config_file_service().revert_config_file(&id, &request.revision).await {
+        Ok(Some(revision)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "completed",
+            "message": "配置文件已还原",
+            "revision": revision
+        })),
+        Ok(None) => not_found(&id),
+        Err(e) => internal_error("还原配置文件", e),
+    }
+}
+
+#[post("/config-store/push")]
+async fn push_config_store() -> impl Responder {
+    info!("推送配置仓库");
+
+    match config_file_service().push_config_store().await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "completed",
+            "message": "配置仓库已推送"
+        })),
+        Err(e) => internal_error("推送配置仓库", e),
+    }
+}
+
+#[post("/config-store/pull")]
+async fn pull_config_store() -> impl Responder {
+    info!("拉取配置仓库");
+
+    match config_file_service().pull_config_store().await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "completed",
+            "message": "配置仓库已拉取"
+        })),
+        Err(e) => internal_error("拉取配置仓库", e),
+    }
 }
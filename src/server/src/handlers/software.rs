@@ -1,4 +1,6 @@
 use crate::models::software::{Software, SoftwareInstallRequest};
+use crate::services::config_files::{ConfigFileService, ConfigFileServiceImpl};
+use crate::services::drift;
 use crate::services::software_service::{SoftwareService, SoftwareServiceImpl};
 use actix_web::{HttpResponse, Responder, get, post, web};
 use log::info;
@@ -47,30 +49,77 @@ async fn get_software_by_id(path: web::Path<String>) -> impl Responder {
     let id = path.into_inner();
     info!("获取软件详情，ID: {}", id);
 
-    // 这里将来会从数据库或文件中获取特定软件
-    // 目前返回一个示例数据
-    let software = Software::new(
-        "Visual Studio Code".to_string(),
-        "1.85.0".to_string(),
-        Some("轻量级代码编辑器".to_string()),
-        "winget install Microsoft.VisualStudioCode".to_string(),
-        crate::models::software::SoftwareCategory::Development,
-    );
-
-    HttpResponse::Ok().json(software)
+    let software = match SoftwareServiceImpl::new().get_software_by_id(&id).await {
+        Ok(Some(software)) => software,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": format!("软件不存在: {}", id)
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("获取软件信息失败: {}", e)
+            }));
+        }
+    };
+
+    // 附带关联的配置文件及其部署状态
+    let config_service = ConfigFileServiceImpl::new(&crate::CONFIG.config_files);
+    let config_files = match config_service.get_linked_config_files(&id).await {
+        Ok(config_files) => config_files.iter().map(drift::check).collect::<Vec<_>>(),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("获取关联配置文件失败: {}", e)
+            }));
+        }
+    };
+
+    let mut detail = serde_json::to_value(&software).unwrap_or_default();
+    detail["linked_config_files"] = serde_json::json!(config_files);
+    HttpResponse::Ok().json(detail)
 }
 
 #[post("/install")]
 async fn install_software(request: web::Json<SoftwareInstallRequest>) -> impl Responder {
     info!("安装软件请求: {:?}", request);
 
-    // 这里将来会实际执行软件安装
-    // 目前返回一个示例响应
-    HttpResponse::Accepted().json(serde_json::json!({
-        "task_id": uuid::Uuid::new_v4().to_string(),
-        "status": "pending",
-        "message": "软件安装任务已创建"
-    }))
+    let software_service = SoftwareServiceImpl::new();
+
+    // 查找所有待安装的软件
+    let mut software_list = Vec::new();
+    for id in &request.software_ids {
+        match software_service.get_software_by_id(id).await {
+            Ok(Some(software)) => software_list.push(software),
+            Ok(None) => {
+                return HttpResponse::NotFound().json(serde_json::json!({
+                    "error": format!("软件不存在: {}", id)
+                }));
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": format!("获取软件信息失败: {}", e)
+                }));
+            }
+        }
+    }
+
+    match software_service
+        .install_batch(software_list, request.custom_install_path.clone(), request.deploy_configs)
+        .await
+    {
+        Ok(batch) => HttpResponse::Accepted().json(serde_json::json!({
+            "batch_id": batch.id,
+            "task_ids": batch.task_ids,
+            "status": "pending",
+            "message": "软件安装任务已创建"
+        })),
+        Err(e) => {
+            log::error!("创建安装任务失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("创建安装任务失败: {}", e)
+            }))
+        }
+    }
 }
 
 #[get("/install/{task_id}")]
@@ -78,14 +127,16 @@ async fn get_install_status(path: web::Path<String>) -> impl Responder {
     let task_id = path.into_inner();
     info!("获取安装状态，任务ID: {}", task_id);
 
-    // 这里将来会查询实际的安装状态
-    // 目前返回一个示例响应
-    HttpResponse::Ok().json(serde_json::json!({
-        "task_id": task_id,
-        "status": "in_progress",
-        "progress": 0.45,
-        "message": "正在安装软件..."
-    }))
+    let software_service = SoftwareServiceImpl::new();
+    match software_service.get_install_status(&task_id).await {
+        Ok(Some(status)) => HttpResponse::Ok().json(status),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("任务不存在: {}", task_id)
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("获取安装状态失败: {}", e)
+        })),
+    }
 }
 
 #[get("/search")]
@@ -95,15 +146,9 @@ async fn search_software(
     let search_term = query.get("q").cloned().unwrap_or_default();
     info!("搜索软件: {}", search_term);
 
-    // 这里将来会实际搜索软件
-    // 目前返回一些示例数据
-    let software_list = vec![Software::new(
-        "Visual Studio Code".to_string(),
-        "1.85.0".to_string(),
-        Some("轻量级代码编辑器".to_string()),
-        "winget install Microsoft.VisualStudioCode".to_string(),
-        crate::models::software::SoftwareCategory::Development,
-    )];
-
-    HttpResponse::Ok().json(software_list)
+    let software_service = SoftwareServiceImpl::new();
+    match software_service.search_software(&search_term).await {
+        Ok(software_list) => HttpResponse::Ok().json(software_list),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
 }
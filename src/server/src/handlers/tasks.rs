@@ -0,0 +1,96 @@
+use crate::services::tasks::{TaskService, TaskServiceImpl};
+use actix_web::{HttpResponse, Responder, get, post, web};
+use log::info;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/tasks")
+            .service(get_all_tasks)
+            .service(get_batch_by_id)
+            .service(pause_batch)
+            .service(resume_batch)
+            .service(cancel_batch)
+            .service(get_task_by_id)
+            .service(cancel_task),
+    );
+}
+
+// 将服务层的结果转换为响应：None 表示不存在，Err 表示当前状态不允许该操作
+fn to_response<T: serde::Serialize>(result: anyhow::Result<Option<T>>, not_found: String) -> HttpResponse {
+    match result {
+        Ok(Some(value)) => HttpResponse::Ok().json(value),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": not_found
+        })),
+        Err(e) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": e.to_string()
+        })),
+    }
+}
+
+#[get("")]
+async fn get_all_tasks() -> impl Responder {
+    info!("获取所有任务");
+
+    let task_service = TaskServiceImpl::new();
+    match task_service.get_all_tasks().await {
+        Ok(tasks) => HttpResponse::Ok().json(tasks),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("获取任务列表失败: {}", e)
+        })),
+    }
+}
+
+#[get("/{id}")]
+async fn get_task_by_id(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("获取任务详情，ID: {}", id);
+
+    let task_service = TaskServiceImpl::new();
+    to_response(task_service.get_task_by_id(&id).await, format!("任务不存在: {}", id))
+}
+
+#[post("/{id}/cancel")]
+async fn cancel_task(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("取消任务，ID: {}", id);
+
+    let task_service = TaskServiceImpl::new();
+    to_response(task_service.cancel_task(&id).await, format!("任务不存在: {}", id))
+}
+
+#[get("/batches/{id}")]
+async fn get_batch_by_id(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("获取队列详情，ID: {}", id);
+
+    let task_service = TaskServiceImpl::new();
+    to_response(task_service.get_batch_by_id(&id).await, format!("队列不存在: {}", id))
+}
+
+#[post("/batches/{id}/pause")]
+async fn pause_batch(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("暂停队列，ID: {}", id);
+
+    let task_service = TaskServiceImpl::new();
+    to_response(task_service.pause_batch(&id).await, format!("队列不存在: {}", id))
+}
+
+#[post("/batches/{id}/resume")]
+async fn resume_batch(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("恢复队列，ID: {}", id);
+
+    let task_service = TaskServiceImpl::new();
+    to_response(task_service.resume_batch(&id).await, format!("队列不存在: {}", id))
+}
+
+#[post("/batches/{id}/cancel")]
+async fn cancel_batch(path: web::Path<String>) -> impl Responder {
+    let id = path.into_inner();
+    info!("取消队列，ID: {}", id);
+
+    let task_service = TaskServiceImpl::new();
+    to_response(task_service.cancel_batch(&id).await, format!("队列不存在: {}", id))
+}
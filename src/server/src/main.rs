@@ -1,7 +1,9 @@
+use crate::config::Config;
 use crate::models::software::Software;
 use actix_cors::Cors;
 use actix_web::{App, HttpServer, middleware};
 use crate::config::software_config::load_all_software;
+use crate::services::tasks::TaskRegistry;
 use listenfd::ListenFd;
 use log::info;
 use once_cell::sync::Lazy;
@@ -15,23 +17,32 @@ mod utils;
 
 static VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// 全局配置，启动时加载一次
+static CONFIG: Lazy<Config> = Lazy::new(|| config::load_config().expect("无法加载配置"));
+
 // 定义全局的软件列表，防止后续重复加载
 static SOFTWARES: Lazy<Mutex<Vec<Software>>> = Lazy::new(|| Mutex::new(load_all_software()));
 
+// 全局的任务注册表，记录安装任务和队列的状态
+static TASKS: Lazy<TaskRegistry> = Lazy::new(TaskRegistry::new);
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
-    info!("系统安装助手后台服务启动中...");
+    info!("系统安装助手后台服务启动中... 版本: {}", VERSION);
 
-    let config = config::load_config().expect("无法加载配置");
-    let bind_address = format!("{}:{}", config.server.host, config.server.port);
+    let bind_address = format!("{}:{}", CONFIG.server.host, CONFIG.server.port);
 
     info!("服务器监听地址: {}", bind_address);
 
     let mut server = HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
+        // 只允许配置中列出的来源跨域访问，避免任意网页读取源文件内容
+        let cors = CONFIG
+            .server
+            .allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
             .allow_any_method()
             .allow_any_header()
             .max_age(3600);
@@ -55,5 +66,8 @@ async fn main() -> std::io::Result<()> {
         }
     };
 
+    // 定期检测部署后的配置文件变化
+    services::drift::start_schedule();
+
     server.run().await
 }
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConfigFile {
@@ -8,10 +9,52 @@ pub struct ConfigFile {
     pub name: String,
     pub description: Option<String>,
     pub source_path: String,
+    // 默认的目标路径，`target_paths` 中没有当前系统的路径时使用，为空表示其他系统不适用
+    #[serde(default)]
     pub target_path: String,
+    // 按操作系统和发行版区分的目标路径
+    #[serde(default)]
+    pub target_paths: TargetPaths,
     pub software_id: Option<String>,
     pub is_directory: bool,
     pub backup_on_install: bool,
+    // 目录部署时只同步匹配 include 且不匹配 exclude 的文件，include 为空表示全部
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    // 目录部署时删除目标目录中源目录不存在的文件
+    #[serde(default)]
+    pub delete_extraneous: bool,
+    #[serde(default)]
+    pub deploy_mode: DeployMode,
+    // 为 true 时源文件作为模板，部署前使用 `{{变量}}` 渲染
+    #[serde(default)]
+    pub template: bool,
+    // 渲染模板时使用的变量，优先于配置档案中的同名变量
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    // 为 true 时源文件以加密形式保存，只在部署时于内存中解密
+    #[serde(default)]
+    pub encrypted: bool,
+    // 目标文件已存在时与源文件的合并方式
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+    // 源文件格式，未设置时按文件名识别，部署前据此校验语法
+    #[serde(default)]
+    pub format: Option<ConfigFormat>,
+    // 复制部署后目标文件的权限和所有者
+    #[serde(default)]
+    pub permissions: FilePermissions,
+    // 为 true 时允许部署到目标路径限制之外的位置，每次放行都会写入审计日志
+    #[serde(default)]
+    pub allow_unsafe_target: bool,
+    // 最近一次部署的记录，撤销链接部署时使用
+    #[serde(default)]
+    pub last_deployment: Option<DeploymentRecord>,
+    // 最近一次从目标位置捕获回源文件的记录
+    #[serde(default)]
+    pub last_capture: Option<CaptureRecord>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -21,10 +64,252 @@ pub struct ConfigFileRequest {
     pub name: String,
     pub description: Option<String>,
     pub source_path: String,
+    #[serde(default)]
     pub target_path: String,
+    #[serde(default)]
+    pub target_paths: TargetPaths,
     pub software_id: Option<String>,
     pub is_directory: bool,
     pub backup_on_install: bool,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub delete_extraneous: bool,
+    #[serde(default)]
+    pub deploy_mode: DeployMode,
+    #[serde(default)]
+    pub template: bool,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+    #[serde(default)]
+    pub format: Option<ConfigFormat>,
+    #[serde(default)]
+    pub permissions: FilePermissions,
+    #[serde(default)]
+    pub allow_unsafe_target: bool,
+}
+
+/// 按操作系统区分的目标路径，未设置的系统使用记录的 `target_path`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TargetPaths {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linux: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macos: Option<String>,
+    // Linux 发行版专用的目标路径，键为 /etc/os-release 中的 ID（如 ubuntu、arch），优先于 linux
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub distros: BTreeMap<String, String>,
+}
+
+impl TargetPaths {
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_none() && self.linux.is_none() && self.macos.is_none() && self.distros.is_empty()
+    }
+
+    /// 所有设置了的路径及其说明
+    pub fn entries(&self) -> Vec<(String, &str)> {
+        let mut entries = Vec::new();
+        for (os, path) in [("windows", &self.windows), ("linux", &self.linux), ("macos", &self.macos)] {
+            if let Some(path) = path {
+                entries.push((os.to_string(), path.as_str()));
+            }
+        }
+        for (distro, path) in &self.distros {
+            entries.push((format!("linux/{}", distro), path.as_str()));
+        }
+        entries
+    }
+}
+
+/// 部署到已存在的目标文件时的合并方式
+///
+/// `deep_merge` 和 `keys_only` 按文件格式解析后合并，保留目标文件中源文件未涉及的设置
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    // 用源文件整体替换目标文件
+    #[default]
+    Overwrite,
+    // 递归合并嵌套的对象和表
+    DeepMerge,
+    // 只替换源文件中出现的顶层键
+    KeysOnly,
+}
+
+/// 配置文件格式，用于部署前的语法校验和结构化合并
+///
+/// `json` 为严格的 JSON，`jsonc` 允许注释和尾随逗号；`text` 表示不做校验
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    Json,
+    Jsonc,
+    Toml,
+    Yaml,
+    Ini,
+    Gitconfig,
+    Text,
+}
+
+/// 目标文件的权限设置，只在 Unix 系统上生效
+///
+/// 未设置 `mode` 时保留源文件的权限，`.ssh` 目录中的文件和加密配置文件默认为 0600。
+/// `owner` 和 `group` 可以是名称或数字 ID，只在以 root 身份运行时修改
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FilePermissions {
+    // 八进制权限，如 "0644"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+/// 部署方式：复制文件，或在目标位置创建指向托管源文件的链接
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployMode {
+    #[default]
+    Copy,
+    Symlink,
+    Hardlink,
+}
+
+/// 被覆盖的文件及其备份位置
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackupEntry {
+    pub original: String,
+    pub backup: String,
+}
+
+/// 部署时单个文件的处理结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileResult {
+    // 相对于部署目标的路径
+    pub path: String,
+    pub status: FileResultStatus,
+    // 被覆盖或删除的文件的备份位置
+    #[serde(default)]
+    pub backup: Option<String>,
+    // 跳过或失败的原因
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileResultStatus {
+    // 新写入的文件
+    Written,
+    // 先备份再覆盖的文件
+    BackedUp,
+    // 从目标位置删除的文件
+    Removed,
+    // 内容相同或存在冲突而未修改的文件
+    Skipped,
+    Failed,
+}
+
+/// 备份目录中的一份备份
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackupRecord {
+    pub id: String,
+    pub config_id: String,
+    pub original_path: String,
+    pub backup_path: String,
+    pub sha256: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 审计日志中的一条记录：配置文件越过目标路径限制时记录
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: String,
+    pub config_id: String,
+    pub name: String,
+    // 执行的操作，如保存、部署、捕获
+    pub action: String,
+    // 展开环境变量后的目标路径
+    pub target_path: String,
+    // 目标路径违反的限制
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 一次部署的记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeploymentRecord {
+    pub mode: DeployMode,
+    // 展开环境变量后的目标路径
+    pub target_path: String,
+    pub deployed_at: DateTime<Utc>,
+    // 本次部署创建的链接
+    #[serde(default)]
+    pub linked: Vec<String>,
+    #[serde(default)]
+    pub backups: Vec<BackupEntry>,
+    // 部署后各文件的内容哈希，用于检测部署后发生的变化
+    #[serde(default)]
+    pub files: Vec<DeployedFile>,
+}
+
+/// 部署时记录的单个文件哈希
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeployedFile {
+    // 相对于目标目录的路径，单个文件为目标文件名
+    pub path: String,
+    // 托管源文件的哈希（加密或模板记录为源文件本身的哈希）
+    pub source_sha256: String,
+    // 部署完成后目标文件的哈希
+    pub target_sha256: String,
+}
+
+/// 加密源文件的结果
+#[derive(Debug, Serialize, Clone)]
+pub struct EncryptResult {
+    #[serde(flatten)]
+    pub config_file: ConfigFile,
+    // 例如加密之前的明文仍保留在配置仓库的历史版本中
+    pub warnings: Vec<String>,
+}
+
+/// 软件安装完成后自动部署一个关联配置文件的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkedDeployment {
+    pub config_id: String,
+    pub name: String,
+    pub status: LinkedDeployStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkedDeployStatus {
+    Deployed,
+    // 当前系统没有适用的目标路径
+    NotApplicable,
+    Failed,
+}
+
+/// 一次捕获的记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaptureRecord {
+    pub captured_at: DateTime<Utc>,
+    // 被更新的源文件，相对于源路径
+    pub files: Vec<String>,
+    // 被覆盖的源文件备份
+    #[serde(default)]
+    pub backups: Vec<BackupEntry>,
 }
 
 impl ConfigFile {
@@ -43,11 +328,84 @@ impl ConfigFile {
             description: None,
             source_path,
             target_path,
+            target_paths: TargetPaths::default(),
             software_id,
             is_directory,
             backup_on_install: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            delete_extraneous: false,
+            deploy_mode: DeployMode::Copy,
+            template: false,
+            variables: BTreeMap::new(),
+            encrypted: false,
+            merge_strategy: MergeStrategy::Overwrite,
+            format: None,
+            permissions: FilePermissions::default(),
+            allow_unsafe_target: false,
+            last_deployment: None,
+            last_capture: None,
             created_at: now,
             updated_at: now,
         }
     }
+
+    /// 根据请求创建新的配置文件记录
+    pub fn from_request(request: ConfigFileRequest) -> Self {
+        let mut config_file = ConfigFile::new(
+            request.name.clone(),
+            request.source_path.clone(),
+            request.target_path.clone(),
+            request.software_id.clone(),
+            request.is_directory,
+        );
+        config_file.apply_request(request);
+        config_file.updated_at = config_file.created_at;
+        config_file
+    }
+
+    /// 选择指定系统上的目标路径（未展开环境变量），该系统不适用时返回 None
+    ///
+    /// `distros` 为当前 Linux 发行版的 ID 及其 ID_LIKE，按顺序匹配发行版专用的路径
+    pub fn target_path_for(&self, os: &str, distros: &[String]) -> Option<&str> {
+        let paths = &self.target_paths;
+        let distro_path = (os == "linux")
+            .then(|| distros.iter().find_map(|distro| paths.distros.get(distro)))
+            .flatten();
+        let os_path = match os {
+            "windows" => paths.windows.as_ref(),
+            "linux" => paths.linux.as_ref(),
+            "macos" => paths.macos.as_ref(),
+            _ => None,
+        };
+        distro_path
+            .or(os_path)
+            .map(String::as_str)
+            .or(Some(self.target_path.as_str()))
+            .filter(|path| !path.trim().is_empty())
+    }
+
+    /// 使用请求中的字段更新记录，ID 和创建时间保持不变
+    pub fn apply_request(&mut self, request: ConfigFileRequest) {
+        self.name = request.name;
+        self.description = request.description;
+        self.source_path = request.source_path;
+        self.target_path = request.target_path;
+        self.target_paths = request.target_paths;
+        self.software_id = request.software_id;
+        self.is_directory = request.is_directory;
+        self.backup_on_install = request.backup_on_install;
+        self.include = request.include;
+        self.exclude = request.exclude;
+        self.delete_extraneous = request.delete_extraneous;
+        self.deploy_mode = request.deploy_mode;
+        self.template = request.template;
+        self.variables = request.variables;
+        self.encrypted = request.encrypted;
+        self.merge_strategy = request.merge_strategy;
+        self.format = request.format;
+        self.permissions = request.permissions;
+        self.allow_unsafe_target = request.allow_unsafe_target;
+        self.updated_at = Utc::now();
+    }
 }
@@ -1,5 +1,6 @@
 pub mod software;
 pub mod config_file;
+pub mod task;
 
 #[cfg(test)]
 mod tests;
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::models::config_file::{FileResult, LinkedDeployment};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Software {
@@ -13,9 +14,15 @@ pub struct Software {
     // 软件描述
     pub description: Option<String>,
     // 安装命令
-    pub install_command: String,
+    pub install_command: CommandSpec,
     // 软件卸载命令
-    pub uninstall_command: Option<String>,
+    pub uninstall_command: Option<CommandSpec>,
+    // 安装前后执行的命令
+    #[serde(default)]
+    pub hooks: Hooks,
+    // 安装程序类型，旧版本保存的数据中没有此字段
+    #[serde(default)]
+    pub installer: InstallerType,
     // 软件类别
     pub category: SoftwareCategory,
     // 标签
@@ -44,29 +51,151 @@ pub enum SoftwareCategory {
     Other,
 }
 
+/// 命令描述：字符串交给系统 shell 执行，数组作为参数向量直接执行程序
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CommandSpec {
+    Shell(String),
+    Argv(Vec<String>),
+}
+
+impl CommandSpec {
+    /// 命令要执行的程序
+    pub fn program(&self) -> Option<&str> {
+        match self {
+            CommandSpec::Shell(command) => command.split_whitespace().next(),
+            CommandSpec::Argv(args) => args.first().map(|arg| arg.as_str()),
+        }
+    }
+
+    /// 命令中的所有文本片段
+    pub fn parts(&self) -> Vec<&str> {
+        match self {
+            CommandSpec::Shell(command) => vec![command.as_str()],
+            CommandSpec::Argv(args) => args.iter().map(|arg| arg.as_str()).collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandSpec::Shell(command) => write!(f, "{}", command),
+            CommandSpec::Argv(args) => write!(f, "{:?}", args),
+        }
+    }
+}
+
+/// 安装程序类型，决定自定义安装路径如何传递给安装命令
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallerType {
+    // Windows 包管理器，使用 --location
+    Winget,
+    // Windows Installer，使用 INSTALLDIR=
+    Msi,
+    // Inno Setup 安装程序，使用 /DIR=
+    Inno,
+    // 压缩包，安装路径即解压根目录
+    Tarball,
+    // 其他安装程序，不支持自定义安装路径
+    #[default]
+    Other,
+}
+
+impl InstallerType {
+    /// 根据安装命令推断安装程序类型
+    ///
+    /// 只检查命令文本，安装程序文件中的标记在安装前由安装服务检查
+    pub fn detect(install_command: &CommandSpec) -> Self {
+        let command = install_command.parts().join(" ").to_lowercase();
+        let program = install_command.program().unwrap_or_default().to_lowercase();
+        let program = program.as_str();
+
+        if program == "winget" || program == "winget.exe" {
+            InstallerType::Winget
+        } else if program.starts_with("msiexec") || command.contains(".msi") {
+            InstallerType::Msi
+        } else if program == "tar" || program == "tar.exe" {
+            InstallerType::Tarball
+        } else if command.split_whitespace().any(|arg| INNO_SWITCHES.contains(&arg)) {
+            InstallerType::Inno
+        } else {
+            InstallerType::Other
+        }
+    }
+}
+
+// Inno Setup 专有的命令行开关
+const INNO_SWITCHES: [&str; 3] = ["/verysilent", "/suppressmsgboxes", "/sp-"];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SoftwareInstallRequest {
     pub software_ids: Vec<String>,
     pub custom_install_path: Option<String>,
+    // 安装成功后自动部署关联的配置文件，默认开启
+    #[serde(default = "default_deploy_configs")]
+    pub deploy_configs: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn default_deploy_configs() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SoftwareInstallStatus {
     pub id: String,
     pub software_id: String,
+    // 所属的批量队列 ID
+    pub batch_id: Option<String>,
+    // 实际使用的安装路径
+    pub install_path: Option<String>,
     pub status: InstallStatus,
     pub progress: f32,
     pub message: Option<String>,
     pub started_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
+    // 安装成功后自动部署关联配置文件的结果
+    #[serde(default)]
+    pub config_deployments: Vec<LinkedDeployment>,
+    #[serde(default)]
+    pub kind: TaskKind,
+    // 配置文件部署任务对应的配置文件 ID
+    #[serde(default)]
+    pub config_id: Option<String>,
+    // 配置文件部署任务中逐个文件的处理结果
+    #[serde(default)]
+    pub files: Vec<FileResult>,
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+/// 任务类型，安装任务和配置文件部署任务共用同一个任务注册表
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    #[default]
+    Install,
+    ConfigDeploy,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum InstallStatus {
     Pending,
     Installing,
     Completed,
     Failed,
+    Cancelled,
+}
+
+impl InstallStatus {
+    /// 任务是否已结束（完成、失败或已取消）
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            InstallStatus::Completed | InstallStatus::Failed | InstallStatus::Cancelled
+        )
+    }
 }
 
 // TOML配置文件相关结构体
@@ -76,6 +205,19 @@ pub struct SoftwareToml {
     pub tags: Tags,
     pub dependencies: Dependencies,
     pub paths: Paths,
+    #[serde(default)]
+    pub hooks: Hooks,
+}
+
+/// 安装前后执行的命令，与安装命令一样支持模板和参数向量
+///
+/// 安装前命令失败时不会执行安装命令，安装后命令失败时任务标记为失败
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_install: Option<CommandSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<CommandSpec>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -84,8 +226,10 @@ pub struct SoftwareInfo {
     pub name: String,
     pub version: String,
     pub description: Option<String>,
-    pub install_command: String,
-    pub uninstall_command: Option<String>,
+    pub install_command: CommandSpec,
+    pub uninstall_command: Option<CommandSpec>,
+    // 未指定时根据安装命令推断
+    pub installer: Option<InstallerType>,
     pub category: String,
 }
 
@@ -114,6 +258,8 @@ impl Software {
         category: SoftwareCategory,
     ) -> Self {
         let now = Utc::now();
+        let install_command = CommandSpec::Shell(install_command);
+        let installer = InstallerType::detect(&install_command);
         
         Software {
             id: Uuid::new_v4().to_string(),
@@ -122,6 +268,8 @@ impl Software {
             description,
             install_command,
             uninstall_command: None,
+            hooks: Hooks::default(),
+            installer,
             category,
             tags: Vec::new(),
             dependencies: Vec::new(),
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+/// 批量任务队列，队列中的任务按顺序依次执行
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskBatch {
+    pub id: String,
+    pub task_ids: Vec<String>,
+    pub state: BatchState,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum BatchState {
+    Running,
+    Paused,
+    Completed,
+    Cancelled,
+}
+
+impl BatchState {
+    /// 队列是否已结束
+    pub fn is_finished(&self) -> bool {
+        matches!(self, BatchState::Completed | BatchState::Cancelled)
+    }
+}
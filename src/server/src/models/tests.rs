@@ -3,6 +3,7 @@ mod software_tests {
     use crate::models::software::*;
     
     #[test]
+    #[allow(clippy::len_zero)]
     fn test_software_new() {
         let name = "Test Software".to_string();
         let version = "1.0.0".to_string();
@@ -22,7 +23,7 @@ mod software_tests {
         assert_eq!(software.name, name);
         assert_eq!(software.version, version);
         assert_eq!(software.description, description);
-        assert_eq!(software.install_command, install_command);
+        assert_eq!(software.install_command, CommandSpec::Shell(install_command));
         assert_eq!(software.category, category);
         assert!(software.id.len() > 0); // UUID 不为空
         assert!(software.tags.is_empty());
@@ -36,6 +37,22 @@ mod software_tests {
         assert_eq!(software.created_at, software.updated_at);
     }
     
+    #[test]
+    fn test_software_without_installer_field() {
+        // 旧版本保存的数据中没有 installer 字段
+        let software = Software::new(
+            "Test Software".to_string(),
+            "1.0.0".to_string(),
+            None,
+            "winget install TestSoftware".to_string(),
+            SoftwareCategory::Development,
+        );
+        let mut value = serde_json::to_value(&software).unwrap();
+        value.as_object_mut().unwrap().remove("installer");
+        let loaded: Software = serde_json::from_value(value).unwrap();
+        assert_eq!(loaded.installer, InstallerType::Other);
+    }
+
     #[test]
     fn test_software_category_serialization() {
         // 测试 SoftwareCategory 的序列化和反序列化
@@ -65,6 +82,7 @@ mod software_tests {
             InstallStatus::Installing,
             InstallStatus::Completed,
             InstallStatus::Failed,
+            InstallStatus::Cancelled,
         ];
         
         for status in statuses {
@@ -73,6 +91,15 @@ mod software_tests {
             assert_eq!(status, deserialized);
         }
     }
+    
+    #[test]
+    fn test_install_status_is_finished() {
+        assert!(!InstallStatus::Pending.is_finished());
+        assert!(!InstallStatus::Installing.is_finished());
+        assert!(InstallStatus::Completed.is_finished());
+        assert!(InstallStatus::Failed.is_finished());
+        assert!(InstallStatus::Cancelled.is_finished());
+    }
 }
 
 #[cfg(test)]
@@ -80,6 +107,7 @@ mod config_file_tests {
     use crate::models::config_file::*;
     
     #[test]
+    #[allow(clippy::len_zero)]
     fn test_config_file_new() {
         let name = "Test Config".to_string();
         let source_path = "./test/source.json".to_string();
@@ -110,4 +138,31 @@ mod config_file_tests {
         assert!(config_file.updated_at <= chrono::Utc::now());
         assert_eq!(config_file.created_at, config_file.updated_at);
     }
+
+    #[test]
+    fn test_target_path_for_platform() {
+        let mut config_file = ConfigFile::new(
+            "Code".to_string(),
+            "./configs/code/settings.json".to_string(),
+            "%APPDATA%/Code/User/settings.json".to_string(),
+            None,
+            false,
+        );
+        config_file.target_paths.linux = Some("~/.config/Code/User/settings.json".to_string());
+        config_file.target_paths.distros.insert("debian".to_string(), "~/.config/Code - OSS/User/settings.json".to_string());
+
+        let ubuntu = vec!["ubuntu".to_string(), "debian".to_string()];
+        let arch = vec!["arch".to_string()];
+        assert_eq!(config_file.target_path_for("windows", &[]), Some("%APPDATA%/Code/User/settings.json"));
+        assert_eq!(config_file.target_path_for("linux", &arch), Some("~/.config/Code/User/settings.json"));
+        // 发行版按 ID_LIKE 匹配，且优先于 linux
+        assert_eq!(config_file.target_path_for("linux", &ubuntu), Some("~/.config/Code - OSS/User/settings.json"));
+        // 发行版路径只用于 Linux
+        assert_eq!(config_file.target_path_for("macos", &ubuntu), Some("%APPDATA%/Code/User/settings.json"));
+
+        // 默认目标路径为空时，没有专用路径的系统不适用
+        config_file.target_path = String::new();
+        assert_eq!(config_file.target_path_for("macos", &[]), None);
+        assert_eq!(config_file.target_path_for("linux", &[]), Some("~/.config/Code/User/settings.json"));
+    }
 }
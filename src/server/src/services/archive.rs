@@ -0,0 +1,428 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::models::config_file::ConfigFile;
+use crate::services::config_files::validate_config_file;
+use crate::services::deployment::{self, MemoryFile, PathFilter};
+use crate::utils::diff::FileDiff;
+use crate::utils::path;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const SOURCES_DIR: &str = "sources";
+const MANIFEST_VERSION: u32 = 1;
+
+// 解压后的内容上限，防止压缩炸弹耗尽内存
+const MAX_UNPACKED_SIZE: u64 = 256 * 1024 * 1024;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// 导出归档的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "tar.zst")]
+    TarZst,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarZst => "tar.zst",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarZst => "application/zstd",
+            ArchiveFormat::Zip => "application/zip",
+        }
+    }
+}
+
+/// 归档中的清单，记录导出的配置文件元数据
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub config_files: Vec<ConfigFile>,
+}
+
+/// 归档无法读取或内容不符合格式
+#[derive(Debug, thiserror::Error)]
+#[error("无效的归档: {message}")]
+pub struct InvalidArchiveError {
+    pub message: String,
+}
+
+/// 导入时与现有记录（ID 或名称相同）冲突的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    // 保留现有记录，不导入
+    #[default]
+    Skip,
+    // 以归档中的内容覆盖现有记录和源文件
+    Overwrite,
+    // 以新的 ID 和名称另外导入
+    Rename,
+}
+
+/// 单条记录的导入动作
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Create,
+    Skip,
+    Overwrite,
+    Rename,
+    // 记录无效或写入失败
+    Failed,
+}
+
+/// 单条记录的导入结果，预览时表示将要执行的动作
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportEntry {
+    // 导入后的 ID 和名称，重命名时与归档中的不同
+    pub id: String,
+    pub name: String,
+    pub original_id: String,
+    pub action: ImportAction,
+    // 与之冲突的现有记录
+    pub conflict_with: Option<String>,
+    // 导入后的源路径
+    pub source_path: String,
+    // 覆盖时源文件的变化，新建时列出所有新增文件
+    pub changes: Vec<FileDiff>,
+    pub error: Option<String>,
+}
+
+/// 一次导入的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub entries: Vec<ImportEntry>,
+}
+
+/// 导入计划中的一条记录：最终写入注册表的记录和源文件
+pub struct ImportPlan {
+    pub entry: ImportEntry,
+    pub config_file: ConfigFile,
+    pub files: Vec<MemoryFile>,
+}
+
+/// 将配置文件记录和源文件打包
+///
+/// 部署和捕获记录只在本机有意义，导出时清除；加密的源文件保持密文
+pub fn export(config_files: &[ConfigFile], format: ArchiveFormat) -> Result<Vec<u8>> {
+    let all = PathFilter::new(&[], &[])?;
+    let mut entries = Vec::new();
+    let mut records = Vec::with_capacity(config_files.len());
+    for config_file in config_files {
+        let source = Path::new(&config_file.source_path);
+        let files = deployment::read_files(source, config_file.is_directory, &all)
+            .map_err(|e| anyhow::anyhow!("{} ({}): {}", config_file.name, config_file.id, e))?;
+        let prefix = Path::new(SOURCES_DIR).join(&config_file.id);
+        entries.extend(files.into_iter().map(|file| MemoryFile {
+            relative: prefix.join(file.relative),
+            content: file.content,
+        }));
+
+        let mut record = config_file.clone();
+        record.last_deployment = None;
+        record.last_capture = None;
+        records.push(record);
+    }
+
+    let manifest = Manifest { version: MANIFEST_VERSION, exported_at: Utc::now(), config_files: records };
+    entries.insert(0, MemoryFile {
+        relative: PathBuf::from(MANIFEST_NAME),
+        content: serde_json::to_vec_pretty(&manifest)?,
+    });
+    match format {
+        ArchiveFormat::TarZst => write_tar_zst(&entries),
+        ArchiveFormat::Zip => write_zip(&entries),
+    }
+}
+
+fn write_tar_zst(entries: &[MemoryFile]) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0)?);
+    let mtime = Utc::now().timestamp().max(0) as u64;
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(entry.content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        builder.append_data(&mut header, &entry.relative, entry.content.as_slice())?;
+    }
+    Ok(builder.into_inner()?.finish()?)
+}
+
+fn write_zip(entries: &[MemoryFile]) -> Result<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+    for entry in entries {
+        writer.start_file(deployment::display_relative(&entry.relative), options)?;
+        writer.write_all(&entry.content)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+// 累计解压大小，超过上限时报错
+fn read_limited(reader: impl Read, size: u64, total: &mut u64) -> Result<Vec<u8>> {
+    *total += size;
+    if *total > MAX_UNPACKED_SIZE {
+        return Err(anyhow::anyhow!("归档解压后超过 {} MB", MAX_UNPACKED_SIZE / 1024 / 1024));
+    }
+    let mut content = Vec::with_capacity(size as usize);
+    reader.take(size).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// 按文件头识别格式并读取归档中的所有文件，不支持链接等特殊条目
+pub fn read_archive(data: &[u8]) -> Result<Vec<MemoryFile>> {
+    if data.starts_with(ZIP_MAGIC) {
+        read_zip(data)
+    } else if data.starts_with(ZSTD_MAGIC) {
+        read_tar_zst(data)
+    } else {
+        Err(anyhow::anyhow!("无法识别的归档格式，只支持 tar.zst 和 zip"))
+    }
+}
+
+fn read_tar_zst(data: &[u8]) -> Result<Vec<MemoryFile>> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(data)?);
+    let mut files = Vec::new();
+    let mut total = 0;
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => continue,
+            tar::EntryType::Regular => {}
+            _ => return Err(anyhow::anyhow!("归档中包含不支持的条目: {}", name)),
+        }
+        let relative = path::safe_relative(&name).map_err(|_| anyhow::anyhow!("归档中包含不安全的路径: {}", name))?;
+        let size = entry.header().size()?;
+        files.push(MemoryFile { relative, content: read_limited(entry, size, &mut total)? });
+    }
+    Ok(files)
+}
+
+fn read_zip(data: &[u8]) -> Result<Vec<MemoryFile>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+    let mut files = Vec::new();
+    let mut total = 0;
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        if file.is_symlink() {
+            return Err(anyhow::anyhow!("归档中包含不支持的条目: {}", name));
+        }
+        let relative = path::safe_relative(&name).map_err(|_| anyhow::anyhow!("归档中包含不安全的路径: {}", name))?;
+        let size = file.size();
+        files.push(MemoryFile { relative, content: read_limited(file, size, &mut total)? });
+    }
+    Ok(files)
+}
+
+/// 解析归档中的清单，并按记录 ID 分组源文件
+///
+/// 单个文件的源文件以文件名为相对路径，目录以目录内的相对路径
+pub fn unpack(files: Vec<MemoryFile>) -> Result<(Manifest, BTreeMap<String, Vec<MemoryFile>>)> {
+    let mut manifest = None;
+    let mut sources: BTreeMap<String, Vec<MemoryFile>> = BTreeMap::new();
+    for file in files {
+        if file.relative == Path::new(MANIFEST_NAME) {
+            manifest = Some(
+                serde_json::from_slice::<Manifest>(&file.content)
+                    .map_err(|e| anyhow::anyhow!("无法解析归档清单: {}", e))?,
+            );
+            continue;
+        }
+        let mut components = file.relative.components();
+        match (components.next(), components.next()) {
+            (Some(dir), Some(id)) if dir.as_os_str() == SOURCES_DIR => {
+                let id = id.as_os_str().to_string_lossy().to_string();
+                let relative: PathBuf = components.collect();
+                if !relative.as_os_str().is_empty() {
+                    sources.entry(id).or_default().push(MemoryFile { relative, content: file.content });
+                }
+            }
+            _ => return Err(anyhow::anyhow!("归档中包含未知文件: {}", file.relative.display())),
+        }
+    }
+    let manifest = manifest.ok_or_else(|| anyhow::anyhow!("归档中缺少 {}", MANIFEST_NAME))?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(anyhow::anyhow!("不支持的归档版本: {}", manifest.version));
+    }
+    Ok((manifest, sources))
+}
+
+/// 读取并解析导入的归档，错误统一以 [`InvalidArchiveError`] 报告
+pub fn load(data: &[u8]) -> Result<(Manifest, BTreeMap<String, Vec<MemoryFile>>)> {
+    read_archive(data)
+        .and_then(unpack)
+        .map_err(|e| InvalidArchiveError { message: e.to_string() }.into())
+}
+
+// 记录 ID 用作归档和导入目录中的目录名
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+// 在名称后附加序号，直到不与已有名称重复
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|index| format!("{} ({})", name, index))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_default()
+}
+
+/// 导入计划的上下文
+pub struct ImportContext<'a> {
+    pub existing: &'a [ConfigFile],
+    pub policy: ConflictPolicy,
+    // 源路径无法沿用时，导入的源文件存放在此目录下的 `{id}` 子目录中
+    pub import_dir: &'a Path,
+    pub max_diff_size: u64,
+}
+
+/// 根据冲突处理方式生成导入计划，不修改任何文件
+///
+/// 与现有记录的 ID 或名称相同视为冲突。新建的记录沿用归档中的相对源路径，
+/// 路径为绝对路径或已被占用时改为存放到导入目录
+pub fn plan_import(
+    manifest: Manifest,
+    mut sources: BTreeMap<String, Vec<MemoryFile>>,
+    context: &ImportContext,
+) -> Result<Vec<ImportPlan>> {
+    let all = PathFilter::new(&[], &[])?;
+    let mut names: HashSet<String> = context.existing.iter().map(|config_file| config_file.name.clone()).collect();
+    let mut ids: HashSet<String> = context.existing.iter().map(|config_file| config_file.id.clone()).collect();
+    let mut claimed: HashSet<String> = context.existing.iter().map(|config_file| config_file.source_path.clone()).collect();
+    let mut plans = Vec::new();
+
+    for imported in manifest.config_files {
+        let files = sources.remove(&imported.id).unwrap_or_default();
+        let mut entry = ImportEntry {
+            id: imported.id.clone(),
+            name: imported.name.clone(),
+            original_id: imported.id.clone(),
+            action: ImportAction::Create,
+            conflict_with: None,
+            source_path: imported.source_path.clone(),
+            changes: Vec::new(),
+            error: None,
+        };
+        let mut config_file = imported;
+
+        let problem = if !valid_id(&config_file.id) {
+            Some(format!("无效的记录 ID: {}", config_file.id))
+        } else if files.is_empty() {
+            Some("归档中缺少源文件".to_string())
+        } else if !config_file.is_directory && files.len() > 1 {
+            Some("单个文件的记录在归档中包含多个源文件".to_string())
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            entry.action = ImportAction::Failed;
+            entry.error = Some(problem);
+            plans.push(ImportPlan { entry, config_file, files });
+            continue;
+        }
+
+        let conflict = context
+            .existing
+            .iter()
+            .find(|existing| existing.id == config_file.id || existing.name == config_file.name);
+        entry.conflict_with = conflict.map(|existing| existing.id.clone());
+        let relocate = |id: &str, config_file: &ConfigFile| {
+            let name = Path::new(&config_file.source_path).file_name().map(PathBuf::from).unwrap_or_else(|| PathBuf::from(id));
+            context.import_dir.join(id).join(name).to_string_lossy().to_string()
+        };
+
+        let mut old_files = Vec::new();
+        match (conflict, context.policy) {
+            (Some(_), ConflictPolicy::Skip) => {
+                entry.action = ImportAction::Skip;
+                plans.push(ImportPlan { entry, config_file, files });
+                continue;
+            }
+            (Some(existing), ConflictPolicy::Overwrite) => {
+                // 沿用现有记录的 ID、源路径和本机的部署记录
+                entry.action = ImportAction::Overwrite;
+                config_file.id = existing.id.clone();
+                config_file.source_path = existing.source_path.clone();
+                config_file.created_at = existing.created_at;
+                config_file.last_deployment = existing.last_deployment.clone();
+                config_file.last_capture = existing.last_capture.clone();
+                let source = Path::new(&existing.source_path);
+                if source.exists() && source.is_dir() == config_file.is_directory {
+                    old_files = deployment::read_files(source, existing.is_directory, &all).unwrap_or_default();
+                }
+            }
+            (Some(_), ConflictPolicy::Rename) => {
+                entry.action = ImportAction::Rename;
+                config_file.id = uuid::Uuid::new_v4().to_string();
+                config_file.name = unique_name(&config_file.name, &names);
+                config_file.source_path = relocate(&config_file.id, &config_file);
+            }
+            (None, _) => {
+                let source = Path::new(&config_file.source_path);
+                if source.is_absolute() || source.exists() || claimed.contains(&config_file.source_path) || ids.contains(&config_file.id) {
+                    config_file.source_path = relocate(&config_file.id, &config_file);
+                }
+            }
+        }
+        if entry.action != ImportAction::Overwrite {
+            config_file.last_deployment = None;
+            config_file.last_capture = None;
+        }
+        // 越过目标路径限制需要在本机明确设置，不随归档导入
+        config_file.allow_unsafe_target = conflict
+            .filter(|_| entry.action == ImportAction::Overwrite)
+            .is_some_and(|existing| existing.allow_unsafe_target);
+        config_file.updated_at = Utc::now();
+
+        entry.id = config_file.id.clone();
+        entry.name = config_file.name.clone();
+        entry.source_path = config_file.source_path.clone();
+        if let Err(e) = validate_config_file(&config_file) {
+            entry.action = ImportAction::Failed;
+            entry.error = Some(e.to_string());
+            plans.push(ImportPlan { entry, config_file, files });
+            continue;
+        }
+
+        // 单个文件以导入后的源文件名展示差异
+        let mut new_files = files.clone();
+        if !config_file.is_directory {
+            let name = deployment::file_name(Path::new(&config_file.source_path)).unwrap_or_default();
+            new_files.iter_mut().chain(old_files.iter_mut()).for_each(|file| file.relative = name.clone());
+        }
+        entry.changes = deployment::diff_file_sets(&old_files, &new_files, true, context.max_diff_size);
+        if config_file.encrypted || conflict.is_some_and(|existing| existing.encrypted) {
+            entry.changes.iter_mut().for_each(FileDiff::redact);
+        }
+
+        names.insert(config_file.name.clone());
+        ids.insert(config_file.id.clone());
+        claimed.insert(config_file.source_path.clone());
+        plans.push(ImportPlan { entry, config_file, files });
+    }
+    Ok(plans)
+}
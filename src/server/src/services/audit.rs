@@ -0,0 +1,65 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::config::ConfigFilesSettings;
+use crate::models::config_file::{AuditEntry, ConfigFile};
+
+// 同一进程内对审计日志的追加需要串行执行
+static AUDIT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 审计日志，每行一条 JSON 记录，只追加不修改
+#[derive(Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(settings: &ConfigFilesSettings) -> Self {
+        AuditLog {
+            path: Path::new(&settings.data_dir).join("audit.jsonl"),
+        }
+    }
+
+    /// 追加一条记录
+    pub fn record(&self, config_file: &ConfigFile, action: &str, target_path: &str, reason: &str) -> Result<AuditEntry> {
+        let entry = AuditEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            config_id: config_file.id.clone(),
+            name: config_file.name.clone(),
+            action: action.to_string(),
+            target_path: target_path.to_string(),
+            reason: reason.to_string(),
+            created_at: Utc::now(),
+        };
+        let line = serde_json::to_string(&entry)?;
+
+        let _guard = AUDIT_LOCK.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(entry)
+    }
+
+    /// 按时间顺序返回记录，指定 `config_id` 时只返回该配置文件的记录
+    pub fn entries(&self, config_id: Option<&str>) -> Result<Vec<AuditEntry>> {
+        let _guard = AUDIT_LOCK.lock().unwrap();
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let mut entries = Vec::new();
+        for (index, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let entry: AuditEntry = serde_json::from_str(line)
+                .map_err(|e| anyhow::anyhow!("无法解析审计日志 {} 第 {} 行: {}", self.path.display(), index + 1, e))?;
+            if config_id.is_none_or(|id| entry.config_id == id) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use chrono::Utc;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::config::{BackupRetention, ConfigFilesSettings};
+use crate::models::config_file::{BackupEntry, BackupRecord};
+use crate::services::deployment::BackupLocation;
+
+// 同一进程内对备份索引的读写需要串行执行
+static CATALOG_LOCK: Mutex<()> = Mutex::new(());
+
+/// 计算文件内容的 SHA-256
+pub fn file_sha256(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 备份目录索引，记录每份备份的来源、哈希和大小
+#[derive(Clone)]
+pub struct BackupCatalog {
+    backup_dir: PathBuf,
+    index_path: PathBuf,
+    retention: BackupRetention,
+}
+
+impl BackupCatalog {
+    pub fn new(settings: &ConfigFilesSettings) -> Self {
+        let backup_dir = PathBuf::from(&settings.backup_dir);
+        BackupCatalog {
+            index_path: backup_dir.join("index.json"),
+            backup_dir,
+            retention: settings.backup_retention.clone(),
+        }
+    }
+
+    fn load_index(&self) -> Result<Vec<BackupRecord>> {
+        if !self.index_path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.index_path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("无法解析备份索引 {}: {}", self.index_path.display(), e))
+    }
+
+    fn save_index(&self, records: &[BackupRecord]) -> Result<()> {
+        fs::create_dir_all(&self.backup_dir)?;
+        let temp_path = self.index_path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(records)?)?;
+        fs::rename(&temp_path, &self.index_path)?;
+        Ok(())
+    }
+
+    /// 将一次部署产生的备份登记到索引，并按保留策略清理旧备份
+    ///
+    /// `protected` 中的备份文件仍被部署记录引用，不会被清理
+    pub fn record(
+        &self,
+        config_id: &str,
+        entries: &[BackupEntry],
+        protected: &HashSet<String>,
+    ) -> Result<Vec<BackupRecord>> {
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let _guard = CATALOG_LOCK.lock().unwrap();
+        let mut records = self.load_index()?;
+        let mut added = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let backup_path = Path::new(&entry.backup);
+            let record = BackupRecord {
+                id: uuid::Uuid::new_v4().to_string(),
+                config_id: config_id.to_string(),
+                original_path: entry.original.clone(),
+                backup_path: entry.backup.clone(),
+                sha256: file_sha256(backup_path)?,
+                size: fs::metadata(backup_path)?.len(),
+                created_at: Utc::now(),
+            };
+            records.push(record.clone());
+            added.push(record);
+        }
+
+        let records = self.apply_retention(records, protected);
+        self.save_index(&records)?;
+        Ok(added)
+    }
+
+    /// 获取配置文件的所有备份，最新的在前
+    pub fn list(&self, config_id: &str) -> Result<Vec<BackupRecord>> {
+        let _guard = CATALOG_LOCK.lock().unwrap();
+        let mut records: Vec<BackupRecord> = self
+            .load_index()?
+            .into_iter()
+            .filter(|record| record.config_id == config_id)
+            .collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+        Ok(records)
+    }
+
+    pub fn get(&self, config_id: &str, backup_id: &str) -> Result<Option<BackupRecord>> {
+        let _guard = CATALOG_LOCK.lock().unwrap();
+        Ok(self
+            .load_index()?
+            .into_iter()
+            .find(|record| record.config_id == config_id && record.id == backup_id))
+    }
+
+    /// 将备份恢复到原始位置
+    ///
+    /// 原始位置现有的文件会先作为新的备份登记，原始位置是链接时只删除链接本身
+    pub fn restore(
+        &self,
+        config_id: &str,
+        backup_id: &str,
+        protected: &HashSet<String>,
+    ) -> Result<Option<BackupRecord>> {
+        let Some(record) = self.get(config_id, backup_id)? else {
+            return Ok(None);
+        };
+
+        let backup_path = Path::new(&record.backup_path);
+        if !backup_path.is_file() {
+            return Err(anyhow::anyhow!("备份文件不存在: {}", record.backup_path));
+        }
+        if file_sha256(backup_path)? != record.sha256 {
+            return Err(anyhow::anyhow!("备份文件已被修改，哈希校验失败: {}", record.backup_path));
+        }
+
+        let original = Path::new(&record.original_path);
+        match fs::symlink_metadata(original) {
+            Ok(meta) if meta.file_type().is_symlink() => fs::remove_file(original)?,
+            Ok(meta) if meta.is_dir() => {
+                return Err(anyhow::anyhow!("原始位置是目录，无法恢复文件: {}", record.original_path));
+            }
+            Ok(_) => {
+                if file_sha256(original)? != record.sha256 {
+                    let location = BackupLocation::new(&self.backup_dir.to_string_lossy());
+                    let name = original
+                        .file_name()
+                        .map(PathBuf::from)
+                        .ok_or_else(|| anyhow::anyhow!("无法获取文件名: {}", record.original_path))?;
+                    let entry = location.backup(original, &name)?;
+                    self.record(config_id, &[entry], protected)?;
+                }
+                // 先删除再写入，避免通过硬链接改写托管的源文件
+                fs::remove_file(original)?;
+            }
+            Err(_) => {}
+        }
+
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(backup_path, original)?;
+
+        info!("已从备份 {} 恢复文件 {}", record.backup_path, record.original_path);
+        Ok(Some(record))
+    }
+
+    // 按保留策略删除过期的备份，返回保留下来的记录
+    fn apply_retention(&self, mut records: Vec<BackupRecord>, protected: &HashSet<String>) -> Vec<BackupRecord> {
+        records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+        let now = Utc::now();
+        let mut kept_per_file = std::collections::HashMap::new();
+        let mut total_size = 0u64;
+        let mut kept = Vec::with_capacity(records.len());
+        let mut expired = Vec::new();
+
+        // 从新到旧依次判断，最新的备份优先保留
+        for record in records {
+            if protected.contains(&record.backup_path) {
+                total_size += record.size;
+                kept.push(record);
+                continue;
+            }
+
+            let key = (record.config_id.clone(), record.original_path.clone());
+            let count = kept_per_file.entry(key).or_insert(0usize);
+            let over_count = self.retention.keep_last.is_some_and(|keep| *count >= keep);
+            let too_old = self
+                .retention
+                .max_age_days
+                .is_some_and(|days| now - record.created_at > chrono::Duration::days(days as i64));
+            let too_large = self
+                .retention
+                .max_total_size
+                .is_some_and(|max| total_size + record.size > max);
+
+            if over_count || too_old || too_large {
+                expired.push(record);
+            } else {
+                *count += 1;
+                total_size += record.size;
+                kept.push(record);
+            }
+        }
+
+        for record in expired {
+            let path = Path::new(&record.backup_path);
+            if let Err(e) = fs::remove_file(path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                // 删除失败的备份继续保留在索引中，下次再尝试清理
+                warn!("删除过期备份失败 {}: {}", record.backup_path, e);
+                kept.push(record);
+                continue;
+            }
+            // 删除因此变空的部署备份目录
+            let mut dir = path.parent();
+            while let Some(current) = dir {
+                if current == self.backup_dir || fs::remove_dir(current).is_err() {
+                    break;
+                }
+                dir = current.parent();
+            }
+            info!("已清理过期备份: {}", record.backup_path);
+        }
+
+        kept.sort_by_key(|record| record.created_at);
+        kept
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use std::path::Path;
+use crate::config::CommandPolicyConfig;
+use crate::models::software::CommandSpec;
+
+/// 按命令执行策略校验命令，`user_inputs` 为来自请求的、将被代入命令的值
+pub fn check_command(
+    policy: &CommandPolicyConfig,
+    command: &CommandSpec,
+    user_inputs: &[&str],
+) -> Result<()> {
+    let program = command
+        .program()
+        .ok_or_else(|| anyhow::anyhow!("命令不能为空"))?;
+
+    if !policy.allowed_executables.is_empty() {
+        let name = executable_name(program);
+        let allowed = policy
+            .allowed_executables
+            .iter()
+            .any(|allowed| executable_name(allowed) == name);
+        if !allowed {
+            return Err(anyhow::anyhow!("不允许执行的程序: {}", program));
+        }
+    }
+
+    // 软件配置中的命令可以正常使用 shell 语法，开启检查时也只检查 shell 命令，参数向量不经过 shell
+    if policy.check_shell_commands
+        && let CommandSpec::Shell(template) = command
+        && let Some(c) = find_forbidden(policy, template)
+    {
+        return Err(anyhow::anyhow!("命令包含禁止的字符 {:?}: {}", c, template));
+    }
+
+    for input in user_inputs {
+        if let Some(c) = find_forbidden(policy, input) {
+            return Err(anyhow::anyhow!("输入包含禁止的字符 {:?}: {}", c, input));
+        }
+    }
+
+    Ok(())
+}
+
+// 程序名统一为不含目录和 .exe 后缀的小写形式
+fn executable_name(program: &str) -> String {
+    let file_name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    let name = Path::new(file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    name.to_lowercase()
+}
+
+fn find_forbidden<'a>(policy: &'a CommandPolicyConfig, text: &str) -> Option<&'a str> {
+    policy
+        .forbidden_metacharacters
+        .iter()
+        .find(|c| !c.is_empty() && text.contains(c.as_str()))
+        .map(|c| c.as_str())
+}
@@ -1,9 +1,34 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use log::{info, error};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
-use crate::models::config_file::ConfigFile;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::TASKS;
+use crate::config::{ConfigFilesSettings, SecretSettings};
+use crate::models::config_file::{
+    AuditEntry, BackupRecord, CaptureRecord, ConfigFile, ConfigFormat, DeployMode, DeploymentRecord, EncryptResult, FilePermissions,
+    FileResultStatus, LinkedDeployStatus, LinkedDeployment, MergeStrategy,
+};
+use crate::models::software::{InstallStatus, TaskKind};
+use crate::services::audit::AuditLog;
+use crate::services::archive::{self, ArchiveFormat, ConflictPolicy, ImportAction, ImportContext, ImportPlan, ImportReport};
+use crate::services::backups::{self, BackupCatalog};
+use crate::services::content::{self, ContentError, ContentUpdate, SourceContent, TextEncoding, UploadedFile};
+use crate::services::deployment::{self, BackupLocation, DeploySummary, MemoryFile, PathFilter, TemplateRenderError};
+use crate::services::drift::{self, DriftScan};
+use crate::services::git_store::{GitStore, Revision};
+use crate::services::permissions::{self, TargetModes};
+use crate::services::secrets::{self, SecretKey};
+use crate::services::target_policy::{SourceRoots, TargetPolicy};
+use crate::utils::diff::{self, ChangeKind, FileDiff};
+use crate::utils::merge;
+use crate::utils::path;
+use crate::utils::platform;
+use crate::utils::syntax::{self, InvalidFormatError, SyntaxError};
+use crate::utils::template::TemplateContext;
 
 #[async_trait]
 pub trait ConfigFileService {
@@ -12,74 +37,894 @@ pub trait ConfigFileService {
     async fn create_config_file(&self, config_file: ConfigFile) -> Result<ConfigFile>;
     async fn update_config_file(&self, id: &str, config_file: ConfigFile) -> Result<Option<ConfigFile>>;
     async fn delete_config_file(&self, id: &str) -> Result<bool>;
-    async fn deploy_config_file(&self, id: &str) -> Result<String>;
+    async fn deploy_config_file(&self, id: &str) -> Result<Option<DeploySummary>>;
+    async fn start_deploy_task(&self, id: &str) -> Result<Option<String>>;
+    async fn undeploy_config_file(&self, id: &str) -> Result<Option<DeploySummary>>;
+    async fn diff_config_file(&self, id: &str, path: Option<&str>) -> Result<Option<DiffReport>>;
+    async fn capture_config_file(&self, id: &str, confirm: bool) -> Result<Option<CaptureResult>>;
+    async fn get_backups(&self, id: &str) -> Result<Option<Vec<BackupRecord>>>;
+    async fn restore_backup(&self, id: &str, backup_id: &str) -> Result<Option<BackupRecord>>;
+    async fn encrypt_config_file(&self, id: &str) -> Result<Option<EncryptResult>>;
+    async fn get_history(&self, id: &str) -> Result<Option<Vec<Revision>>>;
+    async fn revert_config_file(&self, id: &str, revision: &str) -> Result<Option<Revision>>;
+    async fn push_config_store(&self) -> Result<()>;
+    async fn pull_config_store(&self) -> Result<()>;
+    async fn check_drift(&self, refresh: bool) -> Result<DriftScan>;
+    async fn get_linked_config_files(&self, software_id: &str) -> Result<Vec<ConfigFile>>;
+    async fn deploy_linked_config_files(&self, software_id: &str) -> Result<Vec<LinkedDeployment>>;
+    async fn export_config_files(&self, ids: Option<&[String]>, format: ArchiveFormat) -> Result<Option<Vec<u8>>>;
+    async fn import_config_files(&self, data: &[u8], policy: ConflictPolicy, dry_run: bool) -> Result<ImportReport>;
+    async fn get_content(&self, id: &str, path: Option<&str>) -> Result<Option<SourceContent>>;
+    async fn put_content(&self, id: &str, update: ContentUpdate) -> Result<Option<SourceContent>>;
+    async fn upload_content(
+        &self,
+        id: &str,
+        files: Vec<UploadedFile>,
+        expected: Option<String>,
+    ) -> Result<Option<Vec<SourceContent>>>;
+    async fn get_audit_log(&self, config_id: Option<&str>) -> Result<Vec<AuditEntry>>;
 }
 
+// 同一进程内对注册表文件的读写需要串行执行
+static REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+
+// 通过接口写入源文件时，哈希检查和写入需要串行执行
+static CONTENT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 当前系统没有适用的目标路径
+#[derive(Debug, thiserror::Error)]
+#[error("配置文件不适用于当前系统: {os}")]
+pub struct NotApplicableError {
+    pub os: String,
+}
+
+/// 选择当前系统上的目标路径，先渲染其中的模板变量，再展开环境变量
+pub fn resolve_target_path(config_file: &ConfigFile) -> Result<String> {
+    let os = platform::current_os();
+    let distros = platform::distro_ids();
+    let Some(target) = config_file.target_path_for(os, distros) else {
+        let os = match distros.first() {
+            Some(distro) => format!("{}/{}", os, distro),
+            None => os.to_string(),
+        };
+        return Err(NotApplicableError { os }.into());
+    };
+    let context = template_context(config_file);
+    let missing = context.missing_variables(target)?;
+    if !missing.is_empty() {
+        let missing = missing.into_iter().map(|name| format!("目标路径: {}", name)).collect();
+        return Err(TemplateRenderError { missing }.into());
+    }
+    path::expand_env_vars(&context.render(target)?)
+}
+
+// 目标路径允许以环境变量、模板变量或 ~ 开头，部署时再展开
+fn validate_target_path(label: &str, target: &str) -> Result<()> {
+    let target = target.trim();
+    let bytes = target.as_bytes();
+    let is_drive_path = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+    let is_variable_path = target.starts_with(['%', '$', '~']) || target.starts_with("{{");
+    if !is_drive_path && !is_variable_path && !Path::new(target).is_absolute() {
+        return Err(anyhow::anyhow!("{}必须是绝对路径: {}", label, target));
+    }
+    Ok(())
+}
+
+/// 校验配置文件记录：名称和路径不能为空，路径不能包含 `..`，目标路径必须是绝对路径
+///
+/// 设置了按系统区分的目标路径时，默认目标路径可以为空，表示其他系统不适用。
+/// 源路径是否位于托管目录中在写入源文件时检查
+pub fn validate_config_file(config_file: &ConfigFile) -> Result<()> {
+    if config_file.name.trim().is_empty() {
+        return Err(anyhow::anyhow!("配置文件名称不能为空"));
+    }
+
+    let mut paths = vec![("源路径".to_string(), config_file.source_path.as_str())];
+    if !config_file.target_path.trim().is_empty() || config_file.target_paths.is_empty() {
+        paths.push(("目标路径".to_string(), config_file.target_path.as_str()));
+    }
+    for (platform, path) in config_file.target_paths.entries() {
+        paths.push((format!("{} 的目标路径", platform), path));
+    }
+    for (label, path) in &paths {
+        if path.trim().is_empty() {
+            return Err(anyhow::anyhow!("{}不能为空", label));
+        }
+        if path.chars().any(|c| c.is_control()) {
+            return Err(anyhow::anyhow!("{}包含非法字符: {}", label, path));
+        }
+        if path.split(['/', '\\']).any(|segment| segment == "..") {
+            return Err(anyhow::anyhow!("{}不能包含 '..': {}", label, path));
+        }
+    }
+    let targets = &paths[1..];
+    let context = template_context(config_file);
+    for (label, path) in targets {
+        validate_target_path(label, path)?;
+        let missing = context.missing_variables(path)?;
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!("{}引用了未定义的模板变量: {}", label, missing.join(", ")));
+        }
+    }
+
+    // 发行版使用 os-release 中的 ID，只包含小写字母、数字和 `.`、`_`、`-`
+    for distro in config_file.target_paths.distros.keys() {
+        let valid = !distro.is_empty()
+            && distro.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-'));
+        if !valid {
+            return Err(anyhow::anyhow!("无效的发行版标识: {}", distro));
+        }
+    }
+
+    PathFilter::new(&config_file.include, &config_file.exclude)?;
+
+    // 链接指向的是未渲染的模板，模板只能以复制方式部署
+    if config_file.template && config_file.deploy_mode != DeployMode::Copy {
+        return Err(anyhow::anyhow!("模板配置文件只能以复制方式部署"));
+    }
+
+    // 链接会暴露加密后的源文件，加密配置文件只能解密后复制到目标位置
+    if config_file.encrypted && config_file.deploy_mode != DeployMode::Copy {
+        return Err(anyhow::anyhow!("加密配置文件只能以复制方式部署"));
+    }
+
+    // 链接与源文件共享权限，修改目标文件的权限会影响托管的源文件
+    permissions::validate(&config_file.permissions)?;
+    if config_file.permissions != FilePermissions::default() && config_file.deploy_mode != DeployMode::Copy {
+        return Err(anyhow::anyhow!("权限设置只能用于复制方式部署"));
+    }
+
+    if config_file.merge_strategy != MergeStrategy::Overwrite {
+        if config_file.deploy_mode != DeployMode::Copy {
+            return Err(anyhow::anyhow!("合并策略只能用于复制方式部署"));
+        }
+        // 目录中无法识别格式的文件直接覆盖，单个文件必须能识别格式
+        if config_file.format == Some(ConfigFormat::Text) {
+            return Err(anyhow::anyhow!("纯文本格式不支持合并策略"));
+        }
+        if !config_file.is_directory
+            && config_file.format.is_none()
+            && let Some((_, target)) = targets.iter().find(|(_, path)| merge::detect_format(Path::new(path.trim())).is_none())
+        {
+            return Err(anyhow::anyhow!("无法识别目标文件格式，合并策略只支持 JSON、TOML、YAML 和 INI 文件: {}", target));
+        }
+    }
+
+    // 源路径已存在时，类型必须与记录一致
+    let source = Path::new(&config_file.source_path);
+    if source.exists() && source.is_dir() != config_file.is_directory {
+        let expected = if config_file.is_directory { "目录" } else { "文件" };
+        return Err(anyhow::anyhow!("源路径不是{}: {}", expected, config_file.source_path));
+    }
+
+    Ok(())
+}
+
+// 按合并策略将源文件合并到目标位置的现有内容中
+fn merge_with_target(config_file: &ConfigFile, files: Vec<MemoryFile>, target: &Path) -> Result<Vec<MemoryFile>> {
+    files
+        .into_iter()
+        .map(|file| {
+            let path = if config_file.is_directory { target.join(&file.relative) } else { target.to_path_buf() };
+            // 无法识别格式的文件、二进制文件以及尚不存在的目标文件直接覆盖
+            let format = config_file.format.map_or_else(|| merge::detect_format(&path), merge::merge_format);
+            let Some(format) = format else {
+                return Ok(file);
+            };
+            if !path.is_file() || diff::is_binary(&file.content) {
+                return Ok(file);
+            }
+            let existing = fs::read(&path)?;
+            if diff::is_binary(&existing) {
+                return Ok(file);
+            }
+            let merged = merge::merge(
+                format,
+                &String::from_utf8_lossy(&file.content),
+                &String::from_utf8_lossy(&existing),
+                config_file.merge_strategy,
+            )
+            .map_err(|e| anyhow::anyhow!("合并 {} 失败: {}", path.display(), e))?;
+            Ok(MemoryFile { relative: file.relative, content: merged.into_bytes() })
+        })
+        .collect()
+}
+
+/// 校验将要部署的文件的语法，所有文件的错误一并通过 [`InvalidFormatError`] 报告
+///
+/// 格式优先使用记录中的设置，其次按目标文件名和源文件名识别；无法识别格式的文件和二进制文件跳过
+pub fn check_syntax(config_file: &ConfigFile, files: &[MemoryFile], target: &Path) -> Result<()> {
+    let mut errors = Vec::new();
+    for file in files {
+        let path = if config_file.is_directory { target.join(&file.relative) } else { target.to_path_buf() };
+        let format = config_file
+            .format
+            .or_else(|| syntax::detect_format(&path))
+            .or_else(|| syntax::detect_format(&file.relative));
+        let Some(format) = format else {
+            continue;
+        };
+        if diff::is_binary(&file.content) {
+            continue;
+        }
+        if let Err(e) = syntax::validate(format, &file.content) {
+            errors.push(SyntaxError {
+                path: deployment::display_relative(&file.relative),
+                format,
+                line: e.line,
+                column: e.column,
+                message: e.message,
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(InvalidFormatError { errors }.into());
+    }
+    Ok(())
+}
+
+// 接口中指定的源文件：单个文件为源文件名，目录中为相对路径
+fn source_relative(config_file: &ConfigFile, path: Option<&str>) -> Result<PathBuf> {
+    if !config_file.is_directory {
+        return deployment::file_name(Path::new(&config_file.source_path));
+    }
+    let path = path.ok_or_else(|| ContentError::Invalid("目录需要指定文件的相对路径".to_string()))?;
+    path::safe_relative(path).map_err(|e| ContentError::Invalid(e.to_string()).into())
+}
+
+fn source_file_path(config_file: &ConfigFile, relative: &Path) -> PathBuf {
+    let source = Path::new(&config_file.source_path);
+    if config_file.is_directory { source.join(relative) } else { source.to_path_buf() }
+}
+
+/// 模板渲染使用的变量：内置变量、配置档案变量，以及记录中的变量
+pub fn template_context(config_file: &ConfigFile) -> TemplateContext {
+    let mut context = TemplateContext::new();
+    context.extend(&crate::CONFIG.profile.variables);
+    context.extend(&config_file.variables);
+    context
+}
+
+/// 捕获结果：未确认时只包含差异预览
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureResult {
+    pub changes: Vec<FileDiff>,
+    pub summary: Option<DeploySummary>,
+}
+
+/// 源文件与目标位置的差异报告
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    pub is_directory: bool,
+    pub added: usize,
+    pub modified: usize,
+    pub removed: usize,
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Clone)]
 pub struct ConfigFileServiceImpl {
-    // 这里可以添加数据存储、缓存等依赖
     backup_dir: String,
+    backups: BackupCatalog,
+    max_diff_size: u64,
+    max_content_size: u64,
+    secrets: SecretSettings,
+    // 启用版本管理时，源文件的修改提交到 git 仓库
+    git: Option<GitStore>,
+    // 启用定期检测时，变化检测默认返回缓存的结果
+    drift_scheduled: bool,
+    // 配置文件注册表，所有记录以 JSON 数组形式保存
+    registry_path: PathBuf,
+    // 导入的源文件无法沿用原路径时的存放目录
+    import_dir: PathBuf,
+    target_policy: TargetPolicy,
+    // 通过接口写入的源文件必须位于托管目录中
+    source_roots: SourceRoots,
+    // 越过目标路径限制的操作记录在审计日志中
+    audit: AuditLog,
 }
 
 impl ConfigFileServiceImpl {
-    pub fn new(backup_dir: String) -> Self {
+    pub fn new(settings: &ConfigFilesSettings) -> Self {
         ConfigFileServiceImpl {
-            backup_dir,
-        }
-    }
-    
-    // 创建备份的辅助方法
-    async fn backup_file(&self, file_path: &str) -> Result<String> {
-        let path = Path::new(file_path);
-        
-        if !path.exists() {
-            return Ok("文件不存在，无需备份".to_string());
-        }
-        
-        let file_name = path.file_name()
-            .ok_or_else(|| anyhow::anyhow!("无法获取文件名"))?
-            .to_string_lossy();
-            
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let backup_file_name = format!("{}_{}", file_name, timestamp);
-        let backup_path = Path::new(&self.backup_dir).join(backup_file_name);
-        
-        // 确保备份目录存在
-        if let Some(parent) = backup_path.parent() {
-            fs::create_dir_all(parent)?;
+            backup_dir: settings.backup_dir.clone(),
+            backups: BackupCatalog::new(settings),
+            max_diff_size: settings.max_diff_size,
+            max_content_size: settings.max_content_size,
+            secrets: settings.secrets.clone(),
+            git: GitStore::new(&settings.git),
+            drift_scheduled: settings.drift.interval_minutes.is_some_and(|minutes| minutes > 0),
+            registry_path: Path::new(&settings.data_dir).join("config_files.json"),
+            // 启用版本管理时放在配置仓库中，导入的源文件同样纳入版本管理
+            import_dir: match settings.git.enabled {
+                true => Path::new(&settings.git.repo_dir).join("imported"),
+                false => Path::new(&settings.data_dir).join("imported"),
+            },
+            target_policy: TargetPolicy::new(settings),
+            source_roots: SourceRoots::new(settings),
+            audit: AuditLog::new(settings),
         }
-        
-        // 复制文件到备份位置
-        fs::copy(path, &backup_path)?;
-        
-        info!("已备份文件 {} 到 {}", file_path, backup_path.display());
-        
-        Ok(backup_path.to_string_lossy().to_string())
-    }
-    
-    // 部署配置文件的辅助方法
-    async fn copy_file(&self, source: &str, target: &str, backup: bool) -> Result<()> {
-        let source_path = Path::new(source);
-        let target_path = Path::new(target);
-        
-        if !source_path.exists() {
-            return Err(anyhow::anyhow!("源文件不存在: {}", source));
-        }
-        
-        // 如果目标文件存在且需要备份
-        if target_path.exists() && backup {
-            self.backup_file(target).await?;
-        }
-        
-        // 确保目标目录存在
-        if let Some(parent) = target_path.parent() {
+    }
+
+    // 文件读写在阻塞线程池中执行，避免占用异步运行时的工作线程
+    async fn blocking<T: Send + 'static>(&self, work: impl FnOnce(Self) -> Result<T> + Send + 'static) -> Result<T> {
+        let service = self.clone();
+        tokio::task::spawn_blocking(move || work(service)).await?
+    }
+
+    // 按目标路径限制检查当前系统上的目标路径，设置了 allow_unsafe_target 时放行并写入审计日志
+    fn check_target(&self, config_file: &ConfigFile, target_path: &str, action: &str) -> Result<()> {
+        // 复制部署会写入链接指向的文件，链接部署只替换链接本身
+        let follow_target = config_file.deploy_mode == DeployMode::Copy;
+        self.check_path(config_file, target_path, follow_target, config_file.is_directory, action)
+    }
+
+    fn check_path(
+        &self,
+        config_file: &ConfigFile,
+        target_path: &str,
+        follow_target: bool,
+        is_directory: bool,
+        action: &str,
+    ) -> Result<()> {
+        let Err(e) = self.target_policy.check(Path::new(target_path), follow_target, is_directory) else {
+            return Ok(());
+        };
+        if !config_file.allow_unsafe_target {
+            return Err(e.into());
+        }
+        warn!("配置文件 {} ({}) 越过目标路径限制{}: {}", config_file.name, config_file.id, action, e);
+        self.audit.record(config_file, action, target_path, &e.reason)?;
+        Ok(())
+    }
+
+    // 执行配置文件部署任务，并把逐个文件的结果记录到任务注册表
+    async fn run_deploy_task(self, task_id: String, config_file: ConfigFile) {
+        if !TASKS.start_task(&task_id, format!("正在部署 {}...", config_file.name)) {
+            info!("任务已取消，跳过部署: {}", config_file.name);
+            return;
+        }
+
+        let (status, message) = match self.deploy_config_file(&config_file.id).await {
+            Ok(Some(summary)) => {
+                let errors = summary
+                    .files
+                    .iter()
+                    .filter(|file| file.status == FileResultStatus::Failed)
+                    .map(|file| format!("{}: {}", file.path, file.message.as_deref().unwrap_or_default()))
+                    .collect::<Vec<_>>();
+                let message = if errors.is_empty() {
+                    format!(
+                        "部署完成: 新增 {}，修改 {}，删除 {}，未变化 {}",
+                        summary.added.len(),
+                        summary.changed.len(),
+                        summary.removed.len(),
+                        summary.unchanged
+                    )
+                } else {
+                    format!("部署完成，{} 个文件失败", errors.len())
+                };
+                let status = if errors.is_empty() { InstallStatus::Completed } else { InstallStatus::Failed };
+                TASKS.set_task_results(&task_id, summary.files, errors);
+                (status, message)
+            }
+            Ok(None) => (InstallStatus::Failed, format!("配置文件不存在: {}", config_file.id)),
+            Err(e) => {
+                error!("部署配置文件失败 {} ({}): {}", config_file.name, config_file.id, e);
+                TASKS.set_task_results(&task_id, Vec::new(), vec![e.to_string()]);
+                (InstallStatus::Failed, format!("部署失败: {}", e))
+            }
+        };
+        TASKS.finish_task(&task_id, status, message);
+    }
+
+    // 导入前检查记录：源路径必须位于托管目录中，目标路径必须符合部署限制。
+    // 放行的记录在保存时写入审计日志，这里不重复记录
+    fn check_import(&self, config_file: &ConfigFile) -> Result<()> {
+        self.source_roots.check(Path::new(&config_file.source_path))?;
+        if !config_file.allow_unsafe_target
+            && let Ok(target_path) = resolve_target_path(config_file)
+        {
+            let follow_target = config_file.deploy_mode == DeployMode::Copy;
+            self.target_policy.check(Path::new(&target_path), follow_target, config_file.is_directory)?;
+        }
+        Ok(())
+    }
+
+    // 写入一条导入记录的源文件并保存记录，覆盖的源文件先备份；
+    // 写入不完整或记录保存失败时撤销已写入的源文件
+    async fn apply_import(&self, plan: &ImportPlan) -> Result<()> {
+        let config_file = &plan.config_file;
+        let (source, is_directory, files) = (config_file.source_path.clone(), config_file.is_directory, plan.files.clone());
+        let summary = self
+            .blocking(move |service| {
+                let all = PathFilter::new(&[], &[])?;
+                let backup = BackupLocation::new(&service.backup_dir);
+                deployment::deploy_files(&files, Path::new(&source), is_directory, &all, true, Some(&backup), None)
+            })
+            .await?;
+
+        let saved = if summary.failed() > 0 {
+            Err(anyhow::anyhow!("{} 个源文件写入失败", summary.failed()))
+        } else if plan.entry.action == ImportAction::Overwrite {
+            match self.update_config_file(&config_file.id, config_file.clone()).await {
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => Err(anyhow::anyhow!("配置文件不存在: {}", config_file.id)),
+                Err(e) => Err(e),
+            }
+        } else {
+            self.create_config_file(config_file.clone()).await.map(|_| ())
+        };
+        if let Err(e) = saved {
+            let source = config_file.source_path.clone();
+            let written = summary.clone();
+            if let Err(rollback) = self
+                .blocking(move |_| deployment::rollback(Path::new(&source), is_directory, &written))
+                .await
+            {
+                error!("撤销导入的源文件失败 {}: {}", config_file.source_path, rollback);
+            }
+            return Err(e);
+        }
+        if plan.entry.action == ImportAction::Overwrite {
+            self.backups.record(&config_file.id, &summary.backups, &self.protected_backups()?)?;
+        }
+        Ok(())
+    }
+
+    // 写入一个源文件：检查并发修改，加密配置文件先加密，被覆盖的源文件先备份
+    async fn write_source(
+        &self,
+        config_file: &ConfigFile,
+        relative: &Path,
+        plaintext: Vec<u8>,
+        expected: Option<&str>,
+    ) -> Result<SourceContent> {
+        let size = plaintext.len() as u64;
+        if size > self.max_content_size {
+            return Err(ContentError::TooLarge { size, limit: self.max_content_size }.into());
+        }
+        let (record, relative, expected) = (config_file.clone(), relative.to_path_buf(), expected.map(str::to_string));
+        let written = self
+            .blocking(move |service| service.write_source_file(&record, &relative, plaintext, expected.as_deref()))
+            .await?;
+        self.commit_source(config_file, "编辑").await;
+        Ok(written)
+    }
+
+    fn write_source_file(
+        &self,
+        config_file: &ConfigFile,
+        relative: &Path,
+        plaintext: Vec<u8>,
+        expected: Option<&str>,
+    ) -> Result<SourceContent> {
+        let encoding = content::decode(&plaintext).map(|(encoding, _)| encoding);
+        let stored = if config_file.encrypted && !secrets::is_encrypted(&plaintext) {
+            secrets::encrypt(&SecretKey::load(&self.secrets)?, &plaintext)?
+        } else {
+            plaintext
+        };
+
+        let file = source_file_path(config_file, relative);
+        self.source_roots.check(&file)?;
+        let summary = {
+            let _guard = CONTENT_LOCK.lock().unwrap();
+            if file.is_dir() {
+                return Err(ContentError::Invalid(format!("路径是目录: {}", file.display())).into());
+            }
+            let current = if file.is_file() { Some(backups::file_sha256(&file)?) } else { None };
+            content::check_expected(expected, current.as_deref())?;
+            let backup = BackupLocation::new(&self.backup_dir);
+            let written = MemoryFile { relative: relative.to_path_buf(), content: stored.clone() };
+            deployment::write_files(&[written], &file, false, &[], Some(&backup), None)?
+        };
+        info!("写入源文件 {}，ID: {}", file.display(), config_file.id);
+
+        self.backups.record(&config_file.id, &summary.backups, &self.protected_backups()?)?;
+        self.modify_record(&config_file.id, |config_file| config_file.updated_at = chrono::Utc::now())?;
+        Ok(SourceContent {
+            path: deployment::display_relative(relative),
+            size: stored.len() as u64,
+            sha256: content::sha256(&stored),
+            encoding: if config_file.encrypted { None } else { encoding },
+            content: None,
+            redacted: config_file.encrypted,
+        })
+    }
+
+    // 部署一条记录：检查目标路径，准备源文件后写入目标位置并保存部署记录
+    fn deploy(&self, config_file: &ConfigFile) -> Result<DeploySummary> {
+        let id = &config_file.id;
+        self.source_roots.check(Path::new(&config_file.source_path))?;
+        let target_path = resolve_target_path(config_file)?;
+        self.check_target(config_file, &target_path, "部署")?;
+        let target = Path::new(&target_path);
+        let filter = PathFilter::new(&config_file.include, &config_file.exclude)?;
+
+        // 模板渲染、解密和语法校验在写入任何文件之前完成，失败时直接返回错误
+        let prepared = self.prepare_source(config_file, &filter, target)?;
+        let source = Path::new(&config_file.source_path);
+        let modes = TargetModes::new(config_file, source, target, &filter)?;
+
+        let backup_location = BackupLocation::new(&self.backup_dir);
+
+        let summary = match config_file.deploy_mode {
+            DeployMode::Copy => {
+                let backup = config_file.backup_on_install.then_some(&backup_location);
+                let mut summary = if let Some(files) = &prepared {
+                    deployment::deploy_files(
+                        files,
+                        target,
+                        config_file.is_directory,
+                        &filter,
+                        config_file.delete_extraneous,
+                        backup,
+                        Some(&modes),
+                    )?
+                } else if config_file.is_directory {
+                    deployment::deploy_directory(source, target, &filter, config_file.delete_extraneous, backup, Some(&modes))?
+                } else {
+                    deployment::deploy_file(source, target, backup, Some(&modes))?
+                };
+                summary.permissions = permissions::apply(config_file, &modes, source, target, &filter)?;
+                summary
+            }
+            // 链接模式下被替换的文件总是先备份，以便撤销
+            mode => {
+                let filter = config_file.is_directory.then_some(&filter);
+                deployment::deploy_link(source, target, mode, filter, &backup_location)?
+            }
+        };
+
+        // 重复以链接方式部署时保留之前的备份，撤销时仍可恢复最初的文件
+        let previous = config_file.last_deployment.clone();
+        let mut record = DeploymentRecord {
+            mode: config_file.deploy_mode,
+            target_path: target_path.clone(),
+            deployed_at: chrono::Utc::now(),
+            linked: summary.linked.clone(),
+            backups: summary.backups.clone(),
+            files: drift::snapshot(source, target, config_file.is_directory, &filter)?,
+        };
+        // 写入失败的文件不记录哈希，变化检测会将其报告为需要重新部署
+        record.files.retain(|file| {
+            !summary
+                .files
+                .iter()
+                .any(|result| result.status == FileResultStatus::Failed && result.path == file.path)
+        });
+        let keeps_previous = |previous: &DeploymentRecord| {
+            previous.mode != DeployMode::Copy
+                && previous.mode == record.mode
+                && previous.target_path == record.target_path
+        };
+        if let Some(previous) = previous.filter(keeps_previous) {
+            record.backups.splice(0..0, previous.backups);
+        }
+        self.save_deployment(id, Some(record))?;
+        self.backups.record(id, &summary.backups, &self.protected_backups()?)?;
+
+        Ok(summary)
+    }
+
+    // 计算源文件与目标位置的差异
+    fn diff(&self, config_file: &ConfigFile, path: Option<&str>) -> Result<DiffReport> {
+        // 差异方向与部署一致：目标位置的当前内容为旧版本，源文件为新版本
+        self.source_roots.check(Path::new(&config_file.source_path))?;
+        let target_path = resolve_target_path(config_file)?;
+        self.check_target(config_file, &target_path, "预览")?;
+        let filter = PathFilter::new(&config_file.include, &config_file.exclude)?;
+        let target = Path::new(&target_path);
+        let mut files = match self.prepare_source(config_file, &filter, target)? {
+            Some(prepared) => deployment::preview_files(
+                &prepared,
+                target,
+                config_file.is_directory,
+                &filter,
+                config_file.delete_extraneous,
+                self.max_diff_size,
+            )?,
+            None => deployment::preview_sync(
+                Path::new(&config_file.source_path),
+                target,
+                config_file.is_directory,
+                &filter,
+                config_file.delete_extraneous,
+                self.max_diff_size,
+            )?,
+        };
+        // 加密配置文件的内容不通过接口返回
+        if config_file.encrypted {
+            files.iter_mut().for_each(FileDiff::redact);
+        }
+
+        let count = |kind: ChangeKind| files.iter().filter(|file| file.change == kind).count();
+        let (added, modified, removed) = (count(ChangeKind::Added), count(ChangeKind::Modified), count(ChangeKind::Removed));
+
+        // 目录默认只返回逐文件的变化摘要，指定 path 时返回该文件的完整差异
+        if config_file.is_directory {
+            match path {
+                Some(path) => files.retain(|file| file.path == path),
+                None => files.iter_mut().for_each(|file| file.diff = None),
+            }
+        }
+
+        Ok(DiffReport {
+            is_directory: config_file.is_directory,
+            added,
+            modified,
+            removed,
+            files,
+        })
+    }
+
+    // 将目标位置的当前内容写回源文件，未确认时只返回差异预览
+    fn capture(&self, config_file: &ConfigFile, confirm: bool) -> Result<CaptureResult> {
+        // 捕获方向与部署相反：目标位置的当前内容写回托管的源文件
+        let target_path = resolve_target_path(config_file)?;
+        self.check_target(config_file, &target_path, "捕获")?;
+        let source = Path::new(&config_file.source_path);
+        if confirm {
+            self.source_roots.check(source)?;
+        }
+        let target = Path::new(&target_path);
+        let filter = PathFilter::new(&config_file.include, &config_file.exclude)?;
+        if config_file.encrypted {
+            let result = self.capture_encrypted(config_file, target, &filter, confirm)?;
+            if let Some(summary) = &result.summary
+                && !result.changes.is_empty()
+            {
+                self.save_capture(config_file, &result.changes, summary)?;
+            }
+            return Ok(result);
+        }
+        let changes = deployment::preview_sync(
+            target,
+            source,
+            config_file.is_directory,
+            &filter,
+            config_file.delete_extraneous,
+            self.max_diff_size,
+        )?;
+
+        if !confirm {
+            return Ok(CaptureResult { changes, summary: None });
+        }
+        if changes.is_empty() {
+            return Ok(CaptureResult { changes, summary: Some(DeploySummary::default()) });
+        }
+        info!("捕获配置文件，ID: {}, 变更文件数: {}", config_file.id, changes.len());
+
+        // 被覆盖的源文件总是先备份
+        let backup = BackupLocation::new(&self.backup_dir);
+        let summary = if config_file.is_directory {
+            deployment::deploy_directory(target, source, &filter, config_file.delete_extraneous, Some(&backup), None)?
+        } else {
+            deployment::deploy_file(target, source, Some(&backup), None)?
+        };
+
+        self.save_capture(config_file, &changes, &summary)?;
+        Ok(CaptureResult { changes, summary: Some(summary) })
+    }
+
+    // 读取注册表，文件不存在时视为空
+    fn load_registry(&self) -> Result<Vec<ConfigFile>> {
+        if !self.registry_path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.registry_path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("无法解析配置文件注册表 {}: {}", self.registry_path.display(), e))
+    }
+
+    // 先写入临时文件再替换，避免写入中断导致注册表损坏
+    fn save_registry(&self, config_files: &[ConfigFile]) -> Result<()> {
+        if let Some(parent) = self.registry_path.parent() {
             fs::create_dir_all(parent)?;
         }
-        
-        // 复制文件到目标位置
-        fs::copy(source_path, target_path)?;
-        
-        info!("已复制文件 {} 到 {}", source, target);
-        
+        let temp_path = self.registry_path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(config_files)?)?;
+        fs::rename(&temp_path, &self.registry_path)?;
+        Ok(())
+    }
+
+    // 加密、模板或合并部署的配置文件在内存中解密、渲染和合并，明文不会写入临时文件；
+    // 普通配置文件直接使用源路径。写入之前先校验文件语法
+    fn prepare_source(
+        &self,
+        config_file: &ConfigFile,
+        filter: &PathFilter,
+        target: &Path,
+    ) -> Result<Option<Vec<MemoryFile>>> {
+        let merges = config_file.merge_strategy != MergeStrategy::Overwrite;
+        let source = Path::new(&config_file.source_path);
+        if !config_file.template && !config_file.encrypted && !merges {
+            if config_file.format != Some(ConfigFormat::Text) && source.exists() {
+                check_syntax(config_file, &deployment::read_files(source, config_file.is_directory, filter)?, target)?;
+            }
+            return Ok(None);
+        }
+        let mut files = deployment::read_files(source, config_file.is_directory, filter)?;
+        if config_file.encrypted {
+            files = self.decrypt_files(files)?;
+        }
+        if config_file.template {
+            files = deployment::render_files(files, &template_context(config_file))?;
+        }
+        // 校验解密和渲染后的内容，也就是实际写入目标位置的内容
+        check_syntax(config_file, &files, target)?;
+        if merges {
+            files = merge_with_target(config_file, files, target)?;
+        }
+        Ok(Some(files))
+    }
+
+    fn decrypt_files(&self, files: Vec<MemoryFile>) -> Result<Vec<MemoryFile>> {
+        let key = SecretKey::load(&self.secrets)?;
+        files
+            .into_iter()
+            .map(|file| {
+                let content = secrets::decrypt(&key, &file.content)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", file.relative.display(), e))?;
+                Ok(MemoryFile { relative: file.relative, content })
+            })
+            .collect()
+    }
+
+    // 加密配置文件的捕获：比较解密后的源文件与目标位置，只重新加密发生变化的文件
+    fn capture_encrypted(
+        &self,
+        config_file: &ConfigFile,
+        target: &Path,
+        filter: &PathFilter,
+        confirm: bool,
+    ) -> Result<CaptureResult> {
+        let source = Path::new(&config_file.source_path);
+        let is_directory = config_file.is_directory;
+        let mut current = if source.exists() {
+            self.decrypt_files(deployment::read_files(source, is_directory, filter)?)?
+        } else {
+            Vec::new()
+        };
+        let mut captured = deployment::read_files(target, is_directory, filter)?;
+        // 单个文件以源文件名展示差异
+        if !is_directory {
+            let name = source.file_name().map(PathBuf::from).unwrap_or_default();
+            current.iter_mut().chain(captured.iter_mut()).for_each(|file| file.relative = name.clone());
+        }
+
+        let mut changes = deployment::diff_file_sets(&current, &captured, config_file.delete_extraneous, self.max_diff_size);
+        changes.iter_mut().for_each(FileDiff::redact);
+        if !confirm {
+            return Ok(CaptureResult { changes, summary: None });
+        }
+        if changes.is_empty() {
+            return Ok(CaptureResult { changes, summary: Some(DeploySummary::default()) });
+        }
+        info!("捕获加密配置文件，ID: {}, 变更文件数: {}", config_file.id, changes.len());
+
+        let key = SecretKey::load(&self.secrets)?;
+        let changed: HashSet<String> = changes
+            .iter()
+            .filter(|change| change.change != ChangeKind::Removed)
+            .map(|change| change.path.clone())
+            .collect();
+        let encrypted = captured
+            .into_iter()
+            .filter(|file| changed.contains(&deployment::display_relative(&file.relative)))
+            .map(|file| Ok(MemoryFile { content: secrets::encrypt(&key, &file.content)?, relative: file.relative }))
+            .collect::<Result<Vec<_>>>()?;
+        let removed: Vec<PathBuf> = changes
+            .iter()
+            .filter(|change| change.change == ChangeKind::Removed)
+            .map(|change| PathBuf::from(&change.path))
+            .collect();
+
+        // 被覆盖的源文件总是先备份
+        let backup = BackupLocation::new(&self.backup_dir);
+        let summary = deployment::write_files(&encrypted, source, is_directory, &removed, Some(&backup), None)?;
+        Ok(CaptureResult { changes, summary: Some(summary) })
+    }
+
+    // 将源文件的变化提交到配置仓库；提交失败不影响已完成的操作
+    async fn commit_source(&self, config_file: &ConfigFile, action: &str) {
+        let Some(git) = &self.git else {
+            return;
+        };
+        // 加密记录的源文件中仍有明文时不提交，避免明文进入历史版本
+        if config_file.encrypted {
+            let record = config_file.clone();
+            let plaintext = self
+                .blocking(move |_| {
+                    let filter = PathFilter::new(&record.include, &record.exclude)?;
+                    let files = deployment::read_files(Path::new(&record.source_path), record.is_directory, &filter)?;
+                    Ok(files
+                        .into_iter()
+                        .filter(|file| !secrets::is_encrypted(&file.content))
+                        .map(|file| deployment::display_relative(&file.relative))
+                        .collect::<Vec<_>>())
+                })
+                .await;
+            match plaintext {
+                Ok(files) if files.is_empty() => {}
+                Ok(files) => {
+                    warn!("加密配置文件 {} 中有未加密的源文件，不提交到配置仓库: {}", config_file.id, files.join(", "));
+                    return;
+                }
+                Err(e) => {
+                    warn!("无法检查加密配置文件 {} 的源文件，不提交到配置仓库: {}", config_file.id, e);
+                    return;
+                }
+            }
+        }
+        let message = format!("{}配置文件 {} ({})", action, config_file.name, config_file.id);
+        if let Err(e) = git.commit(Path::new(&config_file.source_path), &message).await {
+            warn!("提交配置仓库失败: {}", e);
+        }
+    }
+
+    fn git_store(&self) -> Result<&GitStore> {
+        self.git.as_ref().ok_or_else(|| anyhow::anyhow!("未启用配置仓库版本管理"))
+    }
+
+    // 记录捕获结果，并登记被覆盖的源文件备份
+    //
+    // 捕获后源文件与目标位置一致，同时更新部署记录中的文件哈希
+    fn save_capture(&self, config_file: &ConfigFile, changes: &[FileDiff], summary: &DeploySummary) -> Result<()> {
+        let id = &config_file.id;
+        let record = CaptureRecord {
+            captured_at: chrono::Utc::now(),
+            files: changes.iter().map(|change| change.path.clone()).collect(),
+            backups: summary.backups.clone(),
+        };
+        let deployed_files = match &config_file.last_deployment {
+            Some(deployment) => {
+                let filter = PathFilter::new(&config_file.include, &config_file.exclude)?;
+                let source = Path::new(&config_file.source_path);
+                let target = Path::new(&deployment.target_path);
+                Some(drift::snapshot(source, target, config_file.is_directory, &filter)?)
+            }
+            None => None,
+        };
+        self.modify_record(id, |config_file| {
+            config_file.last_capture = Some(record);
+            if let (Some(deployment), Some(files)) = (&mut config_file.last_deployment, deployed_files) {
+                deployment.files = files;
+            }
+        })?;
+        self.backups.record(id, &summary.backups, &self.protected_backups()?)?;
+        Ok(())
+    }
+
+    // 部署记录仍引用的备份文件，撤销部署时需要用到，不能被保留策略清理
+    fn protected_backups(&self) -> Result<HashSet<String>> {
+        let _guard = REGISTRY_LOCK.lock().unwrap();
+        Ok(self
+            .load_registry()?
+            .into_iter()
+            .filter_map(|config_file| config_file.last_deployment)
+            .flat_map(|record| record.backups)
+            .map(|entry| entry.backup)
+            .collect())
+    }
+
+    // 更新记录中的部署信息，不修改更新时间
+    fn save_deployment(&self, id: &str, record: Option<DeploymentRecord>) -> Result<()> {
+        self.modify_record(id, |config_file| config_file.last_deployment = record)
+    }
+
+    // 原地修改注册表中的一条记录
+    fn modify_record(&self, id: &str, modify: impl FnOnce(&mut ConfigFile)) -> Result<()> {
+        let _guard = REGISTRY_LOCK.lock().unwrap();
+        let mut config_files = self.load_registry()?;
+        if let Some(config_file) = config_files.iter_mut().find(|config_file| config_file.id == id) {
+            modify(config_file);
+            self.save_registry(&config_files)?;
+        }
         Ok(())
     }
 }
@@ -87,89 +932,466 @@ impl ConfigFileServiceImpl {
 #[async_trait]
 impl ConfigFileService for ConfigFileServiceImpl {
     async fn get_all_config_files(&self) -> Result<Vec<ConfigFile>> {
-        // 在实际实现中，这里会从数据库或文件中获取配置文件列表
-        // 目前返回一些示例数据
-        let config_files = vec![
-            ConfigFile::new(
-                "VSCode 设置".to_string(),
-                "./configs/vscode/settings.json".to_string(),
-                "%APPDATA%/Code/User/settings.json".to_string(),
-                Some("vscode-id".to_string()),
-                false,
-            ),
-            ConfigFile::new(
-                "Git 配置".to_string(),
-                "./configs/git/.gitconfig".to_string(),
-                "%USERPROFILE%/.gitconfig".to_string(),
-                Some("git-id".to_string()),
-                false,
-            ),
-        ];
-        
-        Ok(config_files)
-    }
-    
+        let _guard = REGISTRY_LOCK.lock().unwrap();
+        self.load_registry()
+    }
+
     async fn get_config_file_by_id(&self, id: &str) -> Result<Option<ConfigFile>> {
-        // 在实际实现中，这里会从数据库或文件中获取特定配置文件
-        // 目前返回一个示例数据
-        let config_file = ConfigFile::new(
-            "VSCode 设置".to_string(),
-            "./configs/vscode/settings.json".to_string(),
-            "%APPDATA%/Code/User/settings.json".to_string(),
-            Some("vscode-id".to_string()),
-            false,
-        );
-        
-        Ok(Some(config_file))
+        let _guard = REGISTRY_LOCK.lock().unwrap();
+        Ok(self.load_registry()?.into_iter().find(|config_file| config_file.id == id))
     }
-    
+
     async fn create_config_file(&self, config_file: ConfigFile) -> Result<ConfigFile> {
-        // 在实际实现中，这里会将配置文件保存到数据库或文件中
-        // 目前只是记录日志并返回原始数据
+        validate_config_file(&config_file)?;
+        self.source_roots.check(Path::new(&config_file.source_path))?;
+        // 当前系统上无法确定目标路径时，留到部署时检查
+        if let Ok(target_path) = resolve_target_path(&config_file) {
+            self.check_target(&config_file, &target_path, "保存")?;
+        }
+
+        let guard = REGISTRY_LOCK.lock().unwrap();
+        let mut config_files = self.load_registry()?;
+        if config_files.iter().any(|existing| existing.id == config_file.id) {
+            return Err(anyhow::anyhow!("配置文件 ID 已存在: {}", config_file.id));
+        }
+        config_files.push(config_file.clone());
+        self.save_registry(&config_files)?;
+        drop(guard);
+
         info!("创建配置文件: {:?}", config_file);
-        
+        self.commit_source(&config_file, "创建").await;
         Ok(config_file)
     }
-    
+
     async fn update_config_file(&self, id: &str, mut config_file: ConfigFile) -> Result<Option<ConfigFile>> {
-        // 在实际实现中，这里会更新数据库或文件中的配置文件
-        // 目前只是记录日志并返回更新后的数据
-        info!("更新配置文件，ID: {}, 数据: {:?}", id, config_file);
-        
-        config_file.id = id.to_string();
+        validate_config_file(&config_file)?;
+        self.source_roots.check(Path::new(&config_file.source_path))?;
+        if let Ok(target_path) = resolve_target_path(&config_file) {
+            config_file.id = id.to_string();
+            self.check_target(&config_file, &target_path, "保存")?;
+        }
+
+        let guard = REGISTRY_LOCK.lock().unwrap();
+        let mut config_files = self.load_registry()?;
+        let Some(existing) = config_files.iter_mut().find(|existing| existing.id == id) else {
+            return Ok(None);
+        };
+
+        // ID 和创建时间由注册表维护，不允许通过更新修改
+        config_file.id = existing.id.clone();
+        config_file.created_at = existing.created_at;
         config_file.updated_at = chrono::Utc::now();
-        
+        *existing = config_file.clone();
+        self.save_registry(&config_files)?;
+        drop(guard);
+
+        info!("更新配置文件，ID: {}, 数据: {:?}", id, config_file);
+        self.commit_source(&config_file, "更新").await;
         Ok(Some(config_file))
     }
-    
+
     async fn delete_config_file(&self, id: &str) -> Result<bool> {
-        // 在实际实现中，这里会从数据库或文件中删除配置文件
-        // 目前只是记录日志并返回成功
+        let _guard = REGISTRY_LOCK.lock().unwrap();
+        let mut config_files = self.load_registry()?;
+        let count = config_files.len();
+        config_files.retain(|config_file| config_file.id != id);
+        if config_files.len() == count {
+            return Ok(false);
+        }
+        self.save_registry(&config_files)?;
+
         info!("删除配置文件，ID: {}", id);
-        
         Ok(true)
     }
-    
-    async fn deploy_config_file(&self, id: &str) -> Result<String> {
-        // 生成任务ID
-        let task_id = uuid::Uuid::new_v4().to_string();
-        
-        // 在实际实现中，这里会获取配置文件并部署
-        // 目前只是记录日志
-        info!("开始部署配置文件，ID: {}, 任务ID: {}", id, task_id);
-        
-        // 模拟获取配置文件
-        let config_file = self.get_config_file_by_id(id).await?
-            .ok_or_else(|| anyhow::anyhow!("配置文件不存在"))?;
-            
-        // 解析路径中的环境变量
-        let target_path = config_file.target_path.replace("%USERPROFILE%", &std::env::var("USERPROFILE").unwrap_or_default())
-            .replace("%APPDATA%", &std::env::var("APPDATA").unwrap_or_default());
-            
-        // 部署配置文件
-        self.copy_file(&config_file.source_path, &target_path, config_file.backup_on_install).await?;
-        
-        // 返回任务ID，用于后续查询部署状态
-        Ok(task_id)
+
+    async fn deploy_config_file(&self, id: &str) -> Result<Option<DeploySummary>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        info!("开始部署配置文件，ID: {}", id);
+        self.blocking(move |service| service.deploy(&config_file)).await.map(Some)
+    }
+
+    async fn start_deploy_task(&self, id: &str) -> Result<Option<String>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let software_id = config_file.software_id.clone().unwrap_or_default();
+        let task_id = TASKS.create_task(TaskKind::ConfigDeploy, &software_id, Some(config_file.id.clone()));
+        info!("创建配置文件部署任务，ID: {}, 任务ID: {}", id, task_id);
+        tokio::spawn(self.clone().run_deploy_task(task_id.clone(), config_file));
+        Ok(Some(task_id))
+    }
+
+    async fn undeploy_config_file(&self, id: &str) -> Result<Option<DeploySummary>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let record = config_file
+            .last_deployment
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("配置文件尚未部署: {}", id))?;
+        info!("撤销配置文件部署，ID: {}", id);
+        // 撤销会删除目标位置的链接并恢复备份，只处理链接本身
+        self.check_path(&config_file, &record.target_path, false, config_file.is_directory, "撤销")?;
+
+        let source = config_file.source_path.clone();
+        let summary = self
+            .blocking(move |_| deployment::undo_link_deployment(Path::new(&source), &record))
+            .await?;
+        self.save_deployment(id, None)?;
+
+        Ok(Some(summary))
+    }
+
+    async fn diff_config_file(&self, id: &str, path: Option<&str>) -> Result<Option<DiffReport>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let path = path.map(str::to_string);
+        self.blocking(move |service| service.diff(&config_file, path.as_deref())).await.map(Some)
+    }
+
+    async fn capture_config_file(&self, id: &str, confirm: bool) -> Result<Option<CaptureResult>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+
+        // 渲染后的内容写回模板会丢失其中的变量引用
+        if config_file.template {
+            return Err(anyhow::anyhow!("模板配置文件不支持捕获: {}", id));
+        }
+        // 合并后的目标文件包含本机特有的设置，整体写回会把它们变成托管内容
+        if config_file.merge_strategy != MergeStrategy::Overwrite {
+            return Err(anyhow::anyhow!("合并部署的配置文件不支持捕获: {}", id));
+        }
+
+        let record = config_file.clone();
+        let result = self.blocking(move |service| service.capture(&record, confirm)).await?;
+        if result.summary.is_some() && !result.changes.is_empty() {
+            self.commit_source(&config_file, "捕获").await;
+        }
+        Ok(Some(result))
+    }
+
+    async fn get_backups(&self, id: &str) -> Result<Option<Vec<BackupRecord>>> {
+        if self.get_config_file_by_id(id).await?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.backups.list(id)?))
+    }
+
+    async fn restore_backup(&self, id: &str, backup_id: &str) -> Result<Option<BackupRecord>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let Some(backup) = self.backups.get(id, backup_id)? else {
+            return Ok(None);
+        };
+        // 备份可能来自源文件或部署目标：托管目录中的源文件直接恢复，其他位置按目标路径限制检查。
+        // 恢复时原始位置的链接会被删除而不是跟随写入
+        if self.source_roots.check(Path::new(&backup.original_path)).is_err() {
+            self.check_path(&config_file, &backup.original_path, false, false, "恢复")?;
+        }
+        info!("恢复配置文件备份，ID: {}, 备份ID: {}", id, backup_id);
+        let (id, backup_id) = (id.to_string(), backup_id.to_string());
+        self.blocking(move |service| service.backups.restore(&id, &backup_id, &service.protected_backups()?))
+            .await
+    }
+
+    async fn encrypt_config_file(&self, id: &str) -> Result<Option<EncryptResult>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        if config_file.deploy_mode != DeployMode::Copy {
+            return Err(anyhow::anyhow!("加密配置文件只能以复制方式部署"));
+        }
+        info!("加密配置文件源文件，ID: {}", id);
+
+        // 已经加密的文件保持不变，可以重复执行
+        let record = config_file.clone();
+        let count = self
+            .blocking(move |service| {
+                let source = Path::new(&record.source_path);
+                service.source_roots.check(source)?;
+                let key = SecretKey::load(&service.secrets)?;
+                let filter = PathFilter::new(&record.include, &record.exclude)?;
+                let mut count = 0;
+                for file in deployment::read_files(source, record.is_directory, &filter)? {
+                    if secrets::is_encrypted(&file.content) {
+                        continue;
+                    }
+                    let path = if record.is_directory { source.join(&file.relative) } else { source.to_path_buf() };
+                    let mut temp_path = path.clone().into_os_string();
+                    temp_path.push(".encrypting");
+                    let ciphertext = secrets::encrypt(&key, &file.content)?;
+                    if let Err(e) = fs::write(&temp_path, ciphertext).and_then(|_| fs::rename(&temp_path, &path)) {
+                        // 替换失败时不留下临时文件
+                        let _ = fs::remove_file(&temp_path);
+                        return Err(e.into());
+                    }
+                    count += 1;
+                }
+                Ok(count)
+            })
+            .await?;
+        info!("已加密 {} 个源文件，ID: {}", count, id);
+
+        // 加密只影响之后的版本，之前提交的明文仍可以从历史版本中读取，推送后同样存在于远程仓库
+        let mut warnings = Vec::new();
+        if count > 0
+            && let Some(git) = &self.git
+            && let Ok(history) = git.history(Path::new(&config_file.source_path)).await
+            && !history.is_empty()
+        {
+            warn!("配置文件 {} 加密之前的明文保留在配置仓库的 {} 个历史版本中", id, history.len());
+            warnings.push(format!(
+                "配置仓库的 {} 个历史版本中仍包含加密之前的明文，需要重写仓库历史（包括已推送的远程仓库）并更换其中的凭据",
+                history.len()
+            ));
+        }
+
+        self.modify_record(id, |config_file| {
+            config_file.encrypted = true;
+            config_file.updated_at = chrono::Utc::now();
+        })?;
+        self.commit_source(&config_file, "加密").await;
+        let config_file = self.get_config_file_by_id(id).await?;
+        Ok(config_file.map(|config_file| EncryptResult { config_file, warnings }))
+    }
+
+    async fn get_history(&self, id: &str) -> Result<Option<Vec<Revision>>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let git = self.git_store()?;
+        Ok(Some(git.history(Path::new(&config_file.source_path)).await?))
+    }
+
+    async fn revert_config_file(&self, id: &str, revision: &str) -> Result<Option<Revision>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let git = self.git_store()?;
+        info!("还原配置文件，ID: {}, 版本: {}", id, revision);
+
+        let short = revision.get(..8).unwrap_or(revision);
+        let message = format!("还原配置文件 {} ({}) 到版本 {}", config_file.name, config_file.id, short);
+        let head = git.revert(Path::new(&config_file.source_path), revision, &message).await?;
+        self.modify_record(id, |config_file| config_file.updated_at = chrono::Utc::now())?;
+        Ok(Some(head))
+    }
+
+    async fn push_config_store(&self) -> Result<()> {
+        self.git_store()?.push().await
+    }
+
+    async fn pull_config_store(&self) -> Result<()> {
+        self.git_store()?.pull().await
+    }
+
+    async fn check_drift(&self, refresh: bool) -> Result<DriftScan> {
+        if !refresh
+            && self.drift_scheduled
+            && let Some(scan) = drift::cached()
+        {
+            return Ok(scan);
+        }
+        let config_files = self.get_all_config_files().await?;
+        let entries = self.blocking(move |_| Ok(config_files.iter().map(drift::check).collect())).await?;
+        let scan = DriftScan { checked_at: chrono::Utc::now(), entries };
+        drift::store(&scan);
+        Ok(scan)
+    }
+
+    async fn get_linked_config_files(&self, software_id: &str) -> Result<Vec<ConfigFile>> {
+        let config_files = self.get_all_config_files().await?;
+        Ok(config_files
+            .into_iter()
+            .filter(|config_file| config_file.software_id.as_deref() == Some(software_id))
+            .collect())
+    }
+
+    /// 依次部署关联到软件的所有配置文件，单个配置文件失败不影响其他配置文件
+    async fn deploy_linked_config_files(&self, software_id: &str) -> Result<Vec<LinkedDeployment>> {
+        let mut deployments = Vec::new();
+        for config_file in self.get_linked_config_files(software_id).await? {
+            let (status, message) = match self.deploy_config_file(&config_file.id).await {
+                Ok(Some(summary)) if summary.failed() > 0 => {
+                    (LinkedDeployStatus::Failed, format!("{} 个文件写入失败", summary.failed()))
+                }
+                Ok(Some(summary)) => (
+                    LinkedDeployStatus::Deployed,
+                    format!(
+                        "新增 {} 个，修改 {} 个，删除 {} 个文件",
+                        summary.added.len(),
+                        summary.changed.len(),
+                        summary.removed.len()
+                    ),
+                ),
+                // 部署过程中记录已被删除
+                Ok(None) => continue,
+                Err(e) if e.is::<NotApplicableError>() => (LinkedDeployStatus::NotApplicable, e.to_string()),
+                Err(e) => {
+                    warn!("自动部署配置文件失败 {} ({}): {}", config_file.name, config_file.id, e);
+                    (LinkedDeployStatus::Failed, e.to_string())
+                }
+            };
+            deployments.push(LinkedDeployment {
+                config_id: config_file.id,
+                name: config_file.name,
+                status,
+                message: Some(message),
+            });
+        }
+        Ok(deployments)
+    }
+
+    async fn export_config_files(&self, ids: Option<&[String]>, format: ArchiveFormat) -> Result<Option<Vec<u8>>> {
+        let mut config_files = self.get_all_config_files().await?;
+        if let Some(ids) = ids {
+            if ids.iter().any(|id| !config_files.iter().any(|config_file| &config_file.id == id)) {
+                return Ok(None);
+            }
+            config_files.retain(|config_file| ids.contains(&config_file.id));
+        }
+        for config_file in &config_files {
+            self.source_roots.check(Path::new(&config_file.source_path))?;
+        }
+        info!("导出 {} 个配置文件，格式: {}", config_files.len(), format.extension());
+        self.blocking(move |_| archive::export(&config_files, format)).await.map(Some)
+    }
+
+    async fn import_config_files(&self, data: &[u8], policy: ConflictPolicy, dry_run: bool) -> Result<ImportReport> {
+        let existing = self.get_all_config_files().await?;
+        let data = data.to_vec();
+        let mut plans = self
+            .blocking(move |service| {
+                let (manifest, sources) = archive::load(&data)?;
+                let context = ImportContext {
+                    existing: &existing,
+                    policy,
+                    import_dir: &service.import_dir,
+                    max_diff_size: service.max_diff_size,
+                };
+                archive::plan_import(manifest, sources, &context)
+            })
+            .await?;
+
+        // 写入任何源文件之前先排除不会被接受的记录，预演同样报告
+        for plan in &mut plans {
+            if matches!(plan.entry.action, ImportAction::Skip | ImportAction::Failed) {
+                continue;
+            }
+            if let Err(e) = self.check_import(&plan.config_file) {
+                plan.entry.action = ImportAction::Failed;
+                plan.entry.error = Some(e.to_string());
+            }
+        }
+
+        if !dry_run {
+            for plan in &mut plans {
+                if matches!(plan.entry.action, ImportAction::Skip | ImportAction::Failed) {
+                    continue;
+                }
+                info!("导入配置文件 {} ({})，动作: {:?}", plan.entry.name, plan.entry.id, plan.entry.action);
+                if let Err(e) = self.apply_import(plan).await {
+                    warn!("导入配置文件失败 {} ({}): {}", plan.entry.name, plan.entry.id, e);
+                    plan.entry.action = ImportAction::Failed;
+                    plan.entry.error = Some(e.to_string());
+                }
+            }
+        }
+        Ok(ImportReport { dry_run, entries: plans.into_iter().map(|plan| plan.entry).collect() })
+    }
+
+    async fn get_content(&self, id: &str, path: Option<&str>) -> Result<Option<SourceContent>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let relative = source_relative(&config_file, path)?;
+        let file = source_file_path(&config_file, &relative);
+        self.source_roots.check(&file)?;
+        let display = deployment::display_relative(&relative);
+        let stored = self
+            .blocking(move |service| {
+                if !file.is_file() {
+                    return Err(ContentError::Missing(display).into());
+                }
+                let size = fs::metadata(&file)?.len();
+                if size > service.max_content_size {
+                    return Err(ContentError::TooLarge { size, limit: service.max_content_size }.into());
+                }
+                Ok(fs::read(&file)?)
+            })
+            .await?;
+        let size = stored.len() as u64;
+        let decoded = if config_file.encrypted { None } else { content::decode(&stored) };
+        Ok(Some(SourceContent {
+            path: deployment::display_relative(&relative),
+            size,
+            sha256: content::sha256(&stored),
+            encoding: decoded.as_ref().map(|(encoding, _)| *encoding),
+            content: decoded.map(|(_, text)| text),
+            redacted: config_file.encrypted,
+        }))
+    }
+
+    async fn put_content(&self, id: &str, update: ContentUpdate) -> Result<Option<SourceContent>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        let relative = source_relative(&config_file, update.path.as_deref())?;
+
+        // 未指定编码时沿用现有文件的编码，加密文件的编码无法从密文识别
+        let encoding = match update.encoding {
+            Some(encoding) => encoding,
+            None if config_file.encrypted => TextEncoding::Utf8,
+            None => {
+                let file = source_file_path(&config_file, &relative);
+                self.source_roots.check(&file)?;
+                let existing = self.blocking(move |_| Ok(file.is_file().then(|| fs::read(&file).ok()).flatten())).await?;
+                existing
+                    .and_then(|existing| content::decode(&existing))
+                    .map_or(TextEncoding::Utf8, |(encoding, _)| encoding)
+            }
+        };
+        let plaintext = content::encode(&update.content, encoding);
+        self.write_source(&config_file, &relative, plaintext, update.sha256.as_deref()).await.map(Some)
+    }
+
+    async fn upload_content(
+        &self,
+        id: &str,
+        files: Vec<UploadedFile>,
+        expected: Option<String>,
+    ) -> Result<Option<Vec<SourceContent>>> {
+        let Some(config_file) = self.get_config_file_by_id(id).await? else {
+            return Ok(None);
+        };
+        if files.is_empty() {
+            return Err(ContentError::Invalid("没有上传文件".to_string()).into());
+        }
+        if !config_file.is_directory && files.len() > 1 {
+            return Err(ContentError::Invalid("单个文件的配置只能上传一个文件".to_string()).into());
+        }
+        if expected.is_some() && files.len() > 1 {
+            return Err(ContentError::Invalid("上传多个文件时不能指定 sha256".to_string()).into());
+        }
+
+        // 先校验所有路径，避免只写入一部分文件
+        let relatives = files
+            .iter()
+            .map(|file| source_relative(&config_file, Some(&file.path)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut results = Vec::with_capacity(files.len());
+        for (file, relative) in files.into_iter().zip(relatives) {
+            results.push(self.write_source(&config_file, &relative, file.content, expected.as_deref()).await?);
+        }
+        Ok(Some(results))
+    }
+
+    async fn get_audit_log(&self, config_id: Option<&str>) -> Result<Vec<AuditEntry>> {
+        self.audit.entries(config_id)
     }
 }
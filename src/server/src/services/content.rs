@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::utils::diff;
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+const UTF16_LE_BOM: &[u8] = &[0xff, 0xfe];
+const UTF16_BE_BOM: &[u8] = &[0xfe, 0xff];
+
+/// 源文件的文本编码，写回时保持原来的编码和 BOM
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16le,
+    Utf16be,
+}
+
+/// 通过接口读取的源文件内容
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceContent {
+    // 目录中为相对路径，单个文件为文件名
+    pub path: String,
+    pub size: u64,
+    // 保存的内容的哈希，写入时用于检测并发修改
+    pub sha256: String,
+    // 二进制文件或无法识别的编码为空，此时不返回内容
+    pub encoding: Option<TextEncoding>,
+    pub content: Option<String>,
+    // 加密配置文件的内容不通过接口返回
+    pub redacted: bool,
+}
+
+/// 写入源文件内容的请求
+#[derive(Debug, Deserialize)]
+pub struct ContentUpdate {
+    // 目录中文件的相对路径，单个文件可以省略
+    #[serde(default)]
+    pub path: Option<String>,
+    pub content: String,
+    // 读取时返回的哈希，省略时不检查并发修改
+    #[serde(default)]
+    pub sha256: Option<String>,
+    // 省略时沿用文件原来的编码，新文件使用 UTF-8
+    #[serde(default)]
+    pub encoding: Option<TextEncoding>,
+}
+
+/// 通过表单上传的文件，`path` 为目录中的相对路径
+#[derive(Debug)]
+pub struct UploadedFile {
+    pub path: String,
+    pub content: Vec<u8>,
+}
+
+/// 读写源文件内容时的错误
+#[derive(Debug, thiserror::Error)]
+pub enum ContentError {
+    #[error("文件大小 {size} 字节超过上限 {limit} 字节")]
+    TooLarge { size: u64, limit: u64 },
+    // 源文件在读取之后被修改，`current` 为当前的哈希，文件不存在时为空
+    #[error("源文件已被修改，当前 SHA-256: {}", .current.as_deref().unwrap_or("文件不存在"))]
+    Conflict { current: Option<String> },
+    #[error("源文件不存在: {0}")]
+    Missing(String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// 内容的 SHA-256
+pub fn sha256(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// 按 BOM 识别编码并解码，没有 BOM 时只接受 UTF-8，二进制内容返回 None
+pub fn decode(content: &[u8]) -> Option<(TextEncoding, String)> {
+    if let Some(rest) = content.strip_prefix(UTF8_BOM) {
+        return String::from_utf8(rest.to_vec()).ok().map(|text| (TextEncoding::Utf8Bom, text));
+    }
+    if let Some(rest) = content.strip_prefix(UTF16_LE_BOM) {
+        return decode_utf16(rest, u16::from_le_bytes).map(|text| (TextEncoding::Utf16le, text));
+    }
+    if let Some(rest) = content.strip_prefix(UTF16_BE_BOM) {
+        return decode_utf16(rest, u16::from_be_bytes).map(|text| (TextEncoding::Utf16be, text));
+    }
+    if diff::is_binary(content) {
+        return None;
+    }
+    String::from_utf8(content.to_vec()).ok().map(|text| (TextEncoding::Utf8, text))
+}
+
+fn decode_utf16(content: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !content.len().is_multiple_of(2) {
+        return None;
+    }
+    let units: Vec<u16> = content.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16(&units).ok()
+}
+
+/// 按指定编码输出文本，UTF-16 和带 BOM 的 UTF-8 会写入 BOM
+pub fn encode(text: &str, encoding: TextEncoding) -> Vec<u8> {
+    match encoding {
+        TextEncoding::Utf8 => text.as_bytes().to_vec(),
+        TextEncoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+        TextEncoding::Utf16le => {
+            let units = text.encode_utf16().flat_map(u16::to_le_bytes);
+            UTF16_LE_BOM.iter().copied().chain(units).collect()
+        }
+        TextEncoding::Utf16be => {
+            let units = text.encode_utf16().flat_map(u16::to_be_bytes);
+            UTF16_BE_BOM.iter().copied().chain(units).collect()
+        }
+    }
+}
+
+/// 写入前检查并发修改：`expected` 为读取时的哈希，空字符串表示文件应当尚不存在
+pub fn check_expected(expected: Option<&str>, current: Option<&str>) -> Result<(), ContentError> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let matches = match current {
+        Some(current) => current.eq_ignore_ascii_case(expected),
+        None => expected.is_empty(),
+    };
+    if !matches {
+        return Err(ContentError::Conflict { current: current.map(str::to_string) });
+    }
+    Ok(())
+}
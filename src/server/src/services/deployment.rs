@@ -0,0 +1,911 @@
+use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use crate::models::config_file::{BackupEntry, DeployMode, DeploymentRecord, FileResult, FileResultStatus};
+use crate::services::permissions::{PermissionCheck, TargetModes};
+use crate::services::target_policy;
+use crate::utils::diff::{self, FileDiff};
+use crate::utils::template::TemplateContext;
+
+/// 部署结果摘要，文件列表中的路径均为相对于部署目标的路径
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeploySummary {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub restored: Vec<String>,
+    pub unchanged: usize,
+    // 目标位置已存在指向其他位置的链接，未做修改
+    pub conflicts: Vec<String>,
+    // 链接部署时由本次部署管理的链接绝对路径
+    pub linked: Vec<String>,
+    pub backups: Vec<BackupEntry>,
+    // 复制部署后目标文件权限的设置结果
+    pub permissions: Vec<PermissionCheck>,
+    // 逐个文件的处理结果
+    pub files: Vec<FileResult>,
+}
+
+impl DeploySummary {
+    fn record(&mut self, path: String, status: FileResultStatus, backup: Option<&BackupEntry>, message: Option<String>) {
+        self.files.push(FileResult {
+            path,
+            status,
+            backup: backup.map(|entry| entry.backup.clone()),
+            message,
+        });
+    }
+
+    // 目录中单个文件处理失败，记录错误后继续处理其他文件
+    fn fail(&mut self, relative: &Path, error: anyhow::Error) {
+        let path = display_relative(relative);
+        warn!("处理文件 {} 失败: {}", path, error);
+        self.record(path, FileResultStatus::Failed, None, Some(error.to_string()));
+    }
+
+    /// 处理失败的文件数
+    pub fn failed(&self) -> usize {
+        self.files.iter().filter(|file| file.status == FileResultStatus::Failed).count()
+    }
+}
+
+/// 目录部署的文件过滤规则
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| anyhow::anyhow!("无效的匹配模式 {}: {}", pattern, e))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(include)?)
+        };
+        Ok(PathFilter {
+            include,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    /// 判断相对路径是否需要同步
+    pub fn matches(&self, relative: &Path) -> bool {
+        let included = self.include.as_ref().is_none_or(|include| include.is_match(relative));
+        included && !self.exclude.is_match(relative)
+    }
+}
+
+/// 部署时的备份位置，同一次部署的备份保存在同一目录中
+pub struct BackupLocation {
+    root: PathBuf,
+}
+
+impl BackupLocation {
+    pub fn new(backup_dir: &str) -> Self {
+        // 目录名附带随机后缀，同一时刻的多次部署不会互相覆盖
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        BackupLocation {
+            root: Path::new(backup_dir).join(format!("{}_{}", timestamp, &suffix[..8])),
+        }
+    }
+
+    /// 按相对路径备份文件
+    pub fn backup(&self, path: &Path, relative: &Path) -> Result<BackupEntry> {
+        let backup_path = self.root.join(relative);
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(path, &backup_path)?;
+
+        info!("已备份文件 {} 到 {}", path.display(), backup_path.display());
+        Ok(BackupEntry {
+            original: path.to_string_lossy().to_string(),
+            backup: backup_path.to_string_lossy().to_string(),
+        })
+    }
+
+    /// 备份目录中的所有文件，备份路径以 `relative` 为前缀
+    pub fn backup_tree(&self, path: &Path, relative: &Path) -> Result<Vec<BackupEntry>> {
+        let mut entries = Vec::new();
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let inner = entry.path().strip_prefix(path)?;
+            entries.push(self.backup(entry.path(), &relative.join(inner))?);
+        }
+        Ok(entries)
+    }
+}
+
+// 以 / 分隔的相对路径，便于在摘要中跨平台展示
+pub fn display_relative(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn same_content(source: &Path, target: &Path) -> Result<bool> {
+    let source_meta = fs::metadata(source)?;
+    let target_meta = fs::metadata(target)?;
+    if source_meta.len() != target_meta.len() {
+        return Ok(false);
+    }
+    Ok(fs::read(source)? == fs::read(target)?)
+}
+
+/// 部署单个文件，内容相同时跳过
+pub fn deploy_file(
+    source: &Path,
+    target: &Path,
+    backup: Option<&BackupLocation>,
+    modes: Option<&TargetModes>,
+) -> Result<DeploySummary> {
+    if !source.is_file() {
+        return Err(anyhow::anyhow!("源文件不存在: {}", source.display()));
+    }
+
+    let mut summary = DeploySummary::default();
+    let name = file_name(target)?;
+    copy_entry(EntrySource::Path(source), target, &name, backup, modes, &mut summary)?;
+
+    Ok(summary)
+}
+
+/// 部署涉及的文件：（显示用的相对路径, 源文件, 目标文件）
+///
+/// 目录按过滤规则列出源目录中的文件，单个文件以目标文件名标识
+pub fn file_pairs(
+    source: &Path,
+    target: &Path,
+    is_directory: bool,
+    filter: &PathFilter,
+) -> Result<Vec<(String, PathBuf, PathBuf)>> {
+    if !is_directory {
+        let name = file_name(target)?.to_string_lossy().to_string();
+        return Ok(vec![(name, source.to_path_buf(), target.to_path_buf())]);
+    }
+    Ok(collect_files(source, filter)?
+        .into_iter()
+        .map(|relative| (display_relative(&relative), source.join(&relative), target.join(&relative)))
+        .collect())
+}
+
+/// 路径的文件名部分
+pub fn file_name(path: &Path) -> Result<PathBuf> {
+    path.file_name()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("无法获取文件名: {}", path.display()))
+}
+
+// 待写入的内容：磁盘上的源文件，或只存在于内存中的内容（渲染或解密后的文件）
+enum EntrySource<'a> {
+    Path(&'a Path),
+    Bytes(&'a [u8]),
+}
+
+// 将源文件复制到目标位置，写入成功后在摘要中记录结果
+fn copy_entry(
+    source: EntrySource,
+    target: &Path,
+    relative: &Path,
+    backup: Option<&BackupLocation>,
+    modes: Option<&TargetModes>,
+    summary: &mut DeploySummary,
+) -> Result<()> {
+    let display = display_relative(relative);
+
+    if target.is_dir() {
+        return Err(anyhow::anyhow!("目标路径是目录，无法写入文件: {}", target.display()));
+    }
+
+    let exists = target.exists();
+    let mut backup_entry = None;
+    if exists {
+        let unchanged = match source {
+            EntrySource::Path(source) => same_content(source, target)?,
+            EntrySource::Bytes(content) => {
+                fs::metadata(target)?.len() == content.len() as u64 && fs::read(target)? == content
+            }
+        };
+        if unchanged {
+            summary.unchanged += 1;
+            summary.record(display, FileResultStatus::Skipped, None, None);
+            return Ok(());
+        }
+        if let Some(backup) = backup {
+            backup_entry = Some(backup.backup(target, relative)?);
+        }
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    replace_file(&source, target, modes)?;
+
+    if exists {
+        summary.changed.push(display.clone());
+    } else {
+        summary.added.push(display.clone());
+    }
+    let status = if backup_entry.is_some() { FileResultStatus::BackedUp } else { FileResultStatus::Written };
+    summary.record(display, status, backup_entry.as_ref(), None);
+    summary.backups.extend(backup_entry);
+    Ok(())
+}
+
+// 先写入同一目录中的临时文件，设置好权限和所有者之后再替换目标文件，
+// 目标位置不会出现权限仍为默认值的新内容，写入中断时也不会留下不完整的文件
+fn replace_file(source: &EntrySource, target: &Path, modes: Option<&TargetModes>) -> Result<()> {
+    let mode = modes.and_then(|modes| modes.mode(target));
+    let owner = modes.map_or((None, None), TargetModes::owner);
+    // 目标是符号链接时写入链接指向的文件，而不是替换链接本身
+    let target = match fs::symlink_metadata(target) {
+        Ok(metadata) if metadata.file_type().is_symlink() => target_policy::normalize(target),
+        _ => target.to_path_buf(),
+    };
+    let name = file_name(&target)?;
+    let temp = target.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), uuid::Uuid::new_v4().simple()));
+    let replaced = write_temp(source, &target, &temp, mode, owner).and_then(|_| Ok(fs::rename(&temp, &target)?));
+    if replaced.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    replaced
+}
+
+// 临时文件只有所有者可以读写，写完内容并修改所有者之后再设置为最终的权限。
+// 未指定权限时与直接复制或覆盖一致：复制沿用源文件的权限，内存中的内容沿用被替换文件的权限
+#[cfg(unix)]
+fn write_temp(source: &EntrySource, target: &Path, temp: &Path, mode: Option<u32>, owner: (Option<u32>, Option<u32>)) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+
+    let inherited = match source {
+        EntrySource::Path(source) => fs::metadata(source),
+        EntrySource::Bytes(_) => fs::metadata(target),
+    };
+    let mode = mode.or_else(|| inherited.ok().map(|metadata| metadata.mode() & 0o7777));
+    // 没有可沿用的权限时按 umask 创建，与新建文件的默认权限一致
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(if mode.is_some() { 0o600 } else { 0o666 })
+        .open(temp)?;
+    match source {
+        EntrySource::Path(source) => {
+            std::io::copy(&mut fs::File::open(source)?, &mut file)?;
+        }
+        EntrySource::Bytes(content) => file.write_all(content)?,
+    }
+    if let (uid, gid) = owner
+        && (uid.is_some() || gid.is_some())
+    {
+        std::os::unix::fs::fchown(&file, uid, gid)?;
+    }
+    if let Some(mode) = mode {
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_temp(source: &EntrySource, _target: &Path, temp: &Path, _mode: Option<u32>, _owner: (Option<u32>, Option<u32>)) -> Result<()> {
+    use std::io::Write;
+
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).open(temp)?;
+    match source {
+        EntrySource::Path(source) => {
+            std::io::copy(&mut fs::File::open(source)?, &mut file)?;
+        }
+        EntrySource::Bytes(content) => file.write_all(content)?,
+    }
+    Ok(())
+}
+
+/// 将源目录镜像到目标目录
+///
+/// 只处理通过 `filter` 的文件；`delete_extraneous` 为 true 时，
+/// 目标目录中通过过滤但源目录不存在的文件会被删除
+pub fn deploy_directory(
+    source: &Path,
+    target: &Path,
+    filter: &PathFilter,
+    delete_extraneous: bool,
+    backup: Option<&BackupLocation>,
+    modes: Option<&TargetModes>,
+) -> Result<DeploySummary> {
+    if !source.is_dir() {
+        return Err(anyhow::anyhow!("源目录不存在: {}", source.display()));
+    }
+    if target.exists() && !target.is_dir() {
+        return Err(anyhow::anyhow!("目标路径不是目录: {}", target.display()));
+    }
+
+    let mut summary = DeploySummary::default();
+    let mut source_files = HashSet::new();
+
+    for entry in WalkDir::new(source).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(source)?.to_path_buf();
+        if !filter.matches(&relative) {
+            continue;
+        }
+
+        // 单个文件失败时记录错误并继续处理其他文件
+        let copied = reject_symlinks(target, &relative)
+            .and_then(|_| copy_entry(EntrySource::Path(entry.path()), &target.join(&relative), &relative, backup, modes, &mut summary));
+        if let Err(e) = copied {
+            summary.fail(&relative, e);
+        }
+        source_files.insert(relative);
+    }
+
+    if delete_extraneous {
+        let extraneous = extraneous_files(target, &source_files, filter)?;
+        remove_files(target, &extraneous, backup, &mut summary)?;
+    }
+
+    info!(
+        "已同步目录 {} 到 {}: 新增 {}，修改 {}，删除 {}，未变化 {}",
+        source.display(),
+        target.display(),
+        summary.added.len(),
+        summary.changed.len(),
+        summary.removed.len(),
+        summary.unchanged
+    );
+    Ok(summary)
+}
+
+// 目录部署不跟随目标目录中的符号链接，否则写入会落到已检查的目标目录之外
+fn reject_symlinks(root: &Path, relative: &Path) -> Result<()> {
+    let mut path = root.to_path_buf();
+    for component in relative.components() {
+        path.push(component);
+        if fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_symlink()) {
+            return Err(anyhow::anyhow!("目标目录中的 {} 是符号链接，不会跟随写入", path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// 模板中引用了未定义的变量，每项为 `文件: 变量名`
+#[derive(Debug, thiserror::Error)]
+#[error("模板变量未定义: {}", .missing.join(", "))]
+pub struct TemplateRenderError {
+    pub missing: Vec<String>,
+}
+
+// 目标目录中通过过滤但不在 `keep` 中的文件
+fn extraneous_files(target: &Path, keep: &HashSet<PathBuf>, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    // 先收集待删除的文件，避免遍历过程中修改目录
+    Ok(collect_files(target, filter)?
+        .into_iter()
+        .filter(|relative| !keep.contains(relative))
+        .collect())
+}
+
+fn remove_files(
+    root: &Path,
+    relatives: &[PathBuf],
+    backup: Option<&BackupLocation>,
+    summary: &mut DeploySummary,
+) -> Result<()> {
+    for relative in relatives {
+        let path = root.join(relative);
+        if !path.is_file() {
+            continue;
+        }
+        let backup_entry = backup.map(|backup| backup.backup(&path, relative)).transpose()?;
+        fs::remove_file(&path)?;
+        summary.removed.push(display_relative(relative));
+        summary.record(display_relative(relative), FileResultStatus::Removed, backup_entry.as_ref(), None);
+        summary.backups.extend(backup_entry);
+    }
+    Ok(())
+}
+
+/// 只存在于内存中的文件内容，例如渲染后的模板或解密后的配置文件
+///
+/// 目录中的文件以相对路径标识，单个文件以文件名标识
+#[derive(Clone)]
+pub struct MemoryFile {
+    pub relative: PathBuf,
+    pub content: Vec<u8>,
+}
+
+/// 读取源文件或源目录中通过过滤的文件
+pub fn read_files(source: &Path, is_directory: bool, filter: &PathFilter) -> Result<Vec<MemoryFile>> {
+    if !is_directory {
+        if !source.is_file() {
+            return Err(anyhow::anyhow!("源文件不存在: {}", source.display()));
+        }
+        return Ok(vec![MemoryFile {
+            relative: file_name(source)?,
+            content: fs::read(source)?,
+        }]);
+    }
+
+    if !source.is_dir() {
+        return Err(anyhow::anyhow!("源目录不存在: {}", source.display()));
+    }
+    collect_files(source, filter)?
+        .into_iter()
+        .map(|relative| {
+            let content = fs::read(source.join(&relative))?;
+            Ok(MemoryFile { relative, content })
+        })
+        .collect()
+}
+
+/// 在内存中渲染模板文件
+///
+/// 二进制文件原样保留。所有文件中缺失的变量会一并通过
+/// [`TemplateRenderError`] 报告，此时不会产生任何输出
+pub fn render_files(files: Vec<MemoryFile>, context: &TemplateContext) -> Result<Vec<MemoryFile>> {
+    let mut rendered = Vec::with_capacity(files.len());
+    let mut missing = Vec::new();
+    for file in files {
+        if diff::is_binary(&file.content) {
+            rendered.push(file);
+            continue;
+        }
+        let text = String::from_utf8_lossy(&file.content);
+        let display = display_relative(&file.relative);
+        let names = context
+            .missing_variables(&text)
+            .map_err(|e| anyhow::anyhow!("{}: {}", display, e))?;
+        if !names.is_empty() {
+            missing.extend(names.into_iter().map(|name| format!("{}: {}", display, name)));
+            continue;
+        }
+        let content = context.render(&text)?.into_bytes();
+        rendered.push(MemoryFile { relative: file.relative, content });
+    }
+    if !missing.is_empty() {
+        return Err(TemplateRenderError { missing }.into());
+    }
+    Ok(rendered)
+}
+
+/// 将内存中的文件部署到目标位置，规则与 [`deploy_directory`] 和 [`deploy_file`] 相同
+pub fn deploy_files(
+    files: &[MemoryFile],
+    target: &Path,
+    is_directory: bool,
+    filter: &PathFilter,
+    delete_extraneous: bool,
+    backup: Option<&BackupLocation>,
+    modes: Option<&TargetModes>,
+) -> Result<DeploySummary> {
+    let removed = if is_directory && delete_extraneous {
+        let keep = files.iter().map(|file| file.relative.clone()).collect();
+        extraneous_files(target, &keep, filter)?
+    } else {
+        Vec::new()
+    };
+    write_files(files, target, is_directory, &removed, backup, modes)
+}
+
+/// 写入内存中的文件，并删除 `removed` 中列出的相对路径
+///
+/// 单个文件时直接写入 `target`，`files` 中只使用第一项
+pub fn write_files(
+    files: &[MemoryFile],
+    target: &Path,
+    is_directory: bool,
+    removed: &[PathBuf],
+    backup: Option<&BackupLocation>,
+    modes: Option<&TargetModes>,
+) -> Result<DeploySummary> {
+    let mut summary = DeploySummary::default();
+
+    if !is_directory {
+        if let Some(file) = files.first() {
+            let name = file_name(target)?;
+            copy_entry(EntrySource::Bytes(&file.content), target, &name, backup, modes, &mut summary)?;
+        }
+        return Ok(summary);
+    }
+
+    if target.exists() && !target.is_dir() {
+        return Err(anyhow::anyhow!("目标路径不是目录: {}", target.display()));
+    }
+    for file in files {
+        let path = target.join(&file.relative);
+        let written = reject_symlinks(target, &file.relative)
+            .and_then(|_| copy_entry(EntrySource::Bytes(&file.content), &path, &file.relative, backup, modes, &mut summary));
+        if let Err(e) = written {
+            summary.fail(&file.relative, e);
+        }
+    }
+    remove_files(target, removed, backup, &mut summary)?;
+
+    info!(
+        "已写入 {}: 新增 {}，修改 {}，删除 {}，未变化 {}",
+        target.display(),
+        summary.added.len(),
+        summary.changed.len(),
+        summary.removed.len(),
+        summary.unchanged
+    );
+    Ok(summary)
+}
+
+/// 撤销一次写入：删除新增的文件，并从备份恢复被修改和删除的文件
+///
+/// 只用于写入时为所有被覆盖的文件都做了备份的情况
+pub fn rollback(target: &Path, is_directory: bool, summary: &DeploySummary) -> Result<()> {
+    for added in &summary.added {
+        let path = if is_directory { target.join(added) } else { target.to_path_buf() };
+        if path.is_file() {
+            fs::remove_file(&path)?;
+        }
+    }
+    for entry in &summary.backups {
+        fs::copy(&entry.backup, &entry.original)?;
+    }
+    info!("已撤销对 {} 的写入", target.display());
+    Ok(())
+}
+
+/// 比较两组内存中的文件，以 `old` 为旧版本、`new` 为新版本
+///
+/// `delete_extraneous` 为 false 时不报告只存在于 `old` 中的文件
+pub fn diff_file_sets(
+    old: &[MemoryFile],
+    new: &[MemoryFile],
+    delete_extraneous: bool,
+    max_diff_size: u64,
+) -> Vec<FileDiff> {
+    let old: std::collections::BTreeMap<&Path, &[u8]> =
+        old.iter().map(|file| (file.relative.as_path(), file.content.as_slice())).collect();
+    let new: std::collections::BTreeMap<&Path, &[u8]> =
+        new.iter().map(|file| (file.relative.as_path(), file.content.as_slice())).collect();
+
+    let mut relatives: Vec<&Path> = new.keys().copied().collect();
+    if delete_extraneous {
+        relatives.extend(old.keys().copied().filter(|relative| !new.contains_key(relative)));
+        relatives.sort();
+    }
+
+    relatives
+        .into_iter()
+        .filter_map(|relative| {
+            diff::diff_contents(
+                old.get(relative).copied(),
+                new.get(relative).copied(),
+                &display_relative(relative),
+                max_diff_size,
+            )
+        })
+        .collect()
+}
+
+/// 预览将内存中的文件部署到 `to` 时会发生的变化，规则与 [`preview_sync`] 相同
+pub fn preview_files(
+    files: &[MemoryFile],
+    to: &Path,
+    is_directory: bool,
+    filter: &PathFilter,
+    delete_extraneous: bool,
+    max_diff_size: u64,
+) -> Result<Vec<FileDiff>> {
+    if !is_directory {
+        // 单个文件以目标文件名展示差异
+        let name = file_name(to)?;
+        let new: Vec<MemoryFile> = files
+            .iter()
+            .take(1)
+            .map(|file| MemoryFile { relative: name.clone(), content: file.content.clone() })
+            .collect();
+        let old = if to.is_file() { read_files(to, false, filter)? } else { Vec::new() };
+        return Ok(diff_file_sets(&old, &new, false, max_diff_size));
+    }
+
+    let old = if to.is_dir() { read_files(to, true, filter)? } else { Vec::new() };
+    Ok(diff_file_sets(&old, files, delete_extraneous, max_diff_size))
+}
+
+/// 收集目录中通过过滤的文件相对路径
+pub fn collect_files(root: &Path, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !root.is_dir() {
+        return Ok(files);
+    }
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root)?.to_path_buf();
+        if filter.matches(&relative) {
+            files.push(relative);
+        }
+    }
+    Ok(files)
+}
+
+/// 预览将 `from` 同步到 `to` 时 `to` 会发生的变化，不修改任何文件
+///
+/// 差异以 `to` 的当前内容为旧版本、`from` 的内容为新版本
+pub fn preview_sync(
+    from: &Path,
+    to: &Path,
+    is_directory: bool,
+    filter: &PathFilter,
+    delete_extraneous: bool,
+    max_diff_size: u64,
+) -> Result<Vec<FileDiff>> {
+    if !is_directory {
+        if !from.is_file() {
+            return Err(anyhow::anyhow!("文件不存在: {}", from.display()));
+        }
+        let name = to
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow::anyhow!("无法获取文件名: {}", to.display()))?;
+        let old = to.is_file().then_some(to);
+        return Ok(diff::diff_files(old, Some(from), &name, max_diff_size)?.into_iter().collect());
+    }
+
+    if !from.is_dir() {
+        return Err(anyhow::anyhow!("目录不存在: {}", from.display()));
+    }
+    let mut relatives = collect_files(from, filter)?;
+    if delete_extraneous {
+        let existing: HashSet<PathBuf> = relatives.iter().cloned().collect();
+        relatives.extend(
+            collect_files(to, filter)?
+                .into_iter()
+                .filter(|relative| !existing.contains(relative)),
+        );
+        relatives.sort();
+    }
+
+    let mut changes = Vec::new();
+    for relative in relatives {
+        let old = to.join(&relative);
+        let new = from.join(&relative);
+        let old = old.is_file().then_some(old.as_path());
+        let new = new.is_file().then_some(new.as_path());
+        if let Some(change) = diff::diff_files(old, new, &display_relative(&relative), max_diff_size)? {
+            changes.push(change);
+        }
+    }
+    Ok(changes)
+}
+
+/// 判断两个路径是否指向同一个文件（链接会被跟随）
+#[cfg(unix)]
+pub fn is_same_file(a: &Path, b: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let a = fs::metadata(a)?;
+    let b = fs::metadata(b)?;
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+// 无法读取文件标识的平台上按内容判断
+#[cfg(not(unix))]
+pub fn is_same_file(a: &Path, b: &Path) -> Result<bool> {
+    same_content(a, b)
+}
+
+fn create_symlink(source: &Path, target: &Path) -> Result<()> {
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(source, target)?;
+    #[cfg(windows)]
+    {
+        if source.is_dir() {
+            std::os::windows::fs::symlink_dir(source, target)?;
+        } else {
+            std::os::windows::fs::symlink_file(source, target)?;
+        }
+    }
+    Ok(())
+}
+
+// 删除链接本身，不影响链接指向的内容
+fn remove_link(path: &Path) -> Result<()> {
+    // Windows 上指向目录的符号链接需要按目录删除
+    if cfg!(windows) && fs::metadata(path).map(|meta| meta.is_dir()).unwrap_or(false) {
+        fs::remove_dir(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+// 判断目标位置是否是指向 source 的符号链接
+fn links_to(target: &Path, source: &Path) -> bool {
+    fs::canonicalize(target).ok().as_deref() == Some(source)
+}
+
+// 在目标位置创建指向源文件的链接，已有的普通文件会先备份
+fn link_entry(
+    source: &Path,
+    target: &Path,
+    relative: &Path,
+    mode: DeployMode,
+    backup: &BackupLocation,
+    summary: &mut DeploySummary,
+) -> Result<()> {
+    let display = display_relative(relative);
+    let backups_before = summary.backups.len();
+
+    match fs::symlink_metadata(target) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            summary.added.push(display);
+        }
+        Err(e) => return Err(e.into()),
+        Ok(meta) if meta.file_type().is_symlink() => {
+            if mode == DeployMode::Symlink && links_to(target, source) {
+                summary.unchanged += 1;
+                summary.linked.push(target.to_string_lossy().to_string());
+                summary.record(display, FileResultStatus::Skipped, None, None);
+                return Ok(());
+            }
+            let destination = fs::read_link(target)?;
+            summary.conflicts.push(format!("{} -> {}", display, destination.display()));
+            let message = format!("目标位置已存在指向 {} 的链接", destination.display());
+            summary.record(display, FileResultStatus::Skipped, None, Some(message));
+            return Ok(());
+        }
+        Ok(meta) => {
+            if mode == DeployMode::Hardlink && meta.is_file() && is_same_file(source, target)? {
+                summary.unchanged += 1;
+                summary.linked.push(target.to_string_lossy().to_string());
+                summary.record(display, FileResultStatus::Skipped, None, None);
+                return Ok(());
+            }
+            // 普通文件或目录在替换为链接前总是先备份
+            if meta.is_dir() {
+                summary.backups.extend(backup.backup_tree(target, relative)?);
+                fs::remove_dir_all(target)?;
+            } else {
+                summary.backups.push(backup.backup(target, relative)?);
+                fs::remove_file(target)?;
+            }
+            summary.changed.push(display);
+        }
+    }
+
+    match mode {
+        DeployMode::Symlink => create_symlink(source, target)?,
+        DeployMode::Hardlink => fs::hard_link(source, target)?,
+        DeployMode::Copy => return Err(anyhow::anyhow!("复制模式不创建链接")),
+    }
+    summary.linked.push(target.to_string_lossy().to_string());
+    let replaced = summary.backups.len() > backups_before;
+    let status = if replaced { FileResultStatus::BackedUp } else { FileResultStatus::Written };
+    let backup_entry = summary.backups[backups_before..].first().cloned();
+    summary.record(display_relative(relative), status, backup_entry.as_ref(), None);
+    Ok(())
+}
+
+/// 以链接方式部署
+///
+/// 符号链接模式下目标路径本身链接到源文件或源目录；
+/// 硬链接模式下目录中的每个文件分别链接，目录本身不会被链接
+pub fn deploy_link(
+    source: &Path,
+    target: &Path,
+    mode: DeployMode,
+    filter: Option<&PathFilter>,
+    backup: &BackupLocation,
+) -> Result<DeploySummary> {
+    // 链接必须指向源文件的绝对路径
+    let source = fs::canonicalize(source)
+        .map_err(|e| anyhow::anyhow!("源路径不存在: {}: {}", source.display(), e))?;
+    let name = file_name(target)?;
+
+    let mut summary = DeploySummary::default();
+    match (mode, filter) {
+        (DeployMode::Hardlink, Some(filter)) if source.is_dir() => {
+            if target.exists() && !target.is_dir() {
+                return Err(anyhow::anyhow!("目标路径不是目录: {}", target.display()));
+            }
+            for entry in WalkDir::new(&source).sort_by_file_name() {
+                let entry = entry?;
+                if entry.file_type().is_dir() {
+                    continue;
+                }
+                let relative = entry.path().strip_prefix(&source)?.to_path_buf();
+                if filter.matches(&relative) {
+                    link_entry(entry.path(), &target.join(&relative), &relative, mode, backup, &mut summary)?;
+                }
+            }
+        }
+        (DeployMode::Hardlink, _) if source.is_dir() => {
+            return Err(anyhow::anyhow!("硬链接模式不支持链接目录本身: {}", source.display()));
+        }
+        _ => link_entry(&source, target, &name, mode, backup, &mut summary)?,
+    }
+
+    info!(
+        "已链接 {} 到 {}: 新增 {}，替换 {}，冲突 {}",
+        source.display(),
+        target.display(),
+        summary.added.len(),
+        summary.changed.len(),
+        summary.conflicts.len()
+    );
+    Ok(summary)
+}
+
+/// 撤销链接部署：删除本次部署创建的链接，并恢复部署前备份的文件
+pub fn undo_link_deployment(source: &Path, record: &DeploymentRecord) -> Result<DeploySummary> {
+    if record.mode == DeployMode::Copy {
+        return Err(anyhow::anyhow!("复制模式的部署无法撤销，请从备份中恢复"));
+    }
+    let source = fs::canonicalize(source).ok();
+    let mut summary = DeploySummary::default();
+
+    for link in &record.linked {
+        let path = Path::new(link);
+        let Ok(meta) = fs::symlink_metadata(path) else {
+            continue;
+        };
+        // 只删除仍然指向托管源文件的链接，已被替换的文件保持不变
+        let still_linked = match (record.mode, &source) {
+            (DeployMode::Symlink, Some(source)) => meta.file_type().is_symlink() && links_to(path, source),
+            (DeployMode::Hardlink, Some(source)) if source.is_dir() => {
+                let relative = path.strip_prefix(&record.target_path).unwrap_or(path);
+                meta.is_file() && is_same_file(&source.join(relative), path).unwrap_or(false)
+            }
+            (DeployMode::Hardlink, Some(source)) => meta.is_file() && is_same_file(source, path).unwrap_or(false),
+            _ => false,
+        };
+        if !still_linked {
+            summary.conflicts.push(link.clone());
+            continue;
+        }
+        remove_link(path)?;
+        summary.removed.push(link.clone());
+    }
+
+    for entry in &record.backups {
+        let original = Path::new(&entry.original);
+        if fs::symlink_metadata(original).is_ok() {
+            summary.conflicts.push(entry.original.clone());
+            continue;
+        }
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&entry.backup, original)?;
+        summary.restored.push(entry.original.clone());
+    }
+
+    info!(
+        "已撤销部署 {}: 删除链接 {}，恢复文件 {}",
+        record.target_path,
+        summary.removed.len(),
+        summary.restored.len()
+    );
+    Ok(summary)
+}
@@ -0,0 +1,208 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::models::config_file::{ConfigFile, DeployMode, DeployedFile};
+use crate::services::backups::file_sha256;
+use crate::services::config_files::{ConfigFileService, ConfigFileServiceImpl};
+use crate::services::deployment::{self, PathFilter};
+
+// 最近一次扫描的结果，供仪表盘直接读取
+static DRIFT_CACHE: Mutex<Option<DriftScan>> = Mutex::new(None);
+
+/// 部署后目标位置和源文件的变化状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftStatus {
+    InSync,
+    // 目标文件在部署后被修改
+    ModifiedLocally,
+    // 目标文件在部署后被删除
+    Missing,
+    // 托管的源文件在部署后被修改，需要重新部署
+    SourceChanged,
+    // 目标文件和源文件都被修改
+    BothChanged,
+    // 尚未部署，或部署时没有记录文件哈希
+    NotDeployed,
+    // 检测过程中出错
+    Unknown,
+}
+
+impl DriftStatus {
+    fn from_changes(local: bool, source: bool) -> Self {
+        match (local, source) {
+            (false, false) => DriftStatus::InSync,
+            (true, false) => DriftStatus::ModifiedLocally,
+            (false, true) => DriftStatus::SourceChanged,
+            (true, true) => DriftStatus::BothChanged,
+        }
+    }
+}
+
+/// 单个文件的变化，只列出不同步的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDrift {
+    pub path: String,
+    pub status: DriftStatus,
+}
+
+/// 单个配置文件记录的检测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftReport {
+    pub id: String,
+    pub name: String,
+    pub status: DriftStatus,
+    pub target_path: Option<String>,
+    pub deployed_at: Option<DateTime<Utc>>,
+    pub files: Vec<FileDrift>,
+    pub error: Option<String>,
+}
+
+/// 一次扫描所有记录的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftScan {
+    pub checked_at: DateTime<Utc>,
+    pub entries: Vec<DriftReport>,
+}
+
+/// 记录部署完成后源文件和目标文件的哈希，目标文件不存在（例如链接冲突）时跳过
+pub fn snapshot(source: &Path, target: &Path, is_directory: bool, filter: &PathFilter) -> Result<Vec<DeployedFile>> {
+    let pairs = deployment::file_pairs(source, target, is_directory, filter)?;
+    let mut files = Vec::new();
+    for (path, source, target) in pairs {
+        if !target.is_file() {
+            continue;
+        }
+        files.push(DeployedFile {
+            path,
+            source_sha256: file_sha256(&source)?,
+            target_sha256: file_sha256(&target)?,
+        });
+    }
+    Ok(files)
+}
+
+/// 对比最近一次部署记录的哈希，检测目标位置和源文件的变化
+pub fn check(config_file: &ConfigFile) -> DriftReport {
+    let record = config_file.last_deployment.as_ref();
+    let mut report = DriftReport {
+        id: config_file.id.clone(),
+        name: config_file.name.clone(),
+        status: DriftStatus::NotDeployed,
+        target_path: record.map(|record| record.target_path.clone()),
+        deployed_at: record.map(|record| record.deployed_at),
+        files: Vec::new(),
+        error: None,
+    };
+    match record {
+        Some(record) if !record.files.is_empty() => match check_files(config_file, &record.files, &record.target_path) {
+            Ok(files) => {
+                report.status = overall_status(&files);
+                report.files = files;
+            }
+            Err(e) => {
+                report.status = DriftStatus::Unknown;
+                report.error = Some(e.to_string());
+            }
+        },
+        _ => {}
+    }
+    report
+}
+
+fn check_files(config_file: &ConfigFile, recorded: &[DeployedFile], target_path: &str) -> Result<Vec<FileDrift>> {
+    let source = Path::new(&config_file.source_path);
+    let target = Path::new(target_path);
+    let locate = |file: &DeployedFile| {
+        if config_file.is_directory {
+            (source.join(&file.path), target.join(&file.path))
+        } else {
+            (source.to_path_buf(), target.to_path_buf())
+        }
+    };
+
+    let mut files = Vec::new();
+    for file in recorded {
+        let (source_file, target_file) = locate(file);
+        let status = if !target_file.is_file() {
+            DriftStatus::Missing
+        } else if config_file.deploy_mode != DeployMode::Copy {
+            // 链接仍指向源文件时，源文件的修改会直接生效
+            let linked = source_file.is_file() && deployment::is_same_file(&source_file, &target_file)?;
+            if linked { DriftStatus::InSync } else { DriftStatus::ModifiedLocally }
+        } else {
+            let local = file_sha256(&target_file)? != file.target_sha256;
+            let source = !source_file.is_file() || file_sha256(&source_file)? != file.source_sha256;
+            DriftStatus::from_changes(local, source)
+        };
+        if status != DriftStatus::InSync {
+            files.push(FileDrift { path: file.path.clone(), status });
+        }
+    }
+
+    // 部署后源目录中新增的文件
+    if config_file.is_directory {
+        let filter = PathFilter::new(&config_file.include, &config_file.exclude)?;
+        let known: HashSet<&str> = recorded.iter().map(|file| file.path.as_str()).collect();
+        for relative in deployment::collect_files(source, &filter)? {
+            let path = deployment::display_relative(&relative);
+            if !known.contains(path.as_str()) {
+                files.push(FileDrift { path, status: DriftStatus::SourceChanged });
+            }
+        }
+    }
+    Ok(files)
+}
+
+// 任一文件缺失时整体为缺失，其余按目标和源文件是否有变化合并
+fn overall_status(files: &[FileDrift]) -> DriftStatus {
+    if files.iter().any(|file| file.status == DriftStatus::Missing) {
+        return DriftStatus::Missing;
+    }
+    let has = |statuses: [DriftStatus; 2]| files.iter().any(|file| statuses.contains(&file.status));
+    DriftStatus::from_changes(
+        has([DriftStatus::ModifiedLocally, DriftStatus::BothChanged]),
+        has([DriftStatus::SourceChanged, DriftStatus::BothChanged]),
+    )
+}
+
+/// 最近一次扫描的结果
+pub fn cached() -> Option<DriftScan> {
+    DRIFT_CACHE.lock().unwrap().clone()
+}
+
+/// 保存扫描结果
+pub fn store(scan: &DriftScan) {
+    *DRIFT_CACHE.lock().unwrap() = Some(scan.clone());
+}
+
+/// 按配置的间隔在后台定期扫描，未设置间隔时不启动
+pub fn start_schedule() {
+    let Some(minutes) = crate::CONFIG.config_files.drift.interval_minutes.filter(|minutes| *minutes > 0) else {
+        return;
+    };
+    info!("启动配置文件变化检测，间隔 {} 分钟", minutes);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(minutes * 60));
+        loop {
+            interval.tick().await;
+            let service = ConfigFileServiceImpl::new(&crate::CONFIG.config_files);
+            match service.check_drift(true).await {
+                Ok(scan) => {
+                    let drifted = scan
+                        .entries
+                        .iter()
+                        .filter(|entry| !matches!(entry.status, DriftStatus::InSync | DriftStatus::NotDeployed))
+                        .count();
+                    info!("配置文件变化检测完成，{} 项不同步", drifted);
+                }
+                Err(e) => warn!("配置文件变化检测失败: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,259 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use crate::config::GitStoreSettings;
+use crate::utils::command::{CommandOutput, CommandRunner};
+
+// 同一进程内的 git 操作需要串行执行，避免争用仓库的 index.lock
+static GIT_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// 单条 git 命令的最长执行时间，推送和拉取可能需要访问网络
+const GIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+const LITERAL_PREFIX: &str = ":(literal)";
+
+// git log 输出中字段之间的分隔符
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// 配置仓库中的一个版本
+#[derive(Debug, Clone, Serialize)]
+pub struct Revision {
+    pub id: String,
+    pub author: String,
+    pub date: DateTime<Utc>,
+    pub message: String,
+}
+
+/// 以 git 仓库保存托管的源文件，通过本地 `git` 命令操作
+#[derive(Clone)]
+pub struct GitStore {
+    repo_dir: PathBuf,
+    remote: Option<String>,
+    branch: String,
+    auto_push: bool,
+    author_name: String,
+    author_email: String,
+}
+
+// 转换为绝对路径并去掉其中的 `.`，不访问文件系统
+fn absolute(path: &Path) -> PathBuf {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn parse_revision(line: &str) -> Result<Revision> {
+    let fields: Vec<&str> = line.splitn(4, FIELD_SEPARATOR).collect();
+    let [id, author, date, message] = fields[..] else {
+        return Err(anyhow::anyhow!("无法解析 git 日志: {}", line));
+    };
+    Ok(Revision {
+        id: id.to_string(),
+        author: author.to_string(),
+        date: DateTime::parse_from_rfc3339(date)?.with_timezone(&Utc),
+        message: message.to_string(),
+    })
+}
+
+impl GitStore {
+    /// 未启用版本管理时返回 None
+    pub fn new(settings: &GitStoreSettings) -> Option<Self> {
+        settings.enabled.then(|| GitStore {
+            repo_dir: absolute(Path::new(&settings.repo_dir)),
+            remote: settings.remote.clone(),
+            branch: settings.branch.clone(),
+            auto_push: settings.auto_push,
+            author_name: settings.author_name.clone(),
+            author_email: settings.author_email.clone(),
+        })
+    }
+
+    async fn run(&self, args: &[&str]) -> Result<CommandOutput> {
+        let user_name = format!("user.name={}", self.author_name);
+        let user_email = format!("user.email={}", self.author_email);
+        let mut argv = vec!["git", "-c", &user_name, "-c", &user_email];
+        argv.extend_from_slice(args);
+        CommandRunner::argv(&argv)
+            .current_dir(&self.repo_dir)
+            // 需要认证时直接失败，不等待终端输入
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .timeout(GIT_TIMEOUT)
+            .run()
+            .await
+    }
+
+    async fn git(&self, args: &[&str]) -> Result<String> {
+        self.run(args)
+            .await?
+            .into_result()
+            .map_err(|e| anyhow::anyhow!("git {} 失败: {}", args.first().unwrap_or(&""), e))
+    }
+
+    // 仓库目录不是 git 仓库时初始化
+    async fn ensure_repo(&self) -> Result<()> {
+        if self.repo_dir.join(".git").exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.repo_dir)?;
+        self.git(&["init", "-q", "-b", &self.branch]).await?;
+        info!("已初始化配置仓库: {}", self.repo_dir.display());
+        Ok(())
+    }
+
+    /// 源路径在仓库中的路径规格，不在仓库目录中时返回 None
+    ///
+    /// 路径规格按字面匹配，文件名中的 * 等字符不作为通配符
+    fn pathspec(&self, source: &Path) -> Option<String> {
+        let relative = absolute(source).strip_prefix(&self.repo_dir).ok()?.to_path_buf();
+        if relative.as_os_str().is_empty() {
+            return None;
+        }
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Some(format!("{}{}", LITERAL_PREFIX, relative))
+    }
+
+    fn require_pathspec(&self, source: &Path) -> Result<String> {
+        self.pathspec(source).ok_or_else(|| {
+            anyhow::anyhow!("源路径不在配置仓库 {} 中: {}", self.repo_dir.display(), source.display())
+        })
+    }
+
+    async fn has_commits(&self) -> Result<bool> {
+        if !self.repo_dir.join(".git").exists() {
+            return Ok(false);
+        }
+        Ok(self.run(&["rev-parse", "--verify", "-q", "HEAD"]).await?.success())
+    }
+
+    async fn head(&self) -> Result<Revision> {
+        let format = "--format=%H%x1f%an%x1f%aI%x1f%s";
+        parse_revision(self.git(&["log", "-1", format]).await?.trim_end())
+    }
+
+    /// 提交源路径的变化，源路径不在仓库中或没有变化时返回 None
+    pub async fn commit(&self, source: &Path, message: &str) -> Result<Option<Revision>> {
+        let Some(pathspec) = self.pathspec(source) else {
+            return Ok(None);
+        };
+        let _guard = GIT_LOCK.lock().await;
+        self.commit_locked(&pathspec, message).await
+    }
+
+    async fn commit_locked(&self, pathspec: &str, message: &str) -> Result<Option<Revision>> {
+        self.ensure_repo().await?;
+        self.git(&["add", "-A", "--", pathspec]).await?;
+        if self.git(&["status", "--porcelain", "--", pathspec]).await?.trim().is_empty() {
+            return Ok(None);
+        }
+        self.git(&["commit", "-q", "-m", message, "--", pathspec]).await?;
+        let revision = self.head().await?;
+        info!("已提交配置仓库 {}: {}", &revision.id[..8], message);
+
+        if self.auto_push
+            && let Err(e) = self.push_locked().await
+        {
+            warn!("自动推送配置仓库失败: {}", e);
+        }
+        Ok(Some(revision))
+    }
+
+    /// 列出修改过源路径的版本，最新的在前
+    pub async fn history(&self, source: &Path) -> Result<Vec<Revision>> {
+        let pathspec = self.require_pathspec(source)?;
+        let _guard = GIT_LOCK.lock().await;
+        if !self.has_commits().await? {
+            return Ok(Vec::new());
+        }
+        let output = self
+            .git(&["log", "--format=%H%x1f%an%x1f%aI%x1f%s", "--", &pathspec])
+            .await?;
+        output.lines().filter(|line| !line.is_empty()).map(parse_revision).collect()
+    }
+
+    /// 将源路径恢复到指定版本的内容并提交，返回恢复后的最新版本
+    pub async fn revert(&self, source: &Path, revision: &str, message: &str) -> Result<Revision> {
+        let pathspec = self.require_pathspec(source)?;
+        // 版本号不能被当作命令行选项
+        if revision.is_empty() || revision.starts_with('-') || revision.chars().any(|c| c.is_whitespace()) {
+            return Err(anyhow::anyhow!("无效的版本号: {}", revision));
+        }
+
+        let _guard = GIT_LOCK.lock().await;
+        if !self.has_commits().await? {
+            return Err(anyhow::anyhow!("配置仓库中还没有任何版本"));
+        }
+        let commit = format!("{}^{{commit}}", revision);
+        let output = self.run(&["rev-parse", "--verify", "-q", &commit]).await?;
+        if !output.success() {
+            return Err(anyhow::anyhow!("版本不存在: {}", revision));
+        }
+        let id = output.stdout.trim().to_string();
+
+        // ls-tree 不支持路径规格的魔法前缀，使用去掉前缀的相对路径
+        let relative = pathspec.trim_start_matches(LITERAL_PREFIX);
+        let files = self.git(&["ls-tree", "-r", "--name-only", &id, "--", relative]).await?;
+        if files.trim().is_empty() {
+            return Err(anyhow::anyhow!("版本 {} 中不存在源文件: {}", revision, source.display()));
+        }
+
+        // 同时恢复工作区和暂存区，该版本中不存在的文件会被删除
+        let source_arg = format!("--source={}", id);
+        self.git(&["restore", &source_arg, "--staged", "--worktree", "--", &pathspec]).await?;
+        match self.commit_locked(&pathspec, message).await? {
+            Some(revision) => Ok(revision),
+            None => self.head().await,
+        }
+    }
+
+    fn remote(&self) -> Result<&str> {
+        self.remote.as_deref().ok_or_else(|| anyhow::anyhow!("未配置远程仓库"))
+    }
+
+    /// 将当前分支推送到远程仓库
+    pub async fn push(&self) -> Result<()> {
+        let _guard = GIT_LOCK.lock().await;
+        self.push_locked().await
+    }
+
+    async fn push_locked(&self) -> Result<()> {
+        let remote = self.remote()?;
+        if !self.has_commits().await? {
+            return Err(anyhow::anyhow!("配置仓库中还没有任何版本"));
+        }
+        let refspec = format!("HEAD:refs/heads/{}", self.branch);
+        self.git(&["push", "-q", remote, &refspec]).await?;
+        info!("已推送配置仓库到 {}", remote);
+        Ok(())
+    }
+
+    /// 从远程仓库拉取，只允许快进合并
+    pub async fn pull(&self) -> Result<()> {
+        let remote = self.remote()?;
+        let _guard = GIT_LOCK.lock().await;
+        self.ensure_repo().await?;
+        self.git(&["pull", "-q", "--ff-only", remote, &self.branch]).await?;
+        info!("已从 {} 拉取配置仓库", remote);
+        Ok(())
+    }
+}
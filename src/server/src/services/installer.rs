@@ -0,0 +1,205 @@
+use anyhow::Result;
+use std::io::Read;
+use std::path::Path;
+use crate::config::software_config;
+use crate::models::software::{CommandSpec, Hooks, InstallerType, Software};
+use crate::services::command_policy;
+use crate::utils::command::quote_shell_arg;
+use crate::utils::path::expand_env_vars;
+use crate::utils::template::{self, TemplateContext};
+
+/// 解析后的安装计划
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallPlan {
+    pub command: CommandSpec,
+    pub install_path: Option<String>,
+    // 渲染后的安装前后命令
+    pub hooks: Hooks,
+    // 不影响安装但需要告知用户的情况，例如默认安装路径未被使用
+    pub warnings: Vec<String>,
+}
+
+// Inno Setup 生成的安装程序在版本信息等资源中带有该标记，资源位于安装程序开头的加载器中
+const INNO_SIGNATURE: &[u8] = b"Inno Setup";
+const INNO_SIGNATURE_SCAN: u64 = 4 * 1024 * 1024;
+
+// 安装程序文件存在时，检查开头部分是否带有 Inno Setup 的标记
+fn has_inno_signature(program: &Path) -> bool {
+    let Ok(file) = std::fs::File::open(program) else {
+        return false;
+    };
+    let mut head = Vec::new();
+    if file.take(INNO_SIGNATURE_SCAN).read_to_end(&mut head).is_err() {
+        return false;
+    }
+    head.windows(INNO_SIGNATURE.len()).any(|window| window == INNO_SIGNATURE)
+}
+
+/// 按安装程序文件识别无法从命令推断的安装程序类型
+///
+/// 命令中没有专有开关的 Inno Setup 安装程序只能通过文件中的标记识别，
+/// 需要读取文件开头的数兆字节，因此在阻塞线程中执行
+pub async fn detect_installer(software: &Software) -> InstallerType {
+    if software.installer != InstallerType::Other {
+        return software.installer;
+    }
+    let Some(program) = software.install_command.program().map(|program| program.to_string()) else {
+        return software.installer;
+    };
+    match tokio::task::spawn_blocking(move || has_inno_signature(Path::new(&program))).await {
+        Ok(true) => InstallerType::Inno,
+        _ => software.installer,
+    }
+}
+
+/// 校验安装路径：必须是不含引号和控制字符的绝对路径
+pub fn validate_install_path(path: &str) -> Result<()> {
+    let path = path.trim();
+
+    if path.is_empty() {
+        return Err(anyhow::anyhow!("安装路径不能为空"));
+    }
+
+    if path.chars().any(|c| c == '"' || c.is_control()) {
+        return Err(anyhow::anyhow!("安装路径包含非法字符: {}", path));
+    }
+
+    // Windows 盘符路径在其他平台上也视为绝对路径
+    let bytes = path.as_bytes();
+    let is_drive_path = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+    if !is_drive_path && !std::path::Path::new(path).is_absolute() {
+        return Err(anyhow::anyhow!("安装路径必须是绝对路径: {}", path));
+    }
+
+    if path.split(['/', '\\']).any(|segment| segment == "..") {
+        return Err(anyhow::anyhow!("安装路径不能包含 '..': {}", path));
+    }
+
+    Ok(())
+}
+
+/// 根据安装程序类型生成指定安装路径的命令参数
+pub fn install_path_arguments(installer: InstallerType, path: &str) -> Result<Vec<String>> {
+    match installer {
+        InstallerType::Winget => Ok(vec!["--location".to_string(), path.to_string()]),
+        InstallerType::Msi => Ok(vec![format!("INSTALLDIR={}", path)]),
+        InstallerType::Inno => Ok(vec![format!("/DIR={}", path)]),
+        InstallerType::Tarball => Ok(vec!["-C".to_string(), path.to_string()]),
+        InstallerType::Other => Err(anyhow::anyhow!("该安装程序不支持自定义安装路径: {}", path)),
+    }
+}
+
+/// 渲染命令模板：shell 命令中的替换值会被引用，参数向量中的替换值原样代入
+///
+/// 参数向量不经过 shell，其中的环境变量在渲染前展开；shell 命令中的环境变量由 shell 自行展开
+pub fn render_command(context: &TemplateContext, command: &CommandSpec) -> Result<CommandSpec> {
+    match command {
+        CommandSpec::Shell(template) => Ok(CommandSpec::Shell(
+            context.render_with(template, quote_shell_arg)?,
+        )),
+        CommandSpec::Argv(args) => Ok(CommandSpec::Argv(
+            args.iter()
+                .map(|arg| context.render(&expand_env_vars(arg)?))
+                .collect::<Result<Vec<String>>>()?,
+        )),
+    }
+}
+
+// 在命令末尾追加参数，shell 命令中的参数会被引用
+fn append_arguments(command: CommandSpec, arguments: Vec<String>) -> CommandSpec {
+    match command {
+        CommandSpec::Shell(command) => {
+            let quoted: Vec<String> = arguments.iter().map(|arg| quote_shell_arg(arg)).collect();
+            CommandSpec::Shell(format!("{} {}", command, quoted.join(" ")))
+        }
+        CommandSpec::Argv(mut args) => {
+            args.extend(arguments);
+            CommandSpec::Argv(args)
+        }
+    }
+}
+
+/// 生成安装计划，自定义路径优先于软件配置中的默认安装路径
+///
+/// 安装命令中显式引用了 `{{install_path}}` 时不再追加安装程序的路径参数。
+/// 安装前后命令使用相同的变量渲染，`{{install_path}}` 为实际安装路径
+pub fn plan_install(software: &Software, custom_install_path: Option<&str>) -> Result<InstallPlan> {
+    // 执行前按命令策略校验命令和来自请求的安装路径
+    let user_inputs: Vec<&str> = custom_install_path.into_iter().collect();
+    let hooks = [&software.hooks.pre_install, &software.hooks.post_install];
+    for command in std::iter::once(&software.install_command).chain(hooks.into_iter().flatten()) {
+        command_policy::check_command(&crate::CONFIG.command_policy, command, &user_inputs)?;
+    }
+
+    let mut plan = plan_command(software, custom_install_path)?;
+    let mut context = software_config::template_context(software);
+    if let Some(path) = &plan.install_path {
+        context.set("install_path", path.clone());
+    }
+    let render_hook = |hook: &Option<CommandSpec>| {
+        hook.as_ref()
+            .map(|command| render_command(&context, command))
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("{}: 安装前后命令{}", software.name, e))
+    };
+    plan.hooks = Hooks {
+        pre_install: render_hook(&software.hooks.pre_install)?,
+        post_install: render_hook(&software.hooks.post_install)?,
+    };
+    Ok(plan)
+}
+
+// 渲染安装命令并追加安装路径参数
+fn plan_command(software: &Software, custom_install_path: Option<&str>) -> Result<InstallPlan> {
+    let mut context = software_config::template_context(software);
+
+    let default_install_path = match &software.install_path {
+        Some(path) => Some(context.render(path)?),
+        None => None,
+    };
+    let install_path = custom_install_path
+        .map(|path| path.to_string())
+        .or(default_install_path)
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .map(|path| expand_env_vars(&path))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("{}: 安装路径{}", software.name, e))?;
+
+    let mut uses_install_path = false;
+    for part in software.install_command.parts() {
+        uses_install_path |= template::referenced_variables(part)?
+            .iter()
+            .any(|name| name == "install_path");
+    }
+
+    if let Some(path) = &install_path {
+        validate_install_path(path)?;
+        context.set("install_path", path.clone());
+    }
+    let command = render_command(&context, &software.install_command)
+        .map_err(|e| anyhow::anyhow!("{}: {}", software.name, e))?;
+
+    let plan = |command, install_path, warnings| InstallPlan { command, install_path, hooks: Hooks::default(), warnings };
+    let Some(path) = install_path else {
+        return Ok(plan(command, None, Vec::new()));
+    };
+
+    if uses_install_path {
+        return Ok(plan(command, Some(path), Vec::new()));
+    }
+
+    let arguments = match install_path_arguments(software.installer, &path) {
+        Ok(arguments) => arguments,
+        // 软件配置自带的默认路径无法传给安装程序时使用原始命令，并在计划中说明默认路径没有生效
+        Err(e) if custom_install_path.is_none() => {
+            let warning = format!("{}，已忽略默认安装路径", e);
+            return Ok(plan(command, None, vec![warning]));
+        }
+        Err(e) => return Err(anyhow::anyhow!("{}: {}", software.name, e)),
+    };
+    Ok(plan(append_arguments(command, arguments), Some(path), Vec::new()))
+}
@@ -1,3 +1,19 @@
 pub mod software_service;
 pub mod config_files;
+pub mod deployment;
+pub mod archive;
+pub mod content;
+pub mod target_policy;
+pub mod audit;
+pub mod drift;
+pub mod backups;
+pub mod secrets;
+pub mod git_store;
+pub mod permissions;
 pub mod system;
+pub mod installer;
+pub mod command_policy;
+pub mod tasks;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,243 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::models::config_file::{ConfigFile, FilePermissions};
+use crate::services::deployment::{self, PathFilter};
+
+// 私密文件的默认权限：只有所有者可以读写
+const PRIVATE_MODE: u32 = 0o600;
+
+/// 单个目标文件的权限设置结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionCheck {
+    pub path: String,
+    // 期望的八进制权限，无法确定时为空
+    pub mode: Option<String>,
+    // 设置后实际的权限
+    pub actual_mode: Option<String>,
+    // 设置后实际的所有者和所属组 ID
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    // 实际结果与期望一致
+    pub verified: bool,
+    pub warnings: Vec<String>,
+}
+
+/// 解析八进制权限，如 "644"、"0600" 或 "0o755"
+pub fn parse_mode(mode: &str) -> Result<u32> {
+    let digits = mode.trim().trim_start_matches("0o");
+    match u32::from_str_radix(digits, 8) {
+        Ok(value) if !digits.is_empty() && value <= 0o7777 => Ok(value),
+        _ => Err(anyhow::anyhow!("无效的文件权限: {}", mode)),
+    }
+}
+
+fn format_mode(mode: u32) -> String {
+    format!("{:04o}", mode)
+}
+
+/// 校验记录中的权限设置
+pub fn validate(permissions: &FilePermissions) -> Result<()> {
+    if let Some(mode) = &permissions.mode {
+        parse_mode(mode)?;
+    }
+    for (label, name) in [("所有者", &permissions.owner), ("所属组", &permissions.group)] {
+        if let Some(name) = name
+            && (name.trim().is_empty() || name.contains(['/', '\0', ':']))
+        {
+            return Err(anyhow::anyhow!("无效的{}: {}", label, name));
+        }
+    }
+    Ok(())
+}
+
+// 路径位于 .ssh 目录中
+fn in_ssh_dir(path: &Path) -> bool {
+    path.parent()
+        .is_some_and(|parent| parent.components().any(|component| component.as_os_str() == ".ssh"))
+}
+
+/// 部署时要设置的所有者，在写入文件之前解析，名称不存在时直接报错
+#[derive(Debug, Default)]
+pub struct Ownership {
+    uid: Option<u32>,
+    gid: Option<u32>,
+    // 设置了所有者但当前不是 root，无法修改
+    skipped: bool,
+}
+
+impl Ownership {
+    #[cfg(unix)]
+    pub fn resolve(permissions: &FilePermissions) -> Result<Self> {
+        if permissions.owner.is_none() && permissions.group.is_none() {
+            return Ok(Ownership::default());
+        }
+        if !is_elevated() {
+            return Ok(Ownership { skipped: true, ..Default::default() });
+        }
+        Ok(Ownership {
+            uid: permissions.owner.as_deref().map(lookup_user).transpose()?,
+            gid: permissions.group.as_deref().map(lookup_group).transpose()?,
+            skipped: false,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn resolve(_permissions: &FilePermissions) -> Result<Self> {
+        Ok(Ownership::default())
+    }
+}
+
+/// 复制部署时每个目标文件的权限和所有者，在写入文件之前确定
+///
+/// 写入时以这些设置创建临时文件后再替换目标文件，新内容不会以默认权限出现在目标位置
+#[derive(Debug, Default)]
+pub struct TargetModes {
+    // 目标文件 -> 期望的权限，无法确定时不包含
+    modes: HashMap<PathBuf, u32>,
+    ownership: Ownership,
+}
+
+impl TargetModes {
+    /// 未设置权限时沿用源文件的权限；`.ssh` 目录中的文件和解密后的文件默认为 0600
+    #[cfg(unix)]
+    pub fn new(config_file: &ConfigFile, source: &Path, target: &Path, filter: &PathFilter) -> Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let explicit = config_file.permissions.mode.as_deref().map(parse_mode).transpose()?;
+        let ownership = Ownership::resolve(&config_file.permissions)?;
+        let mut modes = HashMap::new();
+        for (_, source_file, target_file) in deployment::file_pairs(source, target, config_file.is_directory, filter)? {
+            let mode = match explicit {
+                Some(mode) => Some(mode),
+                None if config_file.encrypted || in_ssh_dir(&target_file) => Some(PRIVATE_MODE),
+                None => std::fs::metadata(&source_file).ok().map(|metadata| metadata.mode() & 0o7777),
+            };
+            if let Some(mode) = mode {
+                modes.insert(target_file, mode);
+            }
+        }
+        Ok(TargetModes { modes, ownership })
+    }
+
+    /// 非 Unix 系统不支持设置权限和所有者
+    #[cfg(not(unix))]
+    pub fn new(_config_file: &ConfigFile, _source: &Path, _target: &Path, _filter: &PathFilter) -> Result<Self> {
+        Ok(TargetModes::default())
+    }
+
+    /// 目标文件期望的权限
+    pub fn mode(&self, target: &Path) -> Option<u32> {
+        self.modes.get(target).copied()
+    }
+
+    /// 需要修改的所有者和所属组
+    pub fn owner(&self) -> (Option<u32>, Option<u32>) {
+        (self.ownership.uid, self.ownership.gid)
+    }
+}
+
+#[cfg(unix)]
+fn is_elevated() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+// 用户名或数字 ID
+#[cfg(unix)]
+fn lookup_user(name: &str) -> Result<u32> {
+    if let Ok(uid) = name.parse() {
+        return Ok(uid);
+    }
+    let c_name = std::ffi::CString::new(name)?;
+    let entry = unsafe { libc::getpwnam(c_name.as_ptr()) };
+    if entry.is_null() {
+        return Err(anyhow::anyhow!("用户不存在: {}", name));
+    }
+    Ok(unsafe { (*entry).pw_uid })
+}
+
+// 组名或数字 ID
+#[cfg(unix)]
+fn lookup_group(name: &str) -> Result<u32> {
+    if let Ok(gid) = name.parse() {
+        return Ok(gid);
+    }
+    let c_name = std::ffi::CString::new(name)?;
+    let entry = unsafe { libc::getgrnam(c_name.as_ptr()) };
+    if entry.is_null() {
+        return Err(anyhow::anyhow!("用户组不存在: {}", name));
+    }
+    Ok(unsafe { (*entry).gr_gid })
+}
+
+/// 核对复制部署后目标文件的权限和所有者
+///
+/// 新写入的文件在替换之前已经设置好；内容未变化而跳过的文件在这里补上设置
+#[cfg(unix)]
+pub fn apply(
+    config_file: &ConfigFile,
+    modes: &TargetModes,
+    source: &Path,
+    target: &Path,
+    filter: &PathFilter,
+) -> Result<Vec<PermissionCheck>> {
+    use std::fs;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let ownership = &modes.ownership;
+    let mut checks = Vec::new();
+    for (path, _, target_file) in deployment::file_pairs(source, target, config_file.is_directory, filter)? {
+        if !target_file.is_file() {
+            continue;
+        }
+        let mode = modes.mode(&target_file);
+
+        let mut warnings = Vec::new();
+        if ownership.uid.is_some() || ownership.gid.is_some() {
+            std::os::unix::fs::chown(&target_file, ownership.uid, ownership.gid)?;
+        }
+        // 修改所有者会清除 setuid 位，因此在之后设置权限
+        if let Some(mode) = mode {
+            fs::set_permissions(&target_file, fs::Permissions::from_mode(mode))?;
+        }
+        if ownership.skipped {
+            warnings.push("未以 root 身份运行，没有修改所有者".to_string());
+        }
+
+        // 读取实际结果，例如某些文件系统不支持修改权限
+        let metadata = fs::metadata(&target_file)?;
+        let actual_mode = metadata.mode() & 0o7777;
+        let mut verified = true;
+        if mode.is_some_and(|mode| mode != actual_mode) {
+            warnings.push(format!("权限未生效，实际为 {}", format_mode(actual_mode)));
+            verified = false;
+        }
+        if ownership.uid.is_some_and(|uid| uid != metadata.uid()) || ownership.gid.is_some_and(|gid| gid != metadata.gid()) {
+            warnings.push("所有者未生效".to_string());
+            verified = false;
+        }
+        checks.push(PermissionCheck {
+            path,
+            mode: mode.map(format_mode),
+            actual_mode: Some(format_mode(actual_mode)),
+            uid: Some(metadata.uid()),
+            gid: Some(metadata.gid()),
+            verified: verified && !ownership.skipped,
+            warnings,
+        });
+    }
+    Ok(checks)
+}
+
+/// 非 Unix 系统不支持设置权限和所有者
+#[cfg(not(unix))]
+pub fn apply(
+    _config_file: &ConfigFile,
+    _modes: &TargetModes,
+    _source: &Path,
+    _target: &Path,
+    _filter: &PathFilter,
+) -> Result<Vec<PermissionCheck>> {
+    Ok(Vec::new())
+}
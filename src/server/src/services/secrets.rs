@@ -0,0 +1,107 @@
+use anyhow::Result;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+use std::fs;
+use crate::config::SecretSettings;
+
+// 加密文件格式：MAGIC | 密钥类型(1) | 盐(16) | 随机数(12) | 密文
+const MAGIC: &[u8] = b"SIHSEC01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+const KIND_KEY_FILE: u8 = 0;
+const KIND_PASSPHRASE: u8 = 1;
+
+/// 加密配置文件使用的密钥材料
+pub struct SecretKey {
+    kind: u8,
+    material: Vec<u8>,
+}
+
+impl SecretKey {
+    /// 按配置读取密钥：优先使用密钥文件，其次读取口令环境变量
+    pub fn load(settings: &SecretSettings) -> Result<Self> {
+        if let Some(key_file) = &settings.key_file {
+            let material = fs::read(key_file)
+                .map_err(|e| anyhow::anyhow!("无法读取密钥文件 {}: {}", key_file, e))?;
+            if material.is_empty() {
+                return Err(anyhow::anyhow!("密钥文件为空: {}", key_file));
+            }
+            return Ok(SecretKey { kind: KIND_KEY_FILE, material });
+        }
+        if let Some(name) = &settings.passphrase_env {
+            let passphrase = std::env::var(name)
+                .map_err(|_| anyhow::anyhow!("口令环境变量未设置: {}", name))?;
+            if passphrase.is_empty() {
+                return Err(anyhow::anyhow!("口令环境变量为空: {}", name));
+            }
+            return Ok(SecretKey { kind: KIND_PASSPHRASE, material: passphrase.into_bytes() });
+        }
+        Err(anyhow::anyhow!("未配置加密密钥，请设置 config_files.secrets.key_file 或 passphrase_env"))
+    }
+
+    // 每个文件使用独立的盐派生密钥
+    fn derive(&self, salt: &[u8]) -> Result<Key> {
+        let mut key = Key::default();
+        match self.kind {
+            KIND_PASSPHRASE => Argon2::default()
+                .hash_password_into(&self.material, salt, &mut key)
+                .map_err(|e| anyhow::anyhow!("派生密钥失败: {}", e))?,
+            _ => {
+                let mut hasher = Sha256::new();
+                hasher.update(&self.material);
+                hasher.update(salt);
+                key.copy_from_slice(&hasher.finalize());
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// 判断内容是否为本程序加密的格式
+pub fn is_encrypted(content: &[u8]) -> bool {
+    content.len() >= HEADER_LEN && content.starts_with(MAGIC)
+}
+
+/// 使用 ChaCha20-Poly1305 加密内容
+pub fn encrypt(key: &SecretKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = ChaCha20Poly1305::new(&key.derive(&salt)?);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow::anyhow!("加密失败"))?;
+
+    let mut output = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    output.extend_from_slice(MAGIC);
+    output.push(key.kind);
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+/// 解密由 [`encrypt`] 生成的内容，密钥错误或内容被篡改时返回错误
+pub fn decrypt(key: &SecretKey, content: &[u8]) -> Result<Vec<u8>> {
+    if !is_encrypted(content) {
+        return Err(anyhow::anyhow!("内容不是加密格式"));
+    }
+    let kind = content[MAGIC.len()];
+    if kind != key.kind {
+        return Err(anyhow::anyhow!("文件加密使用的密钥类型与当前配置不一致"));
+    }
+    let salt = &content[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = &content[MAGIC.len() + 1 + SALT_LEN..HEADER_LEN];
+
+    let cipher = ChaCha20Poly1305::new(&key.derive(salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), &content[HEADER_LEN..])
+        .map_err(|_| anyhow::anyhow!("解密失败，密钥错误或文件已损坏"))
+}
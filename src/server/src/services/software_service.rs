@@ -1,25 +1,31 @@
 use crate::config::software_config;
+use crate::models::config_file::LinkedDeployStatus;
+use crate::services::config_files::{ConfigFileService, ConfigFileServiceImpl};
+use crate::services::installer::{self, InstallPlan};
+use crate::utils::command::{CommandMode, CommandRunner};
 use crate::{
-    SOFTWARES,
-    models::software::{InstallStatus, Software, SoftwareInstallStatus},
+    SOFTWARES, TASKS,
+    models::software::{CommandSpec, InstallStatus, InstallerType, Software, SoftwareInstallStatus},
+    models::task::TaskBatch,
 };
 use anyhow::Result;
 use async_trait::async_trait;
-use log::{error, info};
+use log::{debug, error, info, warn};
 use std::path::Path;
-use std::process::Command;
+use tokio::sync::watch;
 
 #[async_trait]
 pub trait SoftwareService {
     async fn get_all_software(&self) -> Result<Vec<Software>>;
     async fn get_software_by_id(&self, id: &str) -> Result<Option<Software>>;
     async fn search_software(&self, query: &str) -> Result<Vec<Software>>;
-    async fn install_software(
+    async fn install_batch(
         &self,
-        software: &Software,
+        software_list: Vec<Software>,
         install_path: Option<String>,
-    ) -> Result<String>;
-    async fn get_install_status(&self, task_id: &str) -> Result<SoftwareInstallStatus>;
+        deploy_configs: bool,
+    ) -> Result<TaskBatch>;
+    async fn get_install_status(&self, task_id: &str) -> Result<Option<SoftwareInstallStatus>>;
     async fn edit_software(&self, software: &Software) -> Result<String>;
 }
 
@@ -35,27 +41,134 @@ impl SoftwareServiceImpl {
         }
     }
 
-    // 执行命令行命令的辅助方法
-    async fn execute_command(&self, command: &str) -> Result<String> {
-        info!("执行命令: {}", command);
+    /// 执行命令行命令，收到取消信号时结束整个进程树
+    ///
+    /// 参数向量形式的命令直接执行程序，不经过 shell。命令被取消时返回 `Ok(None)`
+    async fn execute_command(
+        command: &CommandSpec,
+        cancel: watch::Receiver<bool>,
+    ) -> Result<Option<String>> {
+        let mode = match command {
+            CommandSpec::Shell(command) => CommandMode::Shell(command.clone()),
+            CommandSpec::Argv(args) => CommandMode::Argv(args.clone()),
+        };
 
-        // 在实际实现中，这里会使用 tokio::process::Command 异步执行
-        // 目前使用同步版本作为示例
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd").args(&["/C", command]).output()?
-        } else {
-            Command::new("sh").arg("-c").arg(command).output()?
+        let output = CommandRunner::new(mode)
+            .cancel_signal(cancel)
+            .on_stdout_line(|line| debug!("[安装输出] {}", line))
+            .on_stderr_line(|line| debug!("[安装错误] {}", line))
+            .run()
+            .await?;
+
+        if output.cancelled {
+            return Ok(None);
+        }
+        if output.stdout_truncated || output.stderr_truncated {
+            warn!("命令输出超过上限，结果中只保留了开头部分: {}", command);
+        }
+        output.into_result().map(Some)
+    }
+
+    /// 安装成功后部署关联到该软件的配置文件，返回任务的完成信息
+    async fn deploy_linked_configs(task_id: &str, software: &Software) -> String {
+        let service = ConfigFileServiceImpl::new(&crate::CONFIG.config_files);
+        let deployments = match service.deploy_linked_config_files(&software.id).await {
+            Ok(deployments) => deployments,
+            Err(err) => {
+                error!("部署关联配置文件失败 {}: {}", software.name, err);
+                return format!("安装完成，但部署配置文件失败: {}", err);
+            }
         };
+        if deployments.is_empty() {
+            return "安装完成".to_string();
+        }
 
-        if output.status.success() {
-            let result = String::from_utf8_lossy(&output.stdout).to_string();
-            Ok(result)
+        let count = |status: LinkedDeployStatus| deployments.iter().filter(|item| item.status == status).count();
+        let (deployed, failed) = (count(LinkedDeployStatus::Deployed), count(LinkedDeployStatus::Failed));
+        info!("已部署 {} 的关联配置文件: 成功 {} 个，失败 {} 个", software.name, deployed, failed);
+        TASKS.set_config_deployments(task_id, deployments);
+        if failed > 0 {
+            format!("安装完成，已部署 {} 个配置文件，{} 个部署失败", deployed, failed)
         } else {
-            let error = String::from_utf8_lossy(&output.stderr).to_string();
-            error!("命令执行失败: {}", error);
-            Err(anyhow::anyhow!("命令执行失败: {}", error))
+            format!("安装完成，已部署 {} 个配置文件", deployed)
         }
     }
+
+    /// 按顺序执行队列中的安装任务
+    async fn run_install_queue(batch_id: String, jobs: Vec<(String, Software, InstallPlan)>, deploy_configs: bool) {
+        for (task_id, software, plan) in jobs {
+            // 队列暂停时等待恢复，剩余任务保持等待状态
+            TASKS.wait_while_paused(&batch_id).await;
+
+            let Some(cancel) = TASKS.cancel_signal(&task_id) else {
+                continue;
+            };
+            if !TASKS.start_task(&task_id, format!("正在安装 {}...", software.name)) {
+                info!("任务已取消，跳过安装: {}", software.name);
+                continue;
+            }
+
+            // 压缩包需要预先创建解压根目录
+            if software.installer == InstallerType::Tarball
+                && let Some(path) = &plan.install_path
+                && let Err(err) = std::fs::create_dir_all(path)
+            {
+                error!("创建安装目录失败 {}: {}", path, err);
+                TASKS.finish_task(&task_id, InstallStatus::Failed, format!("创建安装目录失败: {}", err));
+                continue;
+            }
+
+            if let Some(command) = &plan.hooks.pre_install {
+                info!("执行安装前命令: {}, 命令: {}", software.name, command);
+                match Self::execute_command(command, cancel.clone()).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        info!("软件安装已取消: {}", software.name);
+                        TASKS.finish_task(&task_id, InstallStatus::Cancelled, "任务已取消".to_string());
+                        continue;
+                    }
+                    Err(err) => {
+                        error!("安装前命令失败 {}: {}", software.name, err);
+                        TASKS.finish_task(&task_id, InstallStatus::Failed, format!("安装前命令失败: {}", err));
+                        continue;
+                    }
+                }
+            }
+
+            info!("开始安装软件: {}, 命令: {}", software.name, plan.command);
+            let mut result = Self::execute_command(&plan.command, cancel.clone()).await;
+            if let (Ok(Some(_)), Some(command)) = (&result, &plan.hooks.post_install) {
+                info!("执行安装后命令: {}, 命令: {}", software.name, command);
+                result = Self::execute_command(command, cancel)
+                    .await
+                    .map_err(|err| anyhow::anyhow!("安装后命令失败: {}", err));
+            }
+            match result {
+                Ok(Some(_)) => {
+                    info!("软件安装完成: {}", software.name);
+                    let mut message = if deploy_configs {
+                        Self::deploy_linked_configs(&task_id, &software).await
+                    } else {
+                        "安装完成".to_string()
+                    };
+                    if !plan.warnings.is_empty() {
+                        message = format!("{}（{}）", message, plan.warnings.join("；"));
+                    }
+                    TASKS.finish_task(&task_id, InstallStatus::Completed, message);
+                }
+                Ok(None) => {
+                    info!("软件安装已取消: {}", software.name);
+                    TASKS.finish_task(&task_id, InstallStatus::Cancelled, "任务已取消".to_string());
+                }
+                Err(err) => {
+                    error!("软件安装失败 {}: {}", software.name, err);
+                    TASKS.finish_task(&task_id, InstallStatus::Failed, err.to_string());
+                }
+            }
+        }
+
+        TASKS.finish_batch(&batch_id);
+    }
 }
 
 #[async_trait]
@@ -69,7 +182,7 @@ impl SoftwareService for SoftwareServiceImpl {
 
     async fn edit_software(&self, software: &Software) -> Result<String> {
         // 通过软件 ID 找到软件
-        let software = self.get_software_by_id(&software.id).await?;
+        let _software = self.get_software_by_id(&software.id).await?;
 
         // 目前返回一个示例数据
         Ok("软件编辑成功".to_string())
@@ -141,36 +254,54 @@ impl SoftwareService for SoftwareServiceImpl {
     }
 
     /**
-     * 安装软件
-     * @param software 软件信息
+     * 批量安装软件，任务按顺序依次执行
+     * @param software_list 软件列表
      * @param install_path 自定义安装路径
-     * @return 安装任务 ID
+     * @param deploy_configs 安装成功后是否自动部署关联的配置文件
+     * @return 安装队列
      */
-    async fn install_software(
+    async fn install_batch(
         &self,
-        software: &Software,
+        mut software_list: Vec<Software>,
         install_path: Option<String>,
-    ) -> Result<String> {
-        // 生成任务ID
-        let task_id = uuid::Uuid::new_v4().to_string();
-
-        // 构建安装命令
-        let mut install_command = software.install_command.clone();
-
-        // 如果提供了自定义安装路径，添加到命令中
-        if let Some(path) = install_path {
-            // 这里需要根据实际的安装程序调整参数
-            // 例如，对于 winget，可能需要使用 --location 参数
-            install_command = format!("{} --location \"{}\"", install_command, path);
+        deploy_configs: bool,
+    ) -> Result<TaskBatch> {
+        // 命令无法识别的安装程序再按文件中的标记识别
+        for software in &mut software_list {
+            software.installer = installer::detect_installer(software).await;
+        }
+
+        // 执行前校验所有软件的安装路径，任何一个无效都不会创建任务
+        let plans = software_list
+            .iter()
+            .map(|software| installer::plan_install(software, install_path.as_deref()))
+            .collect::<Result<Vec<InstallPlan>>>()?;
+        for (software, plan) in software_list.iter().zip(&plans) {
+            for warning in &plan.warnings {
+                warn!("{}: {}", software.name, warning);
+            }
         }
 
-        // 在实际实现中，这里会异步执行安装命令
-        // 并更新安装状态
-        // 目前只是记录日志
-        info!("开始安装软件: {}, 命令: {}", software.name, install_command);
+        let targets: Vec<(String, Option<String>)> = software_list
+            .iter()
+            .zip(&plans)
+            .map(|(software, plan)| (software.id.clone(), plan.install_path.clone()))
+            .collect();
+        let batch = TASKS.create_batch(&targets);
 
-        // 返回任务ID，用于后续查询安装状态
-        Ok(task_id)
+        let jobs = batch
+            .task_ids
+            .iter()
+            .cloned()
+            .zip(software_list)
+            .zip(plans)
+            .map(|((task_id, software), plan)| (task_id, software, plan))
+            .collect();
+
+        info!("创建安装队列，ID: {}, 任务数: {}", batch.id, batch.task_ids.len());
+        tokio::spawn(Self::run_install_queue(batch.id.clone(), jobs, deploy_configs));
+
+        Ok(batch)
     }
 
     /**
@@ -178,19 +309,7 @@ impl SoftwareService for SoftwareServiceImpl {
      * @param task_id 安装任务 ID
      * @return 安装状态
      */
-    async fn get_install_status(&self, task_id: &str) -> Result<SoftwareInstallStatus> {
-        // 在实际实现中，这里会查询实际的安装状态
-        // 目前返回一个示例状态
-        let status = SoftwareInstallStatus {
-            id: task_id.to_string(),
-            software_id: "sample-software-id".to_string(),
-            status: InstallStatus::Installing,
-            progress: 0.45,
-            message: Some("正在安装软件...".to_string()),
-            started_at: chrono::Utc::now(),
-            completed_at: None,
-        };
-
-        Ok(status)
+    async fn get_install_status(&self, task_id: &str) -> Result<Option<SoftwareInstallStatus>> {
+        Ok(TASKS.get_task(task_id))
     }
 }
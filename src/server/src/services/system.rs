@@ -156,39 +156,39 @@ impl SystemServiceImpl {
         
         // 执行 PowerShell 命令
         let output = Command::new("powershell")
-            .args(&["-Command", ps_command])
+            .args(["-Command", ps_command])
             .output();
             
-        if let Ok(output) = output {
-            if let Ok(output_str) = String::from_utf8(output.stdout) {
-                // 解析每个接口的 JSON 输出
-                for line in output_str.lines() {
-                    let line = line.trim();
-                    if line.starts_with("{") && line.ends_with("}") {
-                        // 尝试解析 JSON
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                            let name = json["Name"].as_str().unwrap_or("未知").to_string();
-                            let status = json["Status"].as_str().unwrap_or("未知").to_string();
-                            let mac = json["MacAddress"].as_str().unwrap_or("未知").to_string();
-                            let ip_str = json["IPAddresses"].as_str().unwrap_or("").to_string();
-                            
-                            // 解析 IP 地址列表
-                            let ip_addresses: Vec<String> = if !ip_str.is_empty() {
-                                ip_str.split(',').map(|s| s.trim().to_string()).collect()
-                            } else {
-                                Vec::new()
-                            };
-                            
-                            // 创建网络接口对象
-                            let interface = NetworkInterface {
-                                name,
-                                ip_addresses,
-                                mac_address: mac,
-                                status,
-                            };
-                            
-                            interfaces.push(interface);
-                        }
+        if let Ok(output) = output
+            && let Ok(output_str) = String::from_utf8(output.stdout)
+        {
+            // 解析每个接口的 JSON 输出
+            for line in output_str.lines() {
+                let line = line.trim();
+                if line.starts_with("{") && line.ends_with("}") {
+                    // 尝试解析 JSON
+                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+                        let name = json["Name"].as_str().unwrap_or("未知").to_string();
+                        let status = json["Status"].as_str().unwrap_or("未知").to_string();
+                        let mac = json["MacAddress"].as_str().unwrap_or("未知").to_string();
+                        let ip_str = json["IPAddresses"].as_str().unwrap_or("").to_string();
+                        
+                        // 解析 IP 地址列表
+                        let ip_addresses: Vec<String> = if !ip_str.is_empty() {
+                            ip_str.split(',').map(|s| s.trim().to_string()).collect()
+                        } else {
+                            Vec::new()
+                        };
+                        
+                        // 创建网络接口对象
+                        let interface = NetworkInterface {
+                            name,
+                            ip_addresses,
+                            mac_address: mac,
+                            status,
+                        };
+                        
+                        interfaces.push(interface);
                     }
                 }
             }
@@ -198,72 +198,69 @@ impl SystemServiceImpl {
         if interfaces.is_empty() {
             info!("PowerShell 获取网络信息失败，尝试使用 ipconfig 命令");
             
-            if let Ok(output) = Command::new("ipconfig")
-                .arg("/all")
-                .output() {
+            if let Ok(output) = Command::new("ipconfig").arg("/all").output()
+                && let Ok(output_str) = String::from_utf8(output.stdout)
+            {
+                let mut current_interface: Option<NetworkInterface> = None;
                 
-                if let Ok(output_str) = String::from_utf8(output.stdout) {
-                    let mut current_interface: Option<NetworkInterface> = None;
+                for line in output_str.lines() {
+                    let line = line.trim();
                     
-                    for line in output_str.lines() {
-                        let line = line.trim();
+                    // 检测新的网络接口部分
+                    if !line.is_empty() && !line.starts_with(" ") && line.contains(":") {
+                        // 保存之前的接口
+                        if let Some(interface) = current_interface.take()
+                            && !interface.name.is_empty()
+                        {
+                            interfaces.push(interface);
+                        }
                         
-                        // 检测新的网络接口部分
-                        if !line.is_empty() && !line.starts_with(" ") && line.contains(":") {
-                            // 保存之前的接口
-                            if let Some(interface) = current_interface.take() {
-                                if !interface.name.is_empty() {
-                                    interfaces.push(interface);
-                                }
-                            }
-                            
-                            // 创建新的接口
-                            let name = line.split(':').next().unwrap_or("").trim().to_string();
-                            if !name.is_empty() {
-                                current_interface = Some(NetworkInterface {
-                                    name,
-                                    ip_addresses: Vec::new(),
-                                    mac_address: "未知".to_string(),
-                                    status: "未知".to_string(),
-                                });
-                            }
-                        } else if let Some(ref mut interface) = current_interface {
-                            // IPv4 地址
-                            if line.contains("IPv4") && line.contains(":") {
-                                let parts: Vec<&str> = line.split(':').collect();
-                                if parts.len() > 1 {
-                                    let ip = parts[1].trim().to_string();
-                                    if !ip.is_empty() {
-                                        interface.ip_addresses.push(ip);
-                                    }
-                                }
-                            }
-                            // MAC 地址
-                            else if (line.contains("Physical Address") || line.contains("物理地址")) && line.contains(":") {
-                                let parts: Vec<&str> = line.split(':').collect();
-                                if parts.len() > 1 {
-                                    interface.mac_address = parts[1].trim().to_string();
+                        // 创建新的接口
+                        let name = line.split(':').next().unwrap_or("").trim().to_string();
+                        if !name.is_empty() {
+                            current_interface = Some(NetworkInterface {
+                                name,
+                                ip_addresses: Vec::new(),
+                                mac_address: "未知".to_string(),
+                                status: "未知".to_string(),
+                            });
+                        }
+                    } else if let Some(ref mut interface) = current_interface {
+                        // IPv4 地址
+                        if line.contains("IPv4") && line.contains(":") {
+                            let parts: Vec<&str> = line.split(':').collect();
+                            if parts.len() > 1 {
+                                let ip = parts[1].trim().to_string();
+                                if !ip.is_empty() {
+                                    interface.ip_addresses.push(ip);
                                 }
                             }
-                            // 连接状态
-                            else if (line.contains("Media State") || line.contains("媒体状态")) && line.contains(":") {
-                                let status = if line.contains("Connected") || line.contains("已连接") {
-                                    "已连接".to_string()
-                                } else {
-                                    "已断开连接".to_string()
-                                };
-                                interface.status = status;
+                        }
+                        // MAC 地址
+                        else if (line.contains("Physical Address") || line.contains("物理地址")) && line.contains(":") {
+                            let parts: Vec<&str> = line.split(':').collect();
+                            if parts.len() > 1 {
+                                interface.mac_address = parts[1].trim().to_string();
                             }
                         }
-                    }
-                    
-                    // 添加最后一个接口
-                    if let Some(interface) = current_interface {
-                        if !interface.name.is_empty() {
-                            interfaces.push(interface);
+                        // 连接状态
+                        else if (line.contains("Media State") || line.contains("媒体状态")) && line.contains(":") {
+                            let status = if line.contains("Connected") || line.contains("已连接") {
+                                "已连接".to_string()
+                            } else {
+                                "已断开连接".to_string()
+                            };
+                            interface.status = status;
                         }
                     }
                 }
+                
+                // 添加最后一个接口
+                if let Some(interface) = current_interface
+                    && !interface.name.is_empty()
+                {
+                    interfaces.push(interface);
+                }
             }
         }
         
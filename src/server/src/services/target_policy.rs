@@ -0,0 +1,203 @@
+use log::warn;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use crate::config::ConfigFilesSettings;
+use crate::utils::path;
+
+// 解析符号链接的最大次数，超过时视为链接循环
+const MAX_LINK_DEPTH: usize = 40;
+
+/// 目标路径违反部署限制
+#[derive(Debug, thiserror::Error)]
+#[error("不允许部署到 {target}: {reason}")]
+pub struct UnsafeTargetError {
+    // 解析 `..` 和符号链接之后的路径
+    pub target: String,
+    pub reason: String,
+}
+
+/// 源路径不在托管目录中
+#[derive(Debug, thiserror::Error)]
+#[error("不允许访问 {path}: 源路径不在托管目录中 ({roots})")]
+pub struct UnsafeSourceError {
+    // 解析 `..` 和符号链接之后的路径
+    pub path: String,
+    pub roots: String,
+}
+
+/// 托管源文件的目录：配置仓库目录、数据目录和导入目录
+///
+/// 保存记录以及读写、导出源文件之前检查源路径，避免通过记录读取或改写任意位置的文件
+#[derive(Clone)]
+pub struct SourceRoots {
+    roots: Vec<PathBuf>,
+}
+
+impl SourceRoots {
+    pub fn new(settings: &ConfigFilesSettings) -> Self {
+        // 导入目录位于配置仓库或数据目录中，单独列出以便在错误信息中说明
+        let import_dir = match settings.git.enabled {
+            true => Path::new(&settings.git.repo_dir).join("imported"),
+            false => Path::new(&settings.data_dir).join("imported"),
+        };
+        let roots = [settings.git.repo_dir.clone(), settings.data_dir.clone(), import_dir.to_string_lossy().to_string()];
+        SourceRoots {
+            roots: expand_all(roots),
+        }
+    }
+
+    /// 检查源路径，返回解析之后的路径
+    pub fn check(&self, source: &Path) -> Result<PathBuf, UnsafeSourceError> {
+        let resolved = normalize(source);
+        if self.roots.iter().any(|root| is_within(&resolved, root)) {
+            return Ok(resolved);
+        }
+        let roots = self.roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>();
+        Err(UnsafeSourceError {
+            path: resolved.to_string_lossy().to_string(),
+            roots: roots.join(", "),
+        })
+    }
+}
+
+/// 部署目标路径的限制：只允许部署到允许的目录中，并且不能是系统目录等敏感位置
+#[derive(Clone)]
+pub struct TargetPolicy {
+    // 为空时不限制
+    allowed_roots: Vec<PathBuf>,
+    denied_paths: Vec<PathBuf>,
+}
+
+impl TargetPolicy {
+    pub fn new(settings: &ConfigFilesSettings) -> Self {
+        let policy = &settings.target_policy;
+        // 本服务的数据、备份和托管的源文件被覆盖后无法恢复，同样禁止作为部署目标
+        let own_dirs = [settings.data_dir.clone(), settings.backup_dir.clone(), settings.git.repo_dir.clone()];
+        let denied = sensitive_paths()
+            .into_iter()
+            .chain(policy.denied_paths.iter().cloned())
+            .chain(own_dirs);
+        TargetPolicy {
+            allowed_roots: expand_all(policy.allowed_roots.iter().cloned()),
+            denied_paths: expand_all(denied),
+        }
+    }
+
+    /// 检查目标路径，返回解析之后的路径
+    ///
+    /// `follow_target` 为 true 时解析目标本身的符号链接（复制部署会写入链接指向的文件），
+    /// 否则只解析所在目录（链接部署会替换目标位置的链接）。
+    /// 目录部署时目标目录中包含敏感位置同样不允许
+    pub fn check(&self, target: &Path, follow_target: bool, is_directory: bool) -> Result<PathBuf, UnsafeTargetError> {
+        let resolved = match (follow_target, target.parent(), target.file_name()) {
+            (false, Some(parent), Some(name)) => normalize(parent).join(name),
+            _ => normalize(target),
+        };
+        let error = |reason: String| UnsafeTargetError {
+            target: resolved.to_string_lossy().to_string(),
+            reason,
+        };
+
+        if let Some(denied) = self.denied_paths.iter().find(|denied| is_within(&resolved, denied)) {
+            return Err(error(format!("位于受保护的位置 {}", denied.display())));
+        }
+        if is_directory && let Some(denied) = self.denied_paths.iter().find(|denied| is_within(denied, &resolved)) {
+            return Err(error(format!("目录中包含受保护的位置 {}", denied.display())));
+        }
+        if !self.allowed_roots.is_empty() && !self.allowed_roots.iter().any(|root| is_within(&resolved, root)) {
+            let roots = self.allowed_roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>();
+            return Err(error(format!("不在允许的目录中 ({})", roots.join(", "))));
+        }
+        Ok(resolved)
+    }
+}
+
+// 内置的敏感位置：系统目录以及授予登录权限的文件
+fn sensitive_paths() -> Vec<String> {
+    let mut paths = vec!["~/.ssh/authorized_keys".to_string()];
+    if cfg!(windows) {
+        let names = ["SystemRoot", "ProgramFiles", "ProgramFiles(x86)", "ProgramData"];
+        paths.extend(names.iter().filter_map(|name| std::env::var(name).ok()));
+    } else {
+        let dirs = ["/etc", "/bin", "/sbin", "/usr", "/lib", "/lib32", "/lib64", "/boot", "/dev", "/proc", "/sys", "/var"];
+        paths.extend(dirs.iter().map(|dir| dir.to_string()));
+        if cfg!(target_os = "macos") {
+            paths.extend(["/System", "/Library", "/private/etc"].iter().map(|dir| dir.to_string()));
+        }
+    }
+    paths
+}
+
+// 展开并解析配置中的路径，无法展开的路径忽略
+fn expand_all(paths: impl IntoIterator<Item = String>) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .filter_map(|value| match path::expand_env_vars(&value) {
+            Ok(expanded) => Some(normalize(Path::new(&expanded))),
+            Err(e) => {
+                warn!("忽略无法展开的路径 {}: {}", value, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// 转换为绝对路径，逐个组件解析符号链接，再处理其后的 `..`
+///
+/// `..` 作用于解析链接之后的上级目录，与操作系统访问路径时的行为一致。
+/// 不存在的部分保持原样，指向不存在位置的链接按链接内容继续解析
+pub fn normalize(target: &Path) -> PathBuf {
+    let absolute = path::to_absolute_path(&target.to_string_lossy());
+    // 待处理的组件，逆序保存以便在前面插入链接的内容
+    let mut pending: Vec<PathBuf> = components(&absolute);
+    let mut resolved = PathBuf::new();
+    let mut links = 0;
+    let mut exists = true;
+    while let Some(part) = pending.pop() {
+        match part.components().next() {
+            Some(Component::CurDir) | None => {}
+            Some(Component::ParentDir) => {
+                resolved.pop();
+            }
+            Some(Component::Normal(name)) => {
+                let candidate = resolved.join(name);
+                // 超过解析次数时视为链接循环，剩余部分按字面处理
+                if exists && links < MAX_LINK_DEPTH {
+                    match fs::symlink_metadata(&candidate) {
+                        Ok(meta) if meta.file_type().is_symlink() => {
+                            if let Ok(link) = fs::read_link(&candidate) {
+                                links += 1;
+                                // 相对链接相对于链接所在的目录，绝对链接替换已解析的部分
+                                if link.is_absolute() {
+                                    resolved = PathBuf::new();
+                                }
+                                pending.extend(components(&link));
+                                continue;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(_) => exists = false,
+                    }
+                }
+                resolved = candidate;
+            }
+            Some(other) => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+// 路径的各个组件，逆序排列
+fn components(target: &Path) -> Vec<PathBuf> {
+    target.components().rev().map(|component| PathBuf::from(component.as_os_str())).collect()
+}
+
+// Windows 的路径不区分大小写
+fn is_within(target: &Path, root: &Path) -> bool {
+    if cfg!(windows) {
+        let lower = |path: &Path| PathBuf::from(path.to_string_lossy().to_lowercase());
+        lower(target).starts_with(lower(root))
+    } else {
+        target.starts_with(root)
+    }
+}
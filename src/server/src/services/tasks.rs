@@ -0,0 +1,379 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::info;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tokio::sync::watch;
+use crate::TASKS;
+use crate::models::config_file::{FileResult, LinkedDeployment};
+use crate::models::software::{InstallStatus, SoftwareInstallStatus, TaskKind};
+use crate::models::task::{BatchState, TaskBatch};
+
+struct TaskEntry {
+    status: SoftwareInstallStatus,
+    // 取消信号，运行中的任务收到后会结束整个进程树
+    cancel_tx: watch::Sender<bool>,
+}
+
+struct BatchEntry {
+    batch: TaskBatch,
+    // 暂停信号，队列在开始下一个任务前会等待恢复
+    pause_tx: watch::Sender<bool>,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    tasks: HashMap<String, TaskEntry>,
+    batches: HashMap<String, BatchEntry>,
+}
+
+// 保留的已结束任务数量上限，超出时移除结束最早的任务
+const MAX_FINISHED_TASKS: usize = 500;
+
+impl RegistryState {
+    // 已结束的任务超过上限时，按结束时间移除最早的任务；未结束队列中的任务保留，
+    // 任务全部被移除的已结束队列一并移除
+    fn prune(&mut self, max_finished: usize) {
+        let active_batches: HashSet<&str> = self
+            .batches
+            .values()
+            .filter(|entry| !entry.batch.state.is_finished())
+            .map(|entry| entry.batch.id.as_str())
+            .collect();
+        let mut finished: Vec<(DateTime<Utc>, String)> = self
+            .tasks
+            .values()
+            .filter(|entry| entry.status.status.is_finished())
+            .filter(|entry| entry.status.batch_id.as_deref().is_none_or(|id| !active_batches.contains(id)))
+            .map(|entry| (entry.status.completed_at.unwrap_or(entry.status.started_at), entry.status.id.clone()))
+            .collect();
+        if finished.len() <= max_finished {
+            return;
+        }
+        finished.sort();
+        let excess = finished.len() - max_finished;
+        for (_, task_id) in finished.into_iter().take(excess) {
+            self.tasks.remove(&task_id);
+        }
+
+        let tasks = &self.tasks;
+        self.batches.retain(|_, entry| {
+            !entry.batch.state.is_finished() || entry.batch.task_ids.iter().any(|id| tasks.contains_key(id))
+        });
+    }
+}
+
+/// 任务注册表，记录所有安装任务及其所属队列的状态
+pub struct TaskRegistry {
+    state: Mutex<RegistryState>,
+    max_finished: usize,
+}
+
+impl Default for TaskRegistry {
+    fn default() -> Self {
+        TaskRegistry::with_limit(MAX_FINISHED_TASKS)
+    }
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        TaskRegistry::default()
+    }
+
+    /// 指定保留的已结束任务数量上限
+    pub fn with_limit(max_finished: usize) -> Self {
+        TaskRegistry {
+            state: Mutex::new(RegistryState::default()),
+            max_finished,
+        }
+    }
+
+    /// 创建一个批量队列，并为每个（软件 ID, 安装路径）生成一个等待中的任务
+    pub fn create_batch(&self, targets: &[(String, Option<String>)]) -> TaskBatch {
+        let now = Utc::now();
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        let mut state = self.state.lock().unwrap();
+
+        let mut task_ids = Vec::with_capacity(targets.len());
+        for (software_id, install_path) in targets {
+            let mut status = new_status(TaskKind::Install, software_id, "等待安装");
+            status.batch_id = Some(batch_id.clone());
+            status.install_path = install_path.clone();
+            status.started_at = now;
+            let task_id = status.id.clone();
+            let (cancel_tx, _) = watch::channel(false);
+            state.tasks.insert(task_id.clone(), TaskEntry { status, cancel_tx });
+            task_ids.push(task_id);
+        }
+
+        let batch = TaskBatch {
+            id: batch_id.clone(),
+            task_ids,
+            state: BatchState::Running,
+            created_at: now,
+            updated_at: now,
+        };
+        let (pause_tx, _) = watch::channel(false);
+        state.batches.insert(batch_id, BatchEntry { batch: batch.clone(), pause_tx });
+
+        batch
+    }
+
+    /// 创建一个不属于任何队列的等待中任务，返回任务 ID
+    pub fn create_task(&self, kind: TaskKind, software_id: &str, config_id: Option<String>) -> String {
+        let mut status = new_status(kind, software_id, "等待执行");
+        status.config_id = config_id;
+        let task_id = status.id.clone();
+        let (cancel_tx, _) = watch::channel(false);
+        self.state.lock().unwrap().tasks.insert(task_id.clone(), TaskEntry { status, cancel_tx });
+        task_id
+    }
+
+    pub fn get_task(&self, task_id: &str) -> Option<SoftwareInstallStatus> {
+        let state = self.state.lock().unwrap();
+        state.tasks.get(task_id).map(|entry| entry.status.clone())
+    }
+
+    pub fn list_tasks(&self) -> Vec<SoftwareInstallStatus> {
+        let state = self.state.lock().unwrap();
+        let mut tasks: Vec<SoftwareInstallStatus> =
+            state.tasks.values().map(|entry| entry.status.clone()).collect();
+        tasks.sort_by_key(|task| task.started_at);
+        tasks
+    }
+
+    pub fn get_batch(&self, batch_id: &str) -> Option<TaskBatch> {
+        let state = self.state.lock().unwrap();
+        state.batches.get(batch_id).map(|entry| entry.batch.clone())
+    }
+
+    /// 将等待中的任务标记为安装中，任务已被取消时返回 false
+    pub fn start_task(&self, task_id: &str, message: String) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.tasks.get_mut(task_id) {
+            Some(entry) if entry.status.status == InstallStatus::Pending => {
+                entry.status.status = InstallStatus::Installing;
+                entry.status.message = Some(message);
+                entry.status.started_at = Utc::now();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 结束任务并记录最终状态
+    pub fn finish_task(&self, task_id: &str, status: InstallStatus, message: String) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.tasks.get_mut(task_id) {
+            entry.status.status = status;
+            if status == InstallStatus::Completed {
+                entry.status.progress = 1.0;
+            }
+            entry.status.message = Some(message);
+            entry.status.completed_at = Some(Utc::now());
+        }
+        state.prune(self.max_finished);
+    }
+
+    /// 记录安装完成后自动部署关联配置文件的结果
+    pub fn set_config_deployments(&self, task_id: &str, deployments: Vec<LinkedDeployment>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.tasks.get_mut(task_id) {
+            entry.status.config_deployments = deployments;
+        }
+    }
+
+    /// 记录配置文件部署任务中逐个文件的结果和错误
+    pub fn set_task_results(&self, task_id: &str, files: Vec<FileResult>, errors: Vec<String>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.tasks.get_mut(task_id) {
+            entry.status.files = files;
+            entry.status.errors = errors;
+        }
+    }
+
+    /// 获取任务的取消信号
+    pub fn cancel_signal(&self, task_id: &str) -> Option<watch::Receiver<bool>> {
+        let state = self.state.lock().unwrap();
+        state.tasks.get(task_id).map(|entry| entry.cancel_tx.subscribe())
+    }
+
+    /// 取消任务：等待中的任务直接标记为已取消，运行中的任务由执行方结束进程后标记
+    ///
+    /// 配置文件部署开始后不可取消，中途停止会让目标位置只更新一部分文件
+    pub fn cancel_task(&self, task_id: &str) -> Result<Option<SoftwareInstallStatus>> {
+        let mut state = self.state.lock().unwrap();
+        let Some(entry) = state.tasks.get_mut(task_id) else {
+            return Ok(None);
+        };
+
+        if entry.status.status.is_finished() {
+            return Err(anyhow::anyhow!("任务已结束，无法取消: {:?}", entry.status.status));
+        }
+        if entry.status.kind == TaskKind::ConfigDeploy && entry.status.status == InstallStatus::Installing {
+            return Err(anyhow::anyhow!("配置文件部署已开始，无法取消"));
+        }
+
+        if entry.status.status == InstallStatus::Pending {
+            entry.status.status = InstallStatus::Cancelled;
+            entry.status.message = Some("任务已取消".to_string());
+            entry.status.completed_at = Some(Utc::now());
+        } else {
+            entry.status.message = Some("正在取消任务...".to_string());
+        }
+        entry.cancel_tx.send_replace(true);
+
+        info!("取消任务，ID: {}", task_id);
+        let status = entry.status.clone();
+        state.prune(self.max_finished);
+        Ok(Some(status))
+    }
+
+    /// 暂停或恢复队列，已结束的队列无法修改
+    pub fn set_batch_paused(&self, batch_id: &str, paused: bool) -> Result<Option<TaskBatch>> {
+        let mut state = self.state.lock().unwrap();
+        let Some(entry) = state.batches.get_mut(batch_id) else {
+            return Ok(None);
+        };
+
+        if entry.batch.state.is_finished() {
+            return Err(anyhow::anyhow!("队列已结束: {:?}", entry.batch.state));
+        }
+
+        entry.batch.state = if paused { BatchState::Paused } else { BatchState::Running };
+        entry.batch.updated_at = Utc::now();
+        entry.pause_tx.send_replace(paused);
+
+        info!("{}队列，ID: {}", if paused { "暂停" } else { "恢复" }, batch_id);
+        Ok(Some(entry.batch.clone()))
+    }
+
+    /// 取消队列中所有未结束的任务
+    pub fn cancel_batch(&self, batch_id: &str) -> Result<Option<TaskBatch>> {
+        let task_ids = {
+            let state = self.state.lock().unwrap();
+            let Some(entry) = state.batches.get(batch_id) else {
+                return Ok(None);
+            };
+            if entry.batch.state.is_finished() {
+                return Err(anyhow::anyhow!("队列已结束: {:?}", entry.batch.state));
+            }
+            entry.batch.task_ids.clone()
+        };
+
+        for task_id in &task_ids {
+            // 已结束的任务无需取消
+            let _ = self.cancel_task(task_id);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let Some(entry) = state.batches.get_mut(batch_id) else {
+            return Ok(None);
+        };
+        entry.batch.state = BatchState::Cancelled;
+        entry.batch.updated_at = Utc::now();
+        // 唤醒处于暂停中的队列，使其跳过剩余任务
+        entry.pause_tx.send_replace(false);
+
+        info!("取消队列，ID: {}", batch_id);
+        Ok(Some(entry.batch.clone()))
+    }
+
+    /// 队列执行完毕，被取消的队列保持取消状态
+    pub fn finish_batch(&self, batch_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.batches.get_mut(batch_id)
+            && !entry.batch.state.is_finished()
+        {
+            entry.batch.state = BatchState::Completed;
+            entry.batch.updated_at = Utc::now();
+        }
+        state.prune(self.max_finished);
+    }
+
+    /// 队列处于暂停状态时等待恢复
+    pub async fn wait_while_paused(&self, batch_id: &str) {
+        let receiver = {
+            let state = self.state.lock().unwrap();
+            state.batches.get(batch_id).map(|entry| entry.pause_tx.subscribe())
+        };
+
+        if let Some(mut receiver) = receiver {
+            while *receiver.borrow_and_update() {
+                if receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn new_status(kind: TaskKind, software_id: &str, message: &str) -> SoftwareInstallStatus {
+    SoftwareInstallStatus {
+        id: uuid::Uuid::new_v4().to_string(),
+        software_id: software_id.to_string(),
+        batch_id: None,
+        install_path: None,
+        status: InstallStatus::Pending,
+        progress: 0.0,
+        message: Some(message.to_string()),
+        started_at: Utc::now(),
+        completed_at: None,
+        config_deployments: Vec::new(),
+        kind,
+        config_id: None,
+        files: Vec::new(),
+        errors: Vec::new(),
+    }
+}
+
+#[async_trait]
+pub trait TaskService {
+    async fn get_all_tasks(&self) -> Result<Vec<SoftwareInstallStatus>>;
+    async fn get_task_by_id(&self, id: &str) -> Result<Option<SoftwareInstallStatus>>;
+    async fn cancel_task(&self, id: &str) -> Result<Option<SoftwareInstallStatus>>;
+    async fn get_batch_by_id(&self, id: &str) -> Result<Option<TaskBatch>>;
+    async fn pause_batch(&self, id: &str) -> Result<Option<TaskBatch>>;
+    async fn resume_batch(&self, id: &str) -> Result<Option<TaskBatch>>;
+    async fn cancel_batch(&self, id: &str) -> Result<Option<TaskBatch>>;
+}
+
+pub struct TaskServiceImpl {}
+
+impl TaskServiceImpl {
+    pub fn new() -> Self {
+        TaskServiceImpl {}
+    }
+}
+
+#[async_trait]
+impl TaskService for TaskServiceImpl {
+    async fn get_all_tasks(&self) -> Result<Vec<SoftwareInstallStatus>> {
+        Ok(TASKS.list_tasks())
+    }
+
+    async fn get_task_by_id(&self, id: &str) -> Result<Option<SoftwareInstallStatus>> {
+        Ok(TASKS.get_task(id))
+    }
+
+    async fn cancel_task(&self, id: &str) -> Result<Option<SoftwareInstallStatus>> {
+        TASKS.cancel_task(id)
+    }
+
+    async fn get_batch_by_id(&self, id: &str) -> Result<Option<TaskBatch>> {
+        Ok(TASKS.get_batch(id))
+    }
+
+    async fn pause_batch(&self, id: &str) -> Result<Option<TaskBatch>> {
+        TASKS.set_batch_paused(id, true)
+    }
+
+    async fn resume_batch(&self, id: &str) -> Result<Option<TaskBatch>> {
+        TASKS.set_batch_paused(id, false)
+    }
+
+    async fn cancel_batch(&self, id: &str) -> Result<Option<TaskBatch>> {
+        TASKS.cancel_batch(id)
+    }
+}
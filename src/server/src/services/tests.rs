@@ -0,0 +1,2046 @@
+#[cfg(test)]
+mod task_tests {
+    use crate::TASKS;
+    use crate::models::software::{InstallStatus, Software, SoftwareCategory};
+    use crate::models::task::BatchState;
+    use crate::services::software_service::{SoftwareService, SoftwareServiceImpl};
+    use std::time::Duration;
+
+    // 等待任务进入指定状态，超时返回 false
+    async fn wait_for_status(task_id: &str, status: InstallStatus) -> bool {
+        for _ in 0..100 {
+            if TASKS.get_task(task_id).map(|task| task.status) == Some(status) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        false
+    }
+
+    fn long_running_software() -> Software {
+        let command = if cfg!(target_os = "windows") {
+            "ping -n 30 127.0.0.1"
+        } else {
+            "sleep 30"
+        };
+        Software::new(
+            "Long Running".to_string(),
+            "1.0.0".to_string(),
+            None,
+            command.to_string(),
+            SoftwareCategory::Other,
+        )
+    }
+
+    #[test]
+    fn test_cancel_pending_task() {
+        let batch = TASKS.create_batch(&[("pending-software".to_string(), None)]);
+        let task_id = &batch.task_ids[0];
+
+        let status = TASKS.cancel_task(task_id).unwrap().unwrap();
+        assert_eq!(status.status, InstallStatus::Cancelled);
+        assert!(status.completed_at.is_some());
+
+        // 已结束的任务不能再次取消
+        assert!(TASKS.cancel_task(task_id).is_err());
+        // 已取消的任务不会再被启动
+        assert!(!TASKS.start_task(task_id, "正在安装...".to_string()));
+        // 不存在的任务
+        assert!(TASKS.cancel_task("missing-task").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pause_resume_and_cancel_batch() {
+        let batch = TASKS.create_batch(&[("a".to_string(), None), ("b".to_string(), None)]);
+
+        let paused = TASKS.set_batch_paused(&batch.id, true).unwrap().unwrap();
+        assert_eq!(paused.state, BatchState::Paused);
+        let resumed = TASKS.set_batch_paused(&batch.id, false).unwrap().unwrap();
+        assert_eq!(resumed.state, BatchState::Running);
+
+        let cancelled = TASKS.cancel_batch(&batch.id).unwrap().unwrap();
+        assert_eq!(cancelled.state, BatchState::Cancelled);
+        for task_id in &batch.task_ids {
+            assert_eq!(TASKS.get_task(task_id).unwrap().status, InstallStatus::Cancelled);
+        }
+
+        // 已结束的队列不能再暂停
+        assert!(TASKS.set_batch_paused(&batch.id, true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_paused_batch_waits_for_resume() {
+        let service = SoftwareServiceImpl::new();
+        let batch = TASKS.create_batch(&[("placeholder".to_string(), None)]);
+        TASKS.set_batch_paused(&batch.id, true).unwrap();
+
+        let waiter = tokio::spawn({
+            let batch_id = batch.id.clone();
+            async move { TASKS.wait_while_paused(&batch_id).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiter.is_finished());
+
+        TASKS.set_batch_paused(&batch.id, false).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), waiter).await.unwrap().unwrap();
+
+        // 服务层查询同一注册表
+        let status = service.get_install_status(&batch.task_ids[0]).await.unwrap();
+        assert_eq!(status.unwrap().status, InstallStatus::Pending);
+        TASKS.cancel_batch(&batch.id).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_install() {
+        let service = SoftwareServiceImpl::new();
+        let batch = service.install_batch(vec![long_running_software()], None, false).await.unwrap();
+        let task_id = batch.task_ids[0].clone();
+        assert!(wait_for_status(&task_id, InstallStatus::Installing).await);
+
+        TASKS.cancel_task(&task_id).unwrap();
+        assert!(wait_for_status(&task_id, InstallStatus::Cancelled).await);
+
+        assert_eq!(TASKS.get_task(&task_id).unwrap().batch_id, Some(batch.id.clone()));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(TASKS.get_batch(&batch.id).unwrap().state, BatchState::Completed);
+    }
+
+    #[test]
+    fn test_finished_tasks_are_evicted() {
+        use crate::models::software::TaskKind;
+        use crate::services::tasks::TaskRegistry;
+
+        let registry = TaskRegistry::with_limit(2);
+        let batch = registry.create_batch(&[("batched".to_string(), None)]);
+        let batched = batch.task_ids[0].clone();
+        registry.finish_task(&batched, InstallStatus::Completed, "完成".to_string());
+
+        let mut task_ids = Vec::new();
+        for index in 0..3 {
+            let task_id = registry.create_task(TaskKind::ConfigDeploy, "software", None);
+            registry.start_task(&task_id, format!("任务 {}", index));
+            registry.finish_task(&task_id, InstallStatus::Completed, "完成".to_string());
+            task_ids.push(task_id);
+        }
+        // 未结束队列中的任务不会被移除
+        assert!(registry.get_task(&batched).is_some());
+        assert!(registry.get_task(&task_ids[0]).is_none());
+        assert!(registry.get_task(&task_ids[2]).is_some());
+
+        // 队列结束后按结束时间移除，任务全部被移除的队列一并移除
+        registry.finish_batch(&batch.id);
+        assert!(registry.get_task(&batched).is_none());
+        assert!(registry.get_batch(&batch.id).is_none());
+
+        // 未结束的任务不计入上限
+        let pending = registry.create_task(TaskKind::ConfigDeploy, "software", None);
+        let cancelled = registry.create_task(TaskKind::ConfigDeploy, "software", None);
+        registry.cancel_task(&cancelled).unwrap();
+        assert!(registry.get_task(&pending).is_some());
+        assert_eq!(registry.list_tasks().len(), 3);
+    }
+
+    #[test]
+    fn test_config_deploy_cancel_only_before_start() {
+        use crate::models::software::TaskKind;
+        use crate::services::tasks::TaskRegistry;
+
+        let registry = TaskRegistry::default();
+        let pending = registry.create_task(TaskKind::ConfigDeploy, "software", Some("config".to_string()));
+        assert_eq!(registry.cancel_task(&pending).unwrap().unwrap().status, InstallStatus::Cancelled);
+        assert!(!registry.start_task(&pending, "正在部署...".to_string()));
+
+        // 部署开始后拒绝取消，任务继续执行直到结束
+        let running = registry.create_task(TaskKind::ConfigDeploy, "software", Some("config".to_string()));
+        assert!(registry.start_task(&running, "正在部署...".to_string()));
+        assert!(registry.cancel_task(&running).is_err());
+        let status = registry.get_task(&running).unwrap();
+        assert_eq!(status.status, InstallStatus::Installing);
+        assert!(!*registry.cancel_signal(&running).unwrap().borrow());
+    }
+}
+
+#[cfg(test)]
+mod installer_tests {
+    use crate::models::software::{CommandSpec, InstallerType, Software, SoftwareCategory};
+    use crate::services::installer::*;
+    use crate::utils::command::quote_shell_arg;
+
+    fn software_with(install_command: &str, installer: InstallerType) -> Software {
+        let mut software = Software::new(
+            "Test Software".to_string(),
+            "1.0.0".to_string(),
+            None,
+            install_command.to_string(),
+            SoftwareCategory::Other,
+        );
+        software.installer = installer;
+        software
+    }
+
+    fn shell(command: &str) -> CommandSpec {
+        CommandSpec::Shell(command.to_string())
+    }
+
+    #[test]
+    fn test_detect_installer() {
+        assert_eq!(InstallerType::detect(&shell("winget install Git.Git")), InstallerType::Winget);
+        assert_eq!(InstallerType::detect(&shell("msiexec /i app.msi /qn")), InstallerType::Msi);
+        assert_eq!(InstallerType::detect(&shell("tar -xzf app.tar.gz")), InstallerType::Tarball);
+        assert_eq!(InstallerType::detect(&shell("setup.exe /VERYSILENT")), InstallerType::Inno);
+        assert_eq!(InstallerType::detect(&shell("setup.exe /S")), InstallerType::Other);
+
+        let argv = CommandSpec::Argv(vec!["winget".to_string(), "install".to_string()]);
+        assert_eq!(InstallerType::detect(&argv), InstallerType::Winget);
+    }
+
+    #[tokio::test]
+    async fn test_detect_installer_from_file() {
+        // 没有专有开关时按安装程序文件中的标记识别
+        let dir = std::env::temp_dir().join(format!("installer_tests_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let inno = dir.join("inno-setup.exe");
+        let nsis = dir.join("nsis-setup.exe");
+        std::fs::write(&inno, b"MZ\x90\x00 This installation was built with Inno Setup. \x00").unwrap();
+        std::fs::write(&nsis, b"MZ\x90\x00 Nullsoft Install System \x00").unwrap();
+        let with_program = |program: &std::path::Path, installer: InstallerType| {
+            let mut software = software_with("", installer);
+            software.install_command = CommandSpec::Argv(vec![program.to_string_lossy().to_string(), "/SILENT".to_string()]);
+            software
+        };
+        assert_eq!(detect_installer(&with_program(&inno, InstallerType::Other)).await, InstallerType::Inno);
+        assert_eq!(detect_installer(&with_program(&nsis, InstallerType::Other)).await, InstallerType::Other);
+        assert_eq!(detect_installer(&with_program(&inno, InstallerType::Msi)).await, InstallerType::Msi);
+        assert_eq!(detect_installer(&with_program(&dir.join("missing.exe"), InstallerType::Other)).await, InstallerType::Other);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_install_path_arguments_per_installer() {
+        let path = "D:\\Apps\\Test";
+        assert_eq!(
+            install_path_arguments(InstallerType::Winget, path).unwrap(),
+            vec!["--location", path]
+        );
+        assert_eq!(
+            install_path_arguments(InstallerType::Msi, path).unwrap(),
+            vec!["INSTALLDIR=D:\\Apps\\Test"]
+        );
+        assert_eq!(
+            install_path_arguments(InstallerType::Inno, path).unwrap(),
+            vec!["/DIR=D:\\Apps\\Test"]
+        );
+        assert_eq!(
+            install_path_arguments(InstallerType::Tarball, "/opt/test").unwrap(),
+            vec!["-C", "/opt/test"]
+        );
+        assert!(install_path_arguments(InstallerType::Other, path).is_err());
+    }
+
+    #[test]
+    fn test_validate_install_path() {
+        assert!(validate_install_path("C:\\Program Files\\App").is_ok());
+        assert!(validate_install_path("/opt/app").is_ok());
+        assert!(validate_install_path("").is_err());
+        assert!(validate_install_path("relative\\path").is_err());
+        assert!(validate_install_path("C:\\Apps\\..\\Windows").is_err());
+        assert!(validate_install_path("C:\\Apps\" & del *").is_err());
+    }
+
+    #[test]
+    fn test_plan_install() {
+        let mut software = software_with("winget install Test.App", InstallerType::Winget);
+        software.install_path = Some("C:\\Program Files\\Test".to_string());
+
+        // 未指定自定义路径时使用软件配置中的路径
+        let plan = plan_install(&software, None).unwrap();
+        assert_eq!(plan.install_path.as_deref(), Some("C:\\Program Files\\Test"));
+        assert_eq!(
+            plan.command,
+            shell(&format!(
+                "winget install Test.App --location {}",
+                quote_shell_arg("C:\\Program Files\\Test")
+            ))
+        );
+
+        // 自定义路径优先
+        let plan = plan_install(&software, Some("D:\\Test")).unwrap();
+        assert_eq!(plan.install_path.as_deref(), Some("D:\\Test"));
+
+        // 不支持自定义路径的安装程序明确报错
+        let other = software_with("setup.exe /S", InstallerType::Other);
+        assert!(plan_install(&other, Some("D:\\Test")).is_err());
+        let plan = plan_install(&other, None).unwrap();
+        assert_eq!(plan.command, shell("setup.exe /S"));
+        assert_eq!(plan.install_path, None);
+        assert!(plan.warnings.is_empty());
+
+        // 软件配置中的默认路径无法使用时保留原始命令，并在计划中说明
+        let mut other = other;
+        other.install_path = Some("C:\\Program Files\\Test".to_string());
+        let plan = plan_install(&other, None).unwrap();
+        assert_eq!(plan.command, shell("setup.exe /S"));
+        assert_eq!(plan.install_path, None);
+        assert_eq!(plan.warnings.len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod template_install_tests {
+    use crate::config::software_config::{render_config_paths, validate_templates};
+    use crate::models::software::{CommandSpec, InstallerType, Software, SoftwareCategory};
+    use crate::services::installer::plan_install;
+    use crate::utils::command::quote_shell_arg;
+    use crate::utils::template::TemplateContext;
+
+    fn templated_software(install_command: &str) -> Software {
+        let mut software = Software::new(
+            "Templated".to_string(),
+            "2.0.1".to_string(),
+            None,
+            install_command.to_string(),
+            SoftwareCategory::Other,
+        );
+        software.installer = InstallerType::Tarball;
+        software
+    }
+
+    #[test]
+    fn test_plan_renders_version_and_install_path() {
+        let mut software = templated_software("tar -xzf app-{{version}}.tar.gz -C {{install_path}}");
+        software.install_path = Some("/opt/app-{{version}}".to_string());
+
+        let plan = plan_install(&software, None).unwrap();
+        assert_eq!(plan.install_path.as_deref(), Some("/opt/app-2.0.1"));
+        // 命令中已经引用了安装路径，不再追加路径参数；shell 命令中的替换值会被引用
+        assert_eq!(
+            plan.command,
+            CommandSpec::Shell(format!(
+                "tar -xzf app-{}.tar.gz -C {}",
+                quote_shell_arg("2.0.1"),
+                quote_shell_arg("/opt/app-2.0.1")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_plan_expands_environment_variables() {
+        unsafe {
+            std::env::set_var("INSTALL_TEST_APPS_DIR", "/srv/apps");
+        }
+        let mut software = templated_software("tar -xzf app.tar.gz");
+        software.install_path = Some("${INSTALL_TEST_APPS_DIR}/app-{{version}}".to_string());
+        let plan = plan_install(&software, None).unwrap();
+        assert_eq!(plan.install_path.as_deref(), Some("/srv/apps/app-2.0.1"));
+
+        // 参数向量不经过 shell，其中的环境变量在执行前展开
+        let mut software = templated_software("");
+        software.install_command = CommandSpec::Argv(vec!["$INSTALL_TEST_APPS_DIR/setup".to_string(), "$$literal".to_string()]);
+        let plan = plan_install(&software, None).unwrap();
+        assert_eq!(
+            plan.command,
+            CommandSpec::Argv(vec!["/srv/apps/setup".to_string(), "$literal".to_string()])
+        );
+
+        software.install_path = Some("$INSTALL_TEST_UNDEFINED_DIR/app".to_string());
+        assert!(plan_install(&software, None).is_err());
+    }
+
+    #[test]
+    fn test_validate_templates_reports_undefined_variables() {
+        let software = templated_software("tar -xzf app-{{version}}.tar.gz -C {{install_path}}");
+        assert!(validate_templates(&software).is_ok());
+
+        let mut software = templated_software("tar -xzf app-{{build}}.tar.gz");
+        assert!(validate_templates(&software).is_err());
+
+        software.install_command = CommandSpec::Shell("tar -xzf app.tar.gz".to_string());
+        software.uninstall_command = Some(CommandSpec::Argv(vec![
+            "rm".to_string(),
+            "-rf".to_string(),
+            "{{unknown_dir}}".to_string(),
+        ]));
+        assert!(validate_templates(&software).is_err());
+    }
+
+    #[test]
+    fn test_plan_renders_hooks_and_config_paths() {
+        let mut software = templated_software("tar -xzf app-{{version}}.tar.gz");
+        software.install_path = Some("/opt/app-{{version}}".to_string());
+        software.hooks.pre_install = Some(CommandSpec::Argv(vec!["mkdir".to_string(), "-p".to_string(), "{{install_path}}".to_string()]));
+        software.hooks.post_install = Some(CommandSpec::Shell("chmod +x {{install_path}}/app".to_string()));
+        software.config_files = vec!["{{install_path}}/etc/app.toml".to_string(), "{{home}}/.app-{{version}}.toml".to_string()];
+        assert!(validate_templates(&software).is_ok());
+
+        let plan = plan_install(&software, Some("/srv/app")).unwrap();
+        assert_eq!(
+            plan.hooks.pre_install,
+            Some(CommandSpec::Argv(vec!["mkdir".to_string(), "-p".to_string(), "/srv/app".to_string()]))
+        );
+        assert_eq!(
+            plan.hooks.post_install,
+            Some(CommandSpec::Shell(format!("chmod +x {}/app", quote_shell_arg("/srv/app"))))
+        );
+
+        // 配置文件路径在加载时用默认安装路径渲染
+        let home = TemplateContext::new().render("{{home}}").unwrap();
+        assert_eq!(
+            render_config_paths(&software).unwrap(),
+            vec!["/opt/app-2.0.1/etc/app.toml".to_string(), format!("{}/.app-2.0.1.toml", home)]
+        );
+
+        software.hooks.post_install = Some(CommandSpec::Shell("echo {{build}}".to_string()));
+        assert!(validate_templates(&software).is_err());
+    }
+}
+
+#[cfg(test)]
+mod command_policy_tests {
+    use crate::config::CommandPolicyConfig;
+    use crate::models::software::{CommandSpec, InstallerType, Software, SoftwareCategory};
+    use crate::services::command_policy::check_command;
+    use crate::services::installer::plan_install;
+
+    fn argv(args: &[&str]) -> CommandSpec {
+        CommandSpec::Argv(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn test_allowed_executables() {
+        let policy = CommandPolicyConfig {
+            allowed_executables: vec!["winget".to_string(), "tar".to_string()],
+            ..CommandPolicyConfig::default()
+        };
+
+        assert!(check_command(&policy, &argv(&["winget", "install", "Git.Git"]), &[]).is_ok());
+        assert!(check_command(&policy, &argv(&["C:\\Windows\\winget.exe", "install"]), &[]).is_ok());
+        let shell = CommandSpec::Shell("tar -xzf app.tar.gz".to_string());
+        assert!(check_command(&policy, &shell, &[]).is_ok());
+        assert!(check_command(&policy, &argv(&["powershell", "-Command", "x"]), &[]).is_err());
+    }
+
+    #[test]
+    fn test_forbidden_metacharacters() {
+        let policy = CommandPolicyConfig::default();
+
+        // 默认只检查用户输入，软件配置中的 shell 命令可以使用变量和重定向
+        let shell = CommandSpec::Shell("tar -xzf app.tar.gz -C $HOME/apps > \"$HOME/install.log\"".to_string());
+        assert!(check_command(&policy, &shell, &[]).is_ok());
+
+        // 开启后同时检查 shell 命令
+        let strict = CommandPolicyConfig { check_shell_commands: true, ..CommandPolicyConfig::default() };
+        let shell = CommandSpec::Shell("winget install Git.Git && curl evil".to_string());
+        assert!(check_command(&policy, &shell, &[]).is_ok());
+        assert!(check_command(&strict, &shell, &[]).is_err());
+
+        // 参数向量不经过 shell，命令本身不做元字符检查
+        assert!(check_command(&strict, &argv(&["echo", "a;b"]), &[]).is_ok());
+
+        // 来自请求的输入始终检查
+        let shell = CommandSpec::Shell("winget install Git.Git".to_string());
+        assert!(check_command(&policy, &shell, &["C:\\Apps\\Git"]).is_ok());
+        assert!(check_command(&policy, &shell, &["C:\\Apps; rm -rf /"]).is_err());
+        assert!(check_command(&policy, &argv(&["winget"]), &["$(reboot)"]).is_err());
+    }
+
+    #[test]
+    fn test_argv_plan_appends_raw_arguments() {
+        let mut software = Software::new(
+            "Argv".to_string(),
+            "1.0.0".to_string(),
+            None,
+            String::new(),
+            SoftwareCategory::Other,
+        );
+        software.install_command = argv(&["winget", "install", "Test.App", "--version", "{{version}}"]);
+        software.installer = InstallerType::Winget;
+
+        let plan = plan_install(&software, Some("D:\\My Apps\\Test")).unwrap();
+        assert_eq!(
+            plan.command,
+            argv(&["winget", "install", "Test.App", "--version", "1.0.0", "--location", "D:\\My Apps\\Test"])
+        );
+
+        // 含有 shell 元字符的自定义路径在执行前被拒绝
+        assert!(plan_install(&software, Some("D:\\Apps`whoami`")).is_err());
+
+        // 软件配置中使用 $HOME 的 shell 命令按默认策略可以执行
+        software.install_command = CommandSpec::Shell("tar -xzf app.tar.gz -C $HOME/apps".to_string());
+        software.installer = InstallerType::Tarball;
+        let plan = plan_install(&software, None).unwrap();
+        assert_eq!(plan.command, CommandSpec::Shell("tar -xzf app.tar.gz -C $HOME/apps".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod config_file_tests {
+    use crate::config::{ConfigFilesSettings, GitStoreSettings, SecretSettings, TargetPolicySettings};
+    use crate::models::config_file::{ConfigFile, ConfigFileRequest, DeployMode};
+    use crate::services::config_files::{resolve_target_path, validate_config_file, ConfigFileService, ConfigFileServiceImpl};
+    use crate::services::deployment::TemplateRenderError;
+    use std::path::PathBuf;
+
+    // 每个测试使用独立的临时数据目录
+    // 测试记录默认的部署目标所在目录，不依赖也不修改用户主目录
+    fn target_root() -> PathBuf {
+        std::env::temp_dir().join("config_file_tests_targets")
+    }
+
+    fn temp_settings(name: &str) -> (ConfigFilesSettings, PathBuf) {
+        let root = std::env::temp_dir().join(format!("config_file_tests_{}_{}", name, uuid::Uuid::new_v4()));
+        let settings = ConfigFilesSettings {
+            backup_dir: root.join("backups").to_string_lossy().to_string(),
+            default_config_path: root.to_string_lossy().to_string(),
+            data_dir: root.join("data").to_string_lossy().to_string(),
+            backup_retention: Default::default(),
+            max_diff_size: 1024 * 1024,
+            max_content_size: 1024 * 1024,
+            secrets: SecretSettings {
+                key_file: Some(root.join("secret.key").to_string_lossy().to_string()),
+                passphrase_env: None,
+            },
+            git: GitStoreSettings {
+                repo_dir: root.join("source").to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            drift: Default::default(),
+            target_policy: TargetPolicySettings {
+                allowed_roots: vec![target_root().to_string_lossy().to_string(), root.to_string_lossy().to_string()],
+                denied_paths: Vec::new(),
+            },
+        };
+        (settings, root)
+    }
+
+    fn request(name: &str) -> ConfigFileRequest {
+        ConfigFileRequest {
+            name: name.to_string(),
+            description: Some("测试配置".to_string()),
+            source_path: "./configs/test/settings.json".to_string(),
+            target_path: target_root()
+                .join(uuid::Uuid::new_v4().to_string())
+                .join("settings.json")
+                .to_string_lossy()
+                .to_string(),
+            target_paths: Default::default(),
+            software_id: Some("test-id".to_string()),
+            is_directory: false,
+            backup_on_install: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            delete_extraneous: false,
+            deploy_mode: DeployMode::Copy,
+            template: false,
+            variables: Default::default(),
+            encrypted: false,
+            merge_strategy: Default::default(),
+            format: None,
+            permissions: Default::default(),
+            allow_unsafe_target: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_crud_persists_across_instances() {
+        let (settings, root) = temp_settings("crud");
+        let service = ConfigFileServiceImpl::new(&settings);
+        assert!(service.get_all_config_files().await.unwrap().is_empty());
+        let managed = |name: &str| ConfigFileRequest {
+            source_path: root.join("source/settings.json").to_string_lossy().to_string(),
+            ..request(name)
+        };
+
+        let created = service.create_config_file(ConfigFile::from_request(managed("Test"))).await.unwrap();
+        assert!(service.create_config_file(created.clone()).await.is_err());
+
+        // 新的服务实例从同一数据目录读取记录
+        let service = ConfigFileServiceImpl::new(&settings);
+        let loaded = service.get_config_file_by_id(&created.id).await.unwrap().unwrap();
+        assert_eq!(loaded.name, "Test");
+        assert_eq!(loaded.description.as_deref(), Some("测试配置"));
+
+        let mut changed = loaded.clone();
+        changed.apply_request(managed("Renamed"));
+        changed.id = "other-id".to_string();
+        let updated = service.update_config_file(&created.id, changed).await.unwrap().unwrap();
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.name, "Renamed");
+        assert_eq!(updated.created_at, created.created_at);
+        assert!(service.update_config_file("missing", updated.clone()).await.unwrap().is_none());
+
+        assert!(service.delete_config_file(&created.id).await.unwrap());
+        assert!(!service.delete_config_file(&created.id).await.unwrap());
+        assert!(service.get_config_file_by_id(&created.id).await.unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_validate_config_file() {
+        let valid = ConfigFile::from_request(request("Test"));
+        assert!(validate_config_file(&valid).is_ok());
+
+        let mut config_file = valid.clone();
+        config_file.name = " ".to_string();
+        assert!(validate_config_file(&config_file).is_err());
+
+        let mut config_file = valid.clone();
+        config_file.source_path = "../outside/settings.json".to_string();
+        assert!(validate_config_file(&config_file).is_err());
+
+        let mut config_file = valid.clone();
+        config_file.target_path = "relative/settings.json".to_string();
+        assert!(validate_config_file(&config_file).is_err());
+
+        let mut config_file = valid.clone();
+        config_file.target_path = "C:\\Users\\test\\settings.json".to_string();
+        assert!(validate_config_file(&config_file).is_ok());
+
+        let mut config_file = valid.clone();
+        config_file.include = vec!["[unclosed".to_string()];
+        assert!(validate_config_file(&config_file).is_err());
+
+        let mut config_file = valid.clone();
+        config_file.template = true;
+        config_file.deploy_mode = DeployMode::Symlink;
+        assert!(validate_config_file(&config_file).is_err());
+
+        // 源路径是目录但记录声明为文件
+        let mut config_file = valid;
+        config_file.source_path = std::env::temp_dir().to_string_lossy().to_string();
+        assert!(validate_config_file(&config_file).is_err());
+    }
+
+    #[test]
+    fn test_target_path_templates() {
+        let mut config_file = ConfigFile::from_request(request("Test"));
+        config_file.target_path = "{{config_root}}/{{app}}/settings.json".to_string();
+        config_file.variables.insert("config_root".to_string(), "/srv/configs".to_string());
+        assert!(validate_config_file(&config_file).is_err());
+
+        config_file.variables.insert("app".to_string(), "editor".to_string());
+        assert!(validate_config_file(&config_file).is_ok());
+        assert_eq!(resolve_target_path(&config_file).unwrap(), "/srv/configs/editor/settings.json");
+
+        // 缺少变量时返回缺失的变量名
+        config_file.variables.remove("app");
+        let err = resolve_target_path(&config_file).unwrap_err();
+        let err = err.downcast_ref::<TemplateRenderError>().unwrap();
+        assert!(err.missing.iter().any(|name| name.contains("app")));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlink_deploy_and_undo() {
+        let (settings, root) = temp_settings("symlink");
+        let source = root.join("source/settings.json");
+        let target = root.join("home/.app/settings.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&source, "{\"managed\": true}").unwrap();
+        std::fs::write(&target, "original").unwrap();
+
+        let mut request = request("Symlink");
+        request.source_path = source.to_string_lossy().to_string();
+        request.target_path = target.to_string_lossy().to_string();
+        request.deploy_mode = DeployMode::Symlink;
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(request)).await.unwrap();
+
+        let summary = service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(summary.changed, vec!["settings.json"]);
+        assert_eq!(summary.backups.len(), 1);
+        assert!(std::fs::symlink_metadata(&target).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "{\"managed\": true}");
+
+        // 再次部署时链接已存在，备份记录保留
+        let summary = service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(summary.unchanged, 1);
+        let record = service.get_config_file_by_id(&created.id).await.unwrap().unwrap().last_deployment.unwrap();
+        assert_eq!(record.backups.len(), 1);
+
+        let summary = service.undeploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(summary.removed.len(), 1);
+        assert_eq!(summary.restored.len(), 1);
+        assert!(!std::fs::symlink_metadata(&target).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "original");
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), "{\"managed\": true}");
+
+        // 没有部署记录时无法撤销
+        assert!(service.undeploy_config_file(&created.id).await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_capture_previews_then_writes_source() {
+        let (settings, root) = temp_settings("capture");
+        let source = root.join("source/app");
+        let target = root.join("home/app");
+        for (dir, content) in [(&source, "managed"), (&target, "edited")] {
+            std::fs::create_dir_all(dir.join("nested")).unwrap();
+            std::fs::write(dir.join("settings.conf"), content).unwrap();
+        }
+        std::fs::write(target.join("nested/new.conf"), "new").unwrap();
+        std::fs::write(target.join("cache.tmp"), "ignored").unwrap();
+
+        let mut request = request("Capture");
+        request.source_path = source.to_string_lossy().to_string();
+        request.target_path = target.to_string_lossy().to_string();
+        request.is_directory = true;
+        request.exclude = vec!["*.tmp".to_string()];
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(request)).await.unwrap();
+
+        // 未确认时只返回差异，不修改源文件
+        let preview = service.capture_config_file(&created.id, false).await.unwrap().unwrap();
+        assert!(preview.summary.is_none());
+        let paths: Vec<&str> = preview.changes.iter().map(|change| change.path.as_str()).collect();
+        assert_eq!(paths, vec!["nested/new.conf", "settings.conf"]);
+        assert!(preview.changes[1].diff.as_ref().unwrap().contains("+edited"));
+        assert_eq!(std::fs::read_to_string(source.join("settings.conf")).unwrap(), "managed");
+
+        let result = service.capture_config_file(&created.id, true).await.unwrap().unwrap();
+        let summary = result.summary.unwrap();
+        assert_eq!(summary.changed, vec!["settings.conf"]);
+        assert_eq!(summary.added, vec!["nested/new.conf"]);
+        assert_eq!(std::fs::read_to_string(source.join("settings.conf")).unwrap(), "edited");
+        assert!(!source.join("cache.tmp").exists());
+
+        // 捕获被记录，被覆盖的源文件登记到备份目录
+        let capture = service.get_config_file_by_id(&created.id).await.unwrap().unwrap().last_capture.unwrap();
+        assert_eq!(capture.files.len(), 2);
+        let backups = service.get_backups(&created.id).await.unwrap().unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(std::fs::read_to_string(&backups[0].backup_path).unwrap(), "managed");
+
+        let again = service.capture_config_file(&created.id, false).await.unwrap().unwrap();
+        assert!(again.changes.is_empty());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_diff_reports_file_and_directory_changes() {
+        let (settings, root) = temp_settings("diff");
+        let source = root.join("source/app");
+        let target = root.join("home/app");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(source.join("a.conf"), "key = new\n").unwrap();
+        std::fs::write(target.join("a.conf"), "key = old\n").unwrap();
+        std::fs::write(source.join("b.conf"), "b\n").unwrap();
+
+        let service = ConfigFileServiceImpl::new(&settings);
+        let mut file_request = request("File");
+        file_request.source_path = source.join("a.conf").to_string_lossy().to_string();
+        file_request.target_path = target.join("a.conf").to_string_lossy().to_string();
+        let file = service.create_config_file(ConfigFile::from_request(file_request)).await.unwrap();
+
+        let report = service.diff_config_file(&file.id, None).await.unwrap().unwrap();
+        assert_eq!(report.modified, 1);
+        let diff = report.files[0].diff.as_ref().unwrap();
+        assert!(diff.contains("-key = old") && diff.contains("+key = new"));
+
+        let mut dir_request = request("Directory");
+        dir_request.source_path = source.to_string_lossy().to_string();
+        dir_request.target_path = target.to_string_lossy().to_string();
+        dir_request.is_directory = true;
+        let dir = service.create_config_file(ConfigFile::from_request(dir_request)).await.unwrap();
+
+        // 目录只返回摘要，指定文件时返回完整差异
+        let report = service.diff_config_file(&dir.id, None).await.unwrap().unwrap();
+        assert_eq!((report.added, report.modified, report.removed), (1, 1, 0));
+        assert!(report.files.iter().all(|file| file.diff.is_none()));
+        let report = service.diff_config_file(&dir.id, Some("b.conf")).await.unwrap().unwrap();
+        assert_eq!(report.files.len(), 1);
+        assert!(report.files[0].diff.as_ref().unwrap().contains("+b"));
+
+        assert!(service.diff_config_file("missing", None).await.unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_template_reports_missing_variables_before_writing() {
+        use crate::services::deployment::TemplateRenderError;
+
+        let (settings, root) = temp_settings("template");
+        let source = root.join("source/app");
+        let target = root.join("home/app");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.conf"), "os = {{os}}\nuser = {{name}}\n").unwrap();
+        std::fs::write(source.join("b.conf"), "theme = {{theme}}\n").unwrap();
+
+        let mut request = request("Template");
+        request.source_path = source.to_string_lossy().to_string();
+        request.target_path = target.to_string_lossy().to_string();
+        request.is_directory = true;
+        request.template = true;
+        request.variables.insert("name".to_string(), "tester".to_string());
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(request)).await.unwrap();
+
+        // 差异预览和部署都会报告所有缺失的变量，且不写入任何文件
+        let error = service.diff_config_file(&created.id, None).await.unwrap_err();
+        let missing = &error.downcast_ref::<TemplateRenderError>().unwrap().missing;
+        assert_eq!(missing, &vec!["b.conf: theme".to_string()]);
+        assert!(service.deploy_config_file(&created.id).await.is_err());
+        assert!(!target.exists());
+
+        let mut updated = created.clone();
+        updated.variables.insert("theme".to_string(), "dark".to_string());
+        service.update_config_file(&created.id, updated).await.unwrap();
+
+        let summary = service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(summary.added.len(), 2);
+        let rendered = std::fs::read_to_string(target.join("a.conf")).unwrap();
+        assert!(rendered.ends_with("user = tester\n"));
+        assert!(rendered.starts_with(&format!("os = {}\n", std::env::consts::OS)));
+        assert_eq!(std::fs::read_to_string(target.join("b.conf")).unwrap(), "theme = dark\n");
+        // 模板源文件保持不变
+        assert!(std::fs::read_to_string(source.join("b.conf")).unwrap().contains("{{theme}}"));
+
+        assert!(service.capture_config_file(&created.id, false).await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_config_file_round_trip() {
+        let (settings, root) = temp_settings("encrypted");
+        let source = root.join("source/secrets.env");
+        let target = root.join("home/.secrets.env");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(settings.secrets.key_file.as_ref().unwrap(), "test-key").unwrap();
+        std::fs::write(&source, "TOKEN=abc\n").unwrap();
+
+        let mut request = request("Secrets");
+        request.source_path = source.to_string_lossy().to_string();
+        request.target_path = target.to_string_lossy().to_string();
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(request)).await.unwrap();
+
+        // 加密后源文件不再包含明文
+        let encrypted = service.encrypt_config_file(&created.id).await.unwrap().unwrap();
+        assert!(encrypted.config_file.encrypted);
+        assert!(encrypted.warnings.is_empty());
+        let stored = std::fs::read(&source).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("TOKEN"));
+
+        // 差异报告不包含文件内容，部署时写入解密后的内容
+        let report = service.diff_config_file(&created.id, None).await.unwrap().unwrap();
+        assert_eq!(report.added, 1);
+        assert!(report.files[0].redacted && report.files[0].diff.is_none());
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "TOKEN=abc\n");
+        assert!(service.diff_config_file(&created.id, None).await.unwrap().unwrap().files.is_empty());
+
+        // 捕获时重新加密写回源文件
+        std::fs::write(&target, "TOKEN=xyz\n").unwrap();
+        let preview = service.capture_config_file(&created.id, false).await.unwrap().unwrap();
+        assert_eq!(preview.changes.len(), 1);
+        assert!(preview.changes[0].redacted);
+        service.capture_config_file(&created.id, true).await.unwrap().unwrap();
+        let stored = std::fs::read(&source).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("TOKEN"));
+        assert!(service.diff_config_file(&created.id, None).await.unwrap().unwrap().files.is_empty());
+
+        // 密钥错误时拒绝部署
+        std::fs::write(settings.secrets.key_file.as_ref().unwrap(), "other-key").unwrap();
+        assert!(service.deploy_config_file(&created.id).await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    fn git(dir: &std::path::Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_git_store_history_revert_and_sync() {
+        if std::process::Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let (mut settings, root) = temp_settings("git");
+        let store = root.join("store");
+        let remote = root.join("remote.git");
+        std::fs::create_dir_all(&remote).unwrap();
+        git(&remote, &["init", "-q", "--bare"]);
+        settings.git.enabled = true;
+        settings.git.repo_dir = store.to_string_lossy().to_string();
+        settings.git.remote = Some(remote.to_string_lossy().to_string());
+
+        let source = store.join("app/settings.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "{\"v\": 1}\n").unwrap();
+        let mut request = request("Git");
+        request.source_path = source.to_string_lossy().to_string();
+        request.target_path = root.join("home/settings.json").to_string_lossy().to_string();
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(request)).await.unwrap();
+
+        // 源文件没有变化时更新不产生新的版本
+        std::fs::write(&source, "{\"v\": 2}\n").unwrap();
+        service.update_config_file(&created.id, created.clone()).await.unwrap();
+        service.update_config_file(&created.id, created.clone()).await.unwrap();
+        let history = service.get_history(&created.id).await.unwrap().unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history[0].message.starts_with("更新配置文件 Git"));
+        assert!(history[1].message.starts_with("创建配置文件 Git"));
+
+        let reverted = service.revert_config_file(&created.id, &history[1].id).await.unwrap().unwrap();
+        assert!(reverted.message.starts_with("还原配置文件 Git"));
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), "{\"v\": 1}\n");
+        assert_eq!(service.get_history(&created.id).await.unwrap().unwrap().len(), 3);
+        assert!(service.revert_config_file(&created.id, "--help").await.is_err());
+        assert!(service.revert_config_file(&created.id, "0000000").await.is_err());
+
+        // 推送到本地裸仓库，再从另一个克隆的修改中拉取
+        service.push_config_store().await.unwrap();
+        let clone = root.join("clone");
+        git(&root, &["clone", "-q", "-b", "main", &remote.to_string_lossy(), "clone"]);
+        std::fs::write(clone.join("app/settings.json"), "{\"v\": 3}\n").unwrap();
+        git(&clone, &["commit", "-q", "-am", "remote change"]);
+        git(&clone, &["push", "-q", "origin", "main"]);
+        service.pull_config_store().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), "{\"v\": 3}\n");
+
+        // 未启用版本管理时返回错误
+        settings.git.enabled = false;
+        let service = ConfigFileServiceImpl::new(&settings);
+        assert!(service.get_history(&created.id).await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_reports_plaintext_in_git_history() {
+        if std::process::Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let (mut settings, root) = temp_settings("git_encrypt");
+        let store = root.join("store");
+        settings.git.enabled = true;
+        settings.git.repo_dir = store.to_string_lossy().to_string();
+        std::fs::create_dir_all(&store).unwrap();
+        std::fs::write(settings.secrets.key_file.as_ref().unwrap(), "test-key").unwrap();
+        std::fs::write(store.join("plain.env"), "TOKEN=abc\n").unwrap();
+        std::fs::write(store.join("sealed.env"), "PASSWORD=xyz\n").unwrap();
+        let service = ConfigFileServiceImpl::new(&settings);
+
+        // 创建时提交的明文在加密之后仍可以从历史版本中读取
+        let mut plain = request("Plain");
+        plain.source_path = store.join("plain.env").to_string_lossy().to_string();
+        let created = service.create_config_file(ConfigFile::from_request(plain)).await.unwrap();
+        let result = service.encrypt_config_file(&created.id).await.unwrap().unwrap();
+        assert!(result.config_file.encrypted);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(!git(&store, &["show", "HEAD:plain.env"]).contains("TOKEN"));
+        assert!(git(&store, &["log", "-p", "--", "plain.env"]).contains("+TOKEN=abc"));
+
+        // 加密记录的明文源文件不会被提交，加密之后只有密文进入历史版本
+        let mut sealed = request("Sealed");
+        sealed.source_path = store.join("sealed.env").to_string_lossy().to_string();
+        sealed.encrypted = true;
+        let created = service.create_config_file(ConfigFile::from_request(sealed)).await.unwrap();
+        assert!(service.get_history(&created.id).await.unwrap().unwrap().is_empty());
+        let result = service.encrypt_config_file(&created.id).await.unwrap().unwrap();
+        assert!(result.warnings.is_empty());
+        assert_eq!(service.get_history(&created.id).await.unwrap().unwrap().len(), 1);
+        assert!(!git(&store, &["log", "-p", "--all"]).contains("PASSWORD"));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_merge_strategy_keeps_local_settings() {
+        use crate::models::config_file::MergeStrategy;
+
+        let (settings, root) = temp_settings("merge");
+        let source = root.join("source/settings.json");
+        let target = root.join("home/settings.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&source, "{\n    \"editor.fontSize\": 14\n}\n").unwrap();
+        std::fs::write(&target, "{\n    // 本机\n    \"window.zoomLevel\": 1,\n    \"editor.fontSize\": 12\n}\n").unwrap();
+
+        let mut request = request("Merge");
+        request.source_path = source.to_string_lossy().to_string();
+        request.target_path = target.to_string_lossy().to_string();
+        request.merge_strategy = MergeStrategy::DeepMerge;
+
+        // 合并策略要求复制方式部署，且能识别目标文件格式
+        let mut invalid = ConfigFile::from_request(request);
+        invalid.deploy_mode = DeployMode::Symlink;
+        assert!(validate_config_file(&invalid).is_err());
+        invalid.deploy_mode = DeployMode::Copy;
+        invalid.target_path = root.join("home/settings.txt").to_string_lossy().to_string();
+        assert!(validate_config_file(&invalid).is_err());
+        invalid.target_path = target.to_string_lossy().to_string();
+
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(invalid).await.unwrap();
+        let report = service.diff_config_file(&created.id, None).await.unwrap().unwrap();
+        assert_eq!(report.modified, 1);
+
+        let summary = service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(summary.changed, vec!["settings.json".to_string()]);
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "{\n    // 本机\n    \"window.zoomLevel\": 1,\n    \"editor.fontSize\": 14\n}\n"
+        );
+        assert!(service.diff_config_file(&created.id, None).await.unwrap().unwrap().files.is_empty());
+        assert!(service.capture_config_file(&created.id, false).await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_per_os_target_paths() {
+        use crate::services::config_files::NotApplicableError;
+
+        let (settings, root) = temp_settings("per_os");
+        let source = root.join("source/settings.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "{}\n").unwrap();
+
+        // 没有默认目标路径时必须设置按系统区分的路径
+        let mut per_os = request("PerOs");
+        per_os.source_path = source.to_string_lossy().to_string();
+        per_os.target_path = String::new();
+        assert!(validate_config_file(&ConfigFile::from_request(per_os)).is_err());
+
+        let mut per_os = request("PerOs");
+        per_os.source_path = source.to_string_lossy().to_string();
+        per_os.target_path = String::new();
+        per_os.target_paths.distros.insert("Ubuntu".to_string(), "~/.config/app/settings.json".to_string());
+        assert!(validate_config_file(&ConfigFile::from_request(per_os)).is_err());
+
+        // 只为其他系统设置了目标路径，当前系统不适用，不写入任何文件
+        let mut per_os = request("PerOs");
+        per_os.source_path = source.to_string_lossy().to_string();
+        per_os.target_path = String::new();
+        let other = root.join("other/settings.json").to_string_lossy().to_string();
+        if std::env::consts::OS == "windows" {
+            per_os.target_paths.linux = Some(other.clone());
+        } else {
+            per_os.target_paths.windows = Some("%APPDATA%/app/settings.json".to_string());
+        }
+        let config_file = ConfigFile::from_request(per_os);
+        assert!(validate_config_file(&config_file).is_ok());
+
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(config_file).await.unwrap();
+        let error = service.deploy_config_file(&created.id).await.unwrap_err();
+        let not_applicable = error.downcast_ref::<NotApplicableError>().unwrap();
+        assert!(not_applicable.os.starts_with(std::env::consts::OS));
+        assert!(service.diff_config_file(&created.id, None).await.is_err());
+        assert!(!root.join("other").exists());
+
+        // 当前系统的路径优先于默认目标路径
+        let target = root.join("home/settings.json");
+        let mut config_file = created.clone();
+        config_file.target_path = other;
+        match std::env::consts::OS {
+            "windows" => config_file.target_paths.windows = Some(target.to_string_lossy().to_string()),
+            "macos" => config_file.target_paths.macos = Some(target.to_string_lossy().to_string()),
+            _ => config_file.target_paths.linux = Some(target.to_string_lossy().to_string()),
+        }
+        service.update_config_file(&created.id, config_file).await.unwrap();
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert!(target.is_file());
+        assert!(!root.join("other").exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_drift_detection() {
+        use crate::services::drift::DriftStatus;
+
+        let (settings, root) = temp_settings("drift");
+        let source = root.join("source");
+        let target = root.join("home/app");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.conf"), "a = 1\n").unwrap();
+        std::fs::write(source.join("b.conf"), "b = 1\n").unwrap();
+
+        let mut request = request("Drift");
+        request.source_path = source.to_string_lossy().to_string();
+        request.target_path = target.to_string_lossy().to_string();
+        request.is_directory = true;
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(request)).await.unwrap();
+
+        let status = |scan: crate::services::drift::DriftScan| {
+            let entry = scan.entries.into_iter().find(|entry| entry.id == created.id).unwrap();
+            (entry.status, entry.files.into_iter().map(|file| (file.path, file.status)).collect::<Vec<_>>())
+        };
+        assert_eq!(status(service.check_drift(true).await.unwrap()).0, DriftStatus::NotDeployed);
+
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(status(service.check_drift(true).await.unwrap()), (DriftStatus::InSync, vec![]));
+
+        std::fs::write(target.join("a.conf"), "a = 2\n").unwrap();
+        assert_eq!(
+            status(service.check_drift(true).await.unwrap()),
+            (DriftStatus::ModifiedLocally, vec![("a.conf".to_string(), DriftStatus::ModifiedLocally)])
+        );
+
+        // 不同文件分别在两侧被修改时整体为两侧都有变化
+        std::fs::write(source.join("b.conf"), "b = 2\n").unwrap();
+        assert_eq!(status(service.check_drift(true).await.unwrap()).0, DriftStatus::BothChanged);
+
+        std::fs::remove_file(target.join("b.conf")).unwrap();
+        assert_eq!(status(service.check_drift(true).await.unwrap()).0, DriftStatus::Missing);
+
+        // 重新部署后恢复同步，源目录新增的文件视为源文件变化
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(status(service.check_drift(true).await.unwrap()).0, DriftStatus::InSync);
+        std::fs::write(source.join("c.conf"), "c = 1\n").unwrap();
+        assert_eq!(
+            status(service.check_drift(true).await.unwrap()),
+            (DriftStatus::SourceChanged, vec![("c.conf".to_string(), DriftStatus::SourceChanged)])
+        );
+
+        // 捕获后源文件与目标位置一致
+        std::fs::remove_file(source.join("c.conf")).unwrap();
+        std::fs::write(target.join("a.conf"), "a = 3\n").unwrap();
+        service.capture_config_file(&created.id, true).await.unwrap().unwrap();
+        assert_eq!(status(service.check_drift(true).await.unwrap()).0, DriftStatus::InSync);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_deploy_linked_config_files() {
+        use crate::models::config_file::LinkedDeployStatus;
+
+        let (settings, root) = temp_settings("linked");
+        let source = root.join("source/app.conf");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "enabled = true\n").unwrap();
+        let service = ConfigFileServiceImpl::new(&settings);
+
+        let create = |name: &str, software_id: &str, target: Option<PathBuf>| {
+            let mut linked = request(name);
+            linked.source_path = source.to_string_lossy().to_string();
+            linked.software_id = Some(software_id.to_string());
+            match target {
+                Some(target) => linked.target_path = target.to_string_lossy().to_string(),
+                // 只适用于不存在的发行版，当前系统不适用
+                None => {
+                    linked.target_path = String::new();
+                    linked.target_paths.distros.insert("no-such-distro".to_string(), "~/.config/app.conf".to_string());
+                }
+            }
+            ConfigFile::from_request(linked)
+        };
+        let deployed = service.create_config_file(create("Deployed", "app", Some(root.join("home/app.conf")))).await.unwrap();
+        service.create_config_file(create("Skipped", "app", None)).await.unwrap();
+        service.create_config_file(create("Other", "other", Some(root.join("home/other.conf")))).await.unwrap();
+
+        assert_eq!(service.get_linked_config_files("app").await.unwrap().len(), 2);
+        let deployments = service.deploy_linked_config_files("app").await.unwrap();
+        let statuses: Vec<_> = deployments.iter().map(|item| (item.name.as_str(), item.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![("Deployed", LinkedDeployStatus::Deployed), ("Skipped", LinkedDeployStatus::NotApplicable)]
+        );
+        assert!(root.join("home/app.conf").is_file());
+        assert!(!root.join("home/other.conf").exists());
+
+        let record = service.get_config_file_by_id(&deployed.id).await.unwrap().unwrap();
+        assert!(record.last_deployment.is_some());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_deploy_sets_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (settings, root) = temp_settings("permissions");
+        let source = root.join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("run.sh"), "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(source.join("run.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(source.join("config"), "Host *\n").unwrap();
+        std::fs::set_permissions(source.join("config"), std::fs::Permissions::from_mode(0o644)).unwrap();
+        let mode = |path: PathBuf| std::fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        let service = ConfigFileServiceImpl::new(&settings);
+
+        // 默认保留源文件的权限
+        let mut script = request("Script");
+        script.source_path = source.join("run.sh").to_string_lossy().to_string();
+        script.target_path = root.join("home/bin/run.sh").to_string_lossy().to_string();
+        let created = service.create_config_file(ConfigFile::from_request(script)).await.unwrap();
+        let summary = service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(mode(root.join("home/bin/run.sh")), 0o755);
+        assert_eq!(summary.permissions[0].mode.as_deref(), Some("0755"));
+        assert!(summary.permissions[0].verified);
+
+        // .ssh 目录中的文件默认只有所有者可以读写
+        let mut ssh = request("Ssh");
+        ssh.source_path = source.join("config").to_string_lossy().to_string();
+        ssh.target_path = root.join("home/.ssh/config").to_string_lossy().to_string();
+        let created = service.create_config_file(ConfigFile::from_request(ssh)).await.unwrap();
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(mode(root.join("home/.ssh/config")), 0o600);
+
+        // 新内容先以最终权限写入临时文件再替换目标，目标是链接时写入链接指向的文件
+        std::fs::write(source.join("config"), "Host example\n").unwrap();
+        let real = root.join("home/real_config");
+        std::fs::rename(root.join("home/.ssh/config"), &real).unwrap();
+        std::os::unix::fs::symlink(&real, root.join("home/.ssh/config")).unwrap();
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert!(std::fs::symlink_metadata(root.join("home/.ssh/config")).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(&real).unwrap(), "Host example\n");
+        assert_eq!(mode(real.clone()), 0o600);
+        let names = |dir: PathBuf| std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().file_name()).collect::<Vec<_>>();
+        assert!(names(root.join("home")).iter().all(|name| !name.to_string_lossy().ends_with(".tmp")));
+        assert_eq!(names(root.join("home/.ssh")).len(), 1);
+
+        // 显式设置的权限优先，内容未变化时也会修正权限
+        let mut config_file = created.clone();
+        config_file.permissions.mode = Some("0640".to_string());
+        config_file.permissions.owner = Some("0".to_string());
+        service.update_config_file(&created.id, config_file).await.unwrap();
+        let summary = service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(mode(root.join("home/.ssh/config")), 0o640);
+        let check = &summary.permissions[0];
+        if unsafe { libc::geteuid() } == 0 {
+            assert!(check.verified);
+            assert_eq!(check.uid, Some(0));
+        } else {
+            assert!(!check.verified);
+            assert_eq!(check.warnings.len(), 1);
+        }
+
+        // 权限格式错误或用于链接部署时拒绝
+        let mut invalid = created;
+        invalid.permissions.mode = Some("0999".to_string());
+        assert!(validate_config_file(&invalid).is_err());
+        invalid.permissions.mode = Some("600".to_string());
+        assert!(validate_config_file(&invalid).is_ok());
+        invalid.deploy_mode = DeployMode::Symlink;
+        assert!(validate_config_file(&invalid).is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_deploy_task_reports_file_results() {
+        use crate::TASKS;
+        use crate::models::config_file::FileResultStatus;
+        use crate::models::software::{InstallStatus, TaskKind};
+
+        let (settings, root) = temp_settings("deploy_task");
+        let source = root.join("source");
+        let target = root.join("home/app");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.conf"), "a = 1\n").unwrap();
+        std::fs::write(source.join("b.conf"), "b = 1\n").unwrap();
+        std::fs::write(source.join("c.conf"), "c = 1\n").unwrap();
+        // a.conf 内容不同，先备份再覆盖；b.conf 的位置被目录占用，写入失败
+        std::fs::create_dir_all(target.join("b.conf")).unwrap();
+        std::fs::write(target.join("a.conf"), "a = 0\n").unwrap();
+
+        let mut deploy = request("Task");
+        deploy.source_path = source.to_string_lossy().to_string();
+        deploy.target_path = target.to_string_lossy().to_string();
+        deploy.is_directory = true;
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(deploy)).await.unwrap();
+        assert!(service.start_deploy_task("missing").await.unwrap().is_none());
+
+        let task_id = service.start_deploy_task(&created.id).await.unwrap().unwrap();
+        let mut task = TASKS.get_task(&task_id).unwrap();
+        for _ in 0..100 {
+            if task.status.is_finished() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            task = TASKS.get_task(&task_id).unwrap();
+        }
+
+        assert_eq!(task.kind, TaskKind::ConfigDeploy);
+        assert_eq!(task.config_id.as_deref(), Some(created.id.as_str()));
+        assert_eq!(task.status, InstallStatus::Failed);
+        let statuses: Vec<_> = task.files.iter().map(|file| (file.path.as_str(), file.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("a.conf", FileResultStatus::BackedUp),
+                ("b.conf", FileResultStatus::Failed),
+                ("c.conf", FileResultStatus::Written),
+            ]
+        );
+        assert!(task.files[0].backup.is_some());
+        assert_eq!(task.errors.len(), 1);
+        assert!(task.errors[0].starts_with("b.conf: "));
+        assert_eq!(std::fs::read_to_string(target.join("c.conf")).unwrap(), "c = 1\n");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_archive() {
+        use crate::services::archive::{ArchiveFormat, ConflictPolicy, ImportAction, InvalidArchiveError};
+        use crate::utils::diff::ChangeKind;
+        use std::io::Write;
+
+        // 导出的机器
+        let (settings, root) = temp_settings("export");
+        let source_dir = root.join("source/app");
+        std::fs::create_dir_all(source_dir.join("themes")).unwrap();
+        std::fs::write(source_dir.join("app.conf"), "a = 1\n").unwrap();
+        std::fs::write(source_dir.join("themes/dark.conf"), "dark\n").unwrap();
+        let single = root.join("source/settings.json");
+        std::fs::write(&single, "{}\n").unwrap();
+
+        let mut directory = request("App");
+        directory.source_path = source_dir.to_string_lossy().to_string();
+        directory.target_path = target_root().join(uuid::Uuid::new_v4().to_string()).join("app").to_string_lossy().to_string();
+        directory.is_directory = true;
+        let mut file = request("Settings");
+        file.source_path = single.to_string_lossy().to_string();
+        let service = ConfigFileServiceImpl::new(&settings);
+        let app = service.create_config_file(ConfigFile::from_request(directory)).await.unwrap();
+        service.create_config_file(ConfigFile::from_request(file)).await.unwrap();
+        assert!(service.export_config_files(Some(&["missing".to_string()]), ArchiveFormat::Zip).await.unwrap().is_none());
+        let tar = service.export_config_files(None, ArchiveFormat::TarZst).await.unwrap().unwrap();
+        let zip = service.export_config_files(Some(std::slice::from_ref(&app.id)), ArchiveFormat::Zip).await.unwrap().unwrap();
+
+        // 导入的机器：预览不写入任何内容
+        let (settings, other) = temp_settings("import");
+        let service = ConfigFileServiceImpl::new(&settings);
+        let preview = service.import_config_files(&tar, ConflictPolicy::Skip, true).await.unwrap();
+        assert_eq!(preview.entries.len(), 2);
+        assert!(preview.entries.iter().all(|entry| entry.action == ImportAction::Create));
+        assert_eq!(preview.entries[0].changes.len(), 2);
+        assert!(service.get_all_config_files().await.unwrap().is_empty());
+
+        // 绝对源路径改为存放到导入目录
+        let report = service.import_config_files(&tar, ConflictPolicy::Skip, false).await.unwrap();
+        assert!(report.entries.iter().all(|entry| entry.action == ImportAction::Create && entry.error.is_none()));
+        let imported = service.get_config_file_by_id(&app.id).await.unwrap().unwrap();
+        let imported_dir = other.join("data/imported").join(&app.id).join("app");
+        assert_eq!(PathBuf::from(&imported.source_path), imported_dir);
+        assert_eq!(std::fs::read_to_string(imported_dir.join("themes/dark.conf")).unwrap(), "dark\n");
+        assert!(imported.last_deployment.is_none());
+
+        let report = service.import_config_files(&zip, ConflictPolicy::Skip, false).await.unwrap();
+        assert_eq!(report.entries[0].action, ImportAction::Skip);
+
+        // 覆盖前预览源文件的变化，覆盖时先备份
+        std::fs::write(imported_dir.join("app.conf"), "a = 2\n").unwrap();
+        std::fs::write(imported_dir.join("local.conf"), "local\n").unwrap();
+        let preview = service.import_config_files(&zip, ConflictPolicy::Overwrite, true).await.unwrap();
+        let changes: Vec<_> = preview.entries[0].changes.iter().map(|change| (change.path.as_str(), change.change)).collect();
+        assert_eq!(changes, vec![("app.conf", ChangeKind::Modified), ("local.conf", ChangeKind::Removed)]);
+        service.import_config_files(&zip, ConflictPolicy::Overwrite, false).await.unwrap();
+        assert_eq!(std::fs::read_to_string(imported_dir.join("app.conf")).unwrap(), "a = 1\n");
+        assert!(!imported_dir.join("local.conf").exists());
+        assert_eq!(service.get_backups(&app.id).await.unwrap().unwrap().len(), 2);
+
+        // 重命名时使用新的 ID 和名称
+        let report = service.import_config_files(&zip, ConflictPolicy::Rename, false).await.unwrap();
+        let entry = &report.entries[0];
+        assert_eq!(entry.action, ImportAction::Rename);
+        assert_eq!(entry.name, "App (2)");
+        assert_eq!(entry.conflict_with.as_deref(), Some(app.id.as_str()));
+        assert_ne!(entry.id, app.id);
+        assert_eq!(service.get_all_config_files().await.unwrap().len(), 3);
+
+        // 归档中的路径不能跳出解压目录
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("../evil", zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"evil").unwrap();
+        let evil = writer.finish().unwrap().into_inner();
+        let error = service.import_config_files(&evil, ConflictPolicy::Skip, false).await.unwrap_err();
+        assert!(error.is::<InvalidArchiveError>());
+        assert!(service.import_config_files(b"not an archive", ConflictPolicy::Skip, true).await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+        let _ = std::fs::remove_dir_all(other);
+    }
+
+    #[tokio::test]
+    async fn test_deploy_refuses_invalid_syntax() {
+        use crate::models::config_file::ConfigFormat;
+        use crate::utils::syntax::InvalidFormatError;
+
+        let (settings, root) = temp_settings("syntax");
+        let source = root.join("source/settings.json");
+        let target = root.join("home/settings.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&source, "{\n  \"a\": 1\n  \"b\": 2\n}\n").unwrap();
+        std::fs::write(&target, "{\"a\": 0}\n").unwrap();
+
+        let mut broken = request("Broken");
+        broken.source_path = source.to_string_lossy().to_string();
+        broken.target_path = target.to_string_lossy().to_string();
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(broken)).await.unwrap();
+
+        // 预演和部署都在写入之前拒绝，并报告错误位置
+        for error in [
+            service.diff_config_file(&created.id, None).await.unwrap_err(),
+            service.deploy_config_file(&created.id).await.unwrap_err(),
+        ] {
+            let invalid = error.downcast_ref::<InvalidFormatError>().unwrap();
+            assert_eq!(invalid.errors.len(), 1);
+            assert_eq!(invalid.errors[0].path, "settings.json");
+            assert_eq!(invalid.errors[0].format, ConfigFormat::Jsonc);
+            assert_eq!((invalid.errors[0].line, invalid.errors[0].column), (3, 3));
+        }
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "{\"a\": 0}\n");
+
+        // 模板渲染后的内容才是实际写入的内容
+        let mut templated = service.get_config_file_by_id(&created.id).await.unwrap().unwrap();
+        std::fs::write(&source, "{\"a\": {{value}}}\n").unwrap();
+        templated.template = true;
+        templated.variables.insert("value".to_string(), "1".to_string());
+        service.update_config_file(&created.id, templated.clone()).await.unwrap();
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "{\"a\": 1}\n");
+
+        // 显式设置的格式优先于文件名，text 不做校验
+        templated.template = false;
+        templated.format = Some(ConfigFormat::Json);
+        std::fs::write(&source, "// 注释\n{}\n").unwrap();
+        service.update_config_file(&created.id, templated.clone()).await.unwrap();
+        assert!(service.deploy_config_file(&created.id).await.is_err());
+        templated.format = Some(ConfigFormat::Text);
+        service.update_config_file(&created.id, templated).await.unwrap();
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "// 注释\n{}\n");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_read_and_write_source_content() {
+        use crate::services::content::{ContentError, ContentUpdate, TextEncoding, UploadedFile};
+
+        let (settings, root) = temp_settings("content");
+        let source = root.join("source/settings.ini");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(settings.secrets.key_file.as_ref().unwrap(), "test-key").unwrap();
+        // UTF-16LE 带 BOM
+        let original: Vec<u8> = [0xff, 0xfe].into_iter().chain("a=1\n".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        std::fs::write(&source, &original).unwrap();
+
+        let mut single = request("Content");
+        single.source_path = source.to_string_lossy().to_string();
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(single)).await.unwrap();
+
+        let content = service.get_content(&created.id, None).await.unwrap().unwrap();
+        assert_eq!(content.path, "settings.ini");
+        assert_eq!(content.encoding, Some(TextEncoding::Utf16le));
+        assert_eq!(content.content.as_deref(), Some("a=1\n"));
+
+        // 哈希不一致时拒绝写入，写入时保持原来的编码并备份旧内容
+        let update = |sha256: &str, text: &str| ContentUpdate {
+            path: None,
+            content: text.to_string(),
+            sha256: Some(sha256.to_string()),
+            encoding: None,
+        };
+        let error = service.put_content(&created.id, update("0000", "a=2\n")).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<ContentError>(), Some(ContentError::Conflict { current: Some(_) })));
+        let written = service.put_content(&created.id, update(&content.sha256, "a=2\n")).await.unwrap().unwrap();
+        assert_ne!(written.sha256, content.sha256);
+        let stored = std::fs::read(&source).unwrap();
+        assert_eq!(&stored[..2], &[0xff, 0xfe]);
+        assert_eq!(service.get_content(&created.id, None).await.unwrap().unwrap().content.as_deref(), Some("a=2\n"));
+        let backups = service.get_backups(&created.id).await.unwrap().unwrap();
+        assert_eq!(std::fs::read(&backups[0].backup_path).unwrap(), original);
+
+        let large = "x".repeat(settings.max_content_size as usize + 1);
+        let error = service.put_content(&created.id, update(&written.sha256, &large)).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<ContentError>(), Some(ContentError::TooLarge { .. })));
+
+        // 目录需要指定安全的相对路径，空哈希表示文件应当尚不存在
+        let source_dir = root.join("source/app");
+        std::fs::create_dir_all(&source_dir).unwrap();
+        let mut directory = request("Directory");
+        directory.source_path = source_dir.to_string_lossy().to_string();
+        directory.is_directory = true;
+        let directory = service.create_config_file(ConfigFile::from_request(directory)).await.unwrap();
+        for path in [None, Some("../escape.conf")] {
+            let error = service.get_content(&directory.id, path).await.unwrap_err();
+            assert!(matches!(error.downcast_ref::<ContentError>(), Some(ContentError::Invalid(_))));
+        }
+        let mut create = update("", "new\n");
+        create.path = Some("sub/new.conf".to_string());
+        service.put_content(&directory.id, create).await.unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(source_dir.join("sub/new.conf")).unwrap(), "new\n");
+        let mut create = update("", "again\n");
+        create.path = Some("sub/new.conf".to_string());
+        assert!(service.put_content(&directory.id, create).await.is_err());
+
+        let uploads = vec![
+            UploadedFile { path: "a.conf".to_string(), content: b"a\n".to_vec() },
+            UploadedFile { path: "b.conf".to_string(), content: b"b\n".to_vec() },
+        ];
+        let results = service.upload_content(&directory.id, uploads, None).await.unwrap().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(std::fs::read_to_string(source_dir.join("b.conf")).unwrap(), "b\n");
+
+        // 加密配置文件写入时先加密，读取时不返回内容
+        service.encrypt_config_file(&created.id).await.unwrap().unwrap();
+        let uploaded = UploadedFile { path: "ignored".to_string(), content: b"TOKEN=abc\n".to_vec() };
+        service.upload_content(&created.id, vec![uploaded], None).await.unwrap().unwrap();
+        assert!(crate::services::secrets::is_encrypted(&std::fs::read(&source).unwrap()));
+        let content = service.get_content(&created.id, None).await.unwrap().unwrap();
+        assert!(content.redacted && content.content.is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_target_policy_and_override_audit() {
+        use crate::services::target_policy::{TargetPolicy, UnsafeTargetError};
+
+        let (mut settings, root) = temp_settings("policy");
+        let home = root.join("home");
+        let outside = root.parent().unwrap().join(format!("outside_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&home).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        settings.target_policy.allowed_roots = vec![home.to_string_lossy().to_string()];
+        settings.target_policy.denied_paths = vec![home.join("private").to_string_lossy().to_string()];
+        let policy = TargetPolicy::new(&settings);
+
+        // `..` 和符号链接解析之后再检查，包括尚不存在的目标和断开的链接
+        assert!(policy.check(&home.join("app/settings.json"), true, false).is_ok());
+        assert!(policy.check(&home.join("../outside.json"), true, false).is_err());
+        std::os::unix::fs::symlink(&outside, home.join("escape")).unwrap();
+        assert!(policy.check(&home.join("escape/settings.json"), true, false).is_err());
+        std::os::unix::fs::symlink(outside.join("missing.json"), home.join("dangling.json")).unwrap();
+        assert!(policy.check(&home.join("dangling.json"), true, false).is_err());
+        // 链接部署只替换链接本身，不解析目标位置的链接
+        assert!(policy.check(&home.join("dangling.json"), false, false).is_ok());
+
+        // 系统目录、本服务的数据目录和配置仓库以及额外禁止的位置，目录部署不能包含受保护的位置
+        assert!(policy.check(std::path::Path::new("/etc/passwd"), true, false).is_err());
+        assert!(policy.check(&PathBuf::from(&settings.data_dir).join("config_files.json"), true, false).is_err());
+        let error = policy.check(&PathBuf::from(&settings.git.repo_dir).join("app/settings.json"), true, false).unwrap_err();
+        assert!(error.reason.contains(&settings.git.repo_dir));
+        assert!(policy.check(&root, true, true).is_err());
+        assert!(policy.check(&home.join("private/key"), true, false).is_err());
+        assert!(policy.check(&home, true, true).is_err());
+
+        let source = root.join("source/settings.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "{}\n").unwrap();
+        let target = outside.join("settings.json");
+        let mut unsafe_request = request("Outside");
+        unsafe_request.source_path = source.to_string_lossy().to_string();
+        unsafe_request.target_path = target.to_string_lossy().to_string();
+        let service = ConfigFileServiceImpl::new(&settings);
+        let error = service.create_config_file(ConfigFile::from_request(unsafe_request)).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeTargetError>().is_some());
+        assert!(service.get_audit_log(None).await.unwrap().is_empty());
+
+        // 明确放行后可以保存和部署，每次放行都写入审计日志
+        let mut allowed = request("Outside");
+        allowed.source_path = source.to_string_lossy().to_string();
+        allowed.target_path = target.to_string_lossy().to_string();
+        allowed.allow_unsafe_target = true;
+        let created = service.create_config_file(ConfigFile::from_request(allowed)).await.unwrap();
+        service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "{}\n");
+        let entries = service.get_audit_log(Some(&created.id)).await.unwrap();
+        let actions: Vec<&str> = entries.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(actions, ["保存", "部署"]);
+        assert_eq!(entries[1].target_path, target.to_string_lossy());
+        assert!(service.get_audit_log(Some("other")).await.unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(root);
+        let _ = std::fs::remove_dir_all(outside);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_records_before_writing_sources() {
+        use crate::services::archive::{ArchiveFormat, ConflictPolicy, ImportAction};
+
+        let (settings, root) = temp_settings("import_export_policy");
+        let source = root.join("source/settings.json");
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "{}\n").unwrap();
+        let mut file = request("Settings");
+        file.source_path = source.to_string_lossy().to_string();
+        let service = ConfigFileServiceImpl::new(&settings);
+        service.create_config_file(ConfigFile::from_request(file)).await.unwrap();
+        let archive = service.export_config_files(None, ArchiveFormat::Zip).await.unwrap().unwrap();
+
+        // 导入的机器不允许部署到记录的目标目录：预演和导入都报告失败，且不写入源文件
+        let (mut settings, other) = temp_settings("import_policy");
+        settings.target_policy.allowed_roots = vec![other.to_string_lossy().to_string()];
+        let service = ConfigFileServiceImpl::new(&settings);
+        for dry_run in [true, false] {
+            let report = service.import_config_files(&archive, ConflictPolicy::Skip, dry_run).await.unwrap();
+            assert_eq!(report.entries[0].action, ImportAction::Failed);
+            assert!(report.entries[0].error.as_deref().unwrap().contains("不允许部署"));
+        }
+        assert!(service.get_all_config_files().await.unwrap().is_empty());
+        assert!(!other.join("data/imported").exists());
+
+        let _ = std::fs::remove_dir_all(root);
+        let _ = std::fs::remove_dir_all(other);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_and_restores_cannot_escape_policy() {
+        use crate::models::config_file::FileResultStatus;
+        use crate::services::target_policy::{TargetPolicy, UnsafeSourceError, UnsafeTargetError};
+
+        let (mut settings, root) = temp_settings("symlink_policy");
+        let home = root.join("home");
+        let outside = root.parent().unwrap().join(format!("outside_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(home.join("app")).unwrap();
+        std::fs::create_dir_all(outside.join("deep/dir")).unwrap();
+        settings.target_policy.allowed_roots = vec![home.to_string_lossy().to_string()];
+        let policy = TargetPolicy::new(&settings);
+
+        // 链接之后的 `..` 作用于链接指向的目录
+        std::os::unix::fs::symlink(outside.join("deep/dir"), home.join("sub")).unwrap();
+        assert!(policy.check(&home.join("sub/../settings.json"), true, false).is_err());
+
+        // 目录部署不跟随目标目录中指向外部的子目录链接
+        let source = root.join("source/app");
+        std::fs::create_dir_all(source.join("nested")).unwrap();
+        std::fs::write(source.join("top.conf"), "top\n").unwrap();
+        std::fs::write(source.join("nested/inner.conf"), "inner\n").unwrap();
+        std::os::unix::fs::symlink(&outside, home.join("app/nested")).unwrap();
+        let mut directory = request("Directory");
+        directory.source_path = source.to_string_lossy().to_string();
+        directory.target_path = home.join("app").to_string_lossy().to_string();
+        directory.is_directory = true;
+        let service = ConfigFileServiceImpl::new(&settings);
+        let created = service.create_config_file(ConfigFile::from_request(directory)).await.unwrap();
+        let summary = service.deploy_config_file(&created.id).await.unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(home.join("app/top.conf")).unwrap(), "top\n");
+        assert!(summary.files.iter().any(|file| file.path == "nested/inner.conf" && file.status == FileResultStatus::Failed));
+        assert!(!outside.join("inner.conf").exists());
+
+        // 放行时产生的备份在取消放行后不能再恢复到限制之外的位置
+        let target = outside.join("settings.json");
+        std::fs::write(&target, "{\"old\": 1}\n").unwrap();
+        std::fs::write(root.join("source/settings.json"), "{\"new\": 1}\n").unwrap();
+        let mut allowed = request("Outside");
+        allowed.source_path = root.join("source/settings.json").to_string_lossy().to_string();
+        allowed.target_path = target.to_string_lossy().to_string();
+        allowed.allow_unsafe_target = true;
+        let mut config_file = service.create_config_file(ConfigFile::from_request(allowed)).await.unwrap();
+        service.deploy_config_file(&config_file.id).await.unwrap().unwrap();
+        let backup = service.get_backups(&config_file.id).await.unwrap().unwrap().remove(0);
+        config_file.allow_unsafe_target = false;
+        config_file.target_path = home.join("settings.json").to_string_lossy().to_string();
+        service.update_config_file(&config_file.id, config_file.clone()).await.unwrap().unwrap();
+        let error = service.restore_backup(&config_file.id, &backup.id).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeTargetError>().is_some());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "{\"new\": 1}\n");
+
+        // 源路径在托管目录之外的记录无法保存，捕获也就不会写到外部
+        std::fs::write(home.join("captured.json"), "{}\n").unwrap();
+        let mut unmanaged = request("Unmanaged");
+        unmanaged.source_path = outside.join("captured.json").to_string_lossy().to_string();
+        unmanaged.target_path = home.join("captured.json").to_string_lossy().to_string();
+        let error = service.create_config_file(ConfigFile::from_request(unmanaged)).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+        assert!(!outside.join("captured.json").exists());
+
+        let _ = std::fs::remove_dir_all(root);
+        let _ = std::fs::remove_dir_all(outside);
+    }
+
+    #[tokio::test]
+    async fn test_source_paths_require_managed_roots() {
+        use crate::services::archive::ArchiveFormat;
+        use crate::services::content::UploadedFile;
+        use crate::services::target_policy::UnsafeSourceError;
+
+        let (settings, root) = temp_settings("source_roots");
+        let outside = std::env::temp_dir().join(format!("config_file_tests_outside_{}", uuid::Uuid::new_v4()));
+        let service = ConfigFileServiceImpl::new(&settings);
+
+        // 源路径在托管目录之外时不能创建记录
+        let mut unmanaged = request("Outside");
+        unmanaged.source_path = outside.join("settings.json").to_string_lossy().to_string();
+        let legacy = ConfigFile::from_request(unmanaged);
+        let error = service.create_config_file(legacy.clone()).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+
+        // 旧版本注册表中已有的此类记录同样不能读取、导出、预览或写入
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("settings.json"), "{\"secret\": 1}\n").unwrap();
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data/config_files.json"), serde_json::to_string(&vec![legacy.clone()]).unwrap()).unwrap();
+        let service = ConfigFileServiceImpl::new(&settings);
+        let error = service.get_content(&legacy.id, None).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+        let error = service.export_config_files(None, ArchiveFormat::Zip).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+        let error = service.diff_config_file(&legacy.id, None).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+        let upload = UploadedFile { path: "settings.json".to_string(), content: b"{}".to_vec() };
+        let error = service.upload_content(&legacy.id, vec![upload], None).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+        assert_eq!(std::fs::read_to_string(outside.join("settings.json")).unwrap(), "{\"secret\": 1}\n");
+        std::fs::remove_file(root.join("data/config_files.json")).unwrap();
+
+        // 通过符号链接指向外部的源目录同样被拒绝
+        #[cfg(unix)]
+        {
+            let service = ConfigFileServiceImpl::new(&settings);
+            let mut linked = request("Linked");
+            linked.source_path = root.join("source/linked/settings.json").to_string_lossy().to_string();
+            let created = service.create_config_file(ConfigFile::from_request(linked)).await.unwrap();
+            std::fs::create_dir_all(root.join("source")).unwrap();
+            std::os::unix::fs::symlink(&outside, root.join("source/linked")).unwrap();
+            let error = service.get_content(&created.id, None).await.unwrap_err();
+            assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+            let upload = UploadedFile { path: "settings.json".to_string(), content: b"{}".to_vec() };
+            let error = service.upload_content(&created.id, vec![upload], None).await.unwrap_err();
+            assert!(error.downcast_ref::<UnsafeSourceError>().is_some());
+            assert_eq!(std::fs::read_to_string(outside.join("settings.json")).unwrap(), "{\"secret\": 1}\n");
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+        let _ = std::fs::remove_dir_all(outside);
+    }
+}
+
+#[cfg(test)]
+mod secrets_tests {
+    use crate::config::SecretSettings;
+    use crate::services::secrets::{self, SecretKey};
+
+    #[test]
+    fn test_encrypt_and_decrypt_with_passphrase() {
+        let name = format!("SECRETS_TEST_PASSPHRASE_{}", uuid::Uuid::new_v4().simple());
+        unsafe { std::env::set_var(&name, "correct horse") };
+        let settings = SecretSettings { key_file: None, passphrase_env: Some(name.clone()) };
+        let key = SecretKey::load(&settings).unwrap();
+
+        let encrypted = secrets::encrypt(&key, b"password=1").unwrap();
+        assert!(secrets::is_encrypted(&encrypted));
+        assert!(!secrets::is_encrypted(b"password=1"));
+        // 每次加密使用新的随机数
+        assert_ne!(encrypted, secrets::encrypt(&key, b"password=1").unwrap());
+        assert_eq!(secrets::decrypt(&key, &encrypted).unwrap(), b"password=1");
+
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(secrets::decrypt(&key, &tampered).is_err());
+
+        unsafe { std::env::set_var(&name, "wrong") };
+        let wrong = SecretKey::load(&settings).unwrap();
+        assert!(secrets::decrypt(&wrong, &encrypted).is_err());
+
+        unsafe { std::env::remove_var(&name) };
+        assert!(SecretKey::load(&settings).is_err());
+        assert!(SecretKey::load(&SecretSettings::default()).is_err());
+    }
+}
+
+#[cfg(test)]
+mod deployment_tests {
+    use crate::models::config_file::DeployMode;
+    use crate::services::deployment::{self, BackupLocation, PathFilter};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn temp_root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("deployment_tests_{}_{}", name, uuid::Uuid::new_v4()))
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let filter = PathFilter::new(&["**/*.json".to_string()], &["cache/**".to_string()]).unwrap();
+        assert!(filter.matches(Path::new("settings.json")));
+        assert!(filter.matches(Path::new("nested/keybindings.json")));
+        assert!(!filter.matches(Path::new("cache/state.json")));
+        assert!(!filter.matches(Path::new("notes.txt")));
+
+        let all = PathFilter::new(&[], &[]).unwrap();
+        assert!(all.matches(Path::new("anything/at/all")));
+    }
+
+    #[test]
+    fn test_deploy_directory_mirrors_tree() {
+        let root = temp_root("mirror");
+        let source = root.join("source");
+        let target = root.join("target");
+        write(&source.join("a.json"), "new a");
+        write(&source.join("nested/b.json"), "b");
+        write(&source.join("same.json"), "same");
+        write(&source.join("skip.log"), "log");
+        write(&target.join("a.json"), "old a");
+        write(&target.join("same.json"), "same");
+        write(&target.join("stale.json"), "stale");
+        write(&target.join("keep.txt"), "unmanaged");
+
+        let filter = PathFilter::new(&[], &["*.log".to_string(), "*.txt".to_string()]).unwrap();
+        let backup = BackupLocation::new(&root.join("backups").to_string_lossy());
+        let summary = deployment::deploy_directory(&source, &target, &filter, true, Some(&backup), None).unwrap();
+
+        assert_eq!(summary.added, vec!["nested/b.json"]);
+        assert_eq!(summary.changed, vec!["a.json"]);
+        assert_eq!(summary.removed, vec!["stale.json"]);
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(summary.backups.len(), 2);
+
+        assert_eq!(fs::read_to_string(target.join("a.json")).unwrap(), "new a");
+        assert_eq!(fs::read_to_string(target.join("nested/b.json")).unwrap(), "b");
+        assert!(!target.join("stale.json").exists());
+        assert!(!target.join("skip.log").exists());
+        // 被排除的文件不会被删除
+        assert!(target.join("keep.txt").exists());
+        assert!(summary.backups.iter().any(|entry| fs::read_to_string(&entry.backup).unwrap() == "old a"));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_deploy_file() {
+        let root = temp_root("file");
+        let source = root.join("settings.json");
+        let target = root.join("target/settings.json");
+        write(&source, "v1");
+
+        let summary = deployment::deploy_file(&source, &target, None, None).unwrap();
+        assert_eq!(summary.added, vec!["settings.json"]);
+
+        let summary = deployment::deploy_file(&source, &target, None, None).unwrap();
+        assert_eq!(summary.unchanged, 1);
+        assert!(summary.added.is_empty() && summary.changed.is_empty());
+
+        assert!(deployment::deploy_file(&root.join("missing"), &target, None, None).is_err());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_rollback_restores_written_files() {
+        let root = temp_root("rollback");
+        let target = root.join("target");
+        write(&target.join("a.conf"), "old");
+        write(&target.join("extra.conf"), "extra");
+
+        let files = vec![
+            deployment::MemoryFile { relative: PathBuf::from("a.conf"), content: b"new".to_vec() },
+            deployment::MemoryFile { relative: PathBuf::from("b.conf"), content: b"added".to_vec() },
+        ];
+        let all = PathFilter::new(&[], &[]).unwrap();
+        let backup = BackupLocation::new(&root.join("backups").to_string_lossy());
+        let summary = deployment::deploy_files(&files, &target, true, &all, true, Some(&backup), None).unwrap();
+        assert_eq!(fs::read_to_string(target.join("a.conf")).unwrap(), "new");
+        assert!(!target.join("extra.conf").exists());
+
+        // 新增的文件被删除，修改和删除的文件从备份恢复
+        deployment::rollback(&target, true, &summary).unwrap();
+        assert_eq!(fs::read_to_string(target.join("a.conf")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(target.join("extra.conf")).unwrap(), "extra");
+        assert!(!target.join("b.conf").exists());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_reports_foreign_links() {
+        let root = temp_root("foreign");
+        let source = root.join("source.conf");
+        let other = root.join("other.conf");
+        let target = root.join("target.conf");
+        write(&source, "managed");
+        write(&other, "foreign");
+        std::os::unix::fs::symlink(&other, &target).unwrap();
+
+        let backup = BackupLocation::new(&root.join("backups").to_string_lossy());
+        let summary = deployment::deploy_link(&source, &target, DeployMode::Symlink, None, &backup).unwrap();
+        assert_eq!(summary.conflicts.len(), 1);
+        assert!(summary.linked.is_empty());
+        // 外部链接保持不变
+        assert_eq!(fs::read_link(&target).unwrap(), other);
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hardlink_directory() {
+        use std::os::unix::fs::MetadataExt;
+
+        let root = temp_root("hardlink");
+        let source = root.join("source");
+        let target = root.join("target");
+        write(&source.join("a.conf"), "a");
+        write(&source.join("nested/b.conf"), "b");
+        write(&target.join("a.conf"), "old");
+
+        let filter = PathFilter::new(&[], &[]).unwrap();
+        let backup = BackupLocation::new(&root.join("backups").to_string_lossy());
+        let summary = deployment::deploy_link(&source, &target, DeployMode::Hardlink, Some(&filter), &backup).unwrap();
+        assert_eq!(summary.added, vec!["nested/b.conf"]);
+        assert_eq!(summary.changed, vec!["a.conf"]);
+        assert_eq!(summary.linked.len(), 2);
+        assert_eq!(
+            fs::metadata(source.join("a.conf")).unwrap().ino(),
+            fs::metadata(target.join("a.conf")).unwrap().ino()
+        );
+
+        let summary = deployment::deploy_link(&source, &target, DeployMode::Hardlink, Some(&filter), &backup).unwrap();
+        assert_eq!(summary.unchanged, 2);
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
+
+#[cfg(test)]
+mod backup_tests {
+    use crate::config::{BackupRetention, ConfigFilesSettings};
+    use crate::services::backups::{file_sha256, BackupCatalog};
+    use crate::services::deployment::BackupLocation;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn temp_catalog(name: &str, retention: BackupRetention) -> (BackupCatalog, ConfigFilesSettings, PathBuf) {
+        let root = std::env::temp_dir().join(format!("backup_tests_{}_{}", name, uuid::Uuid::new_v4()));
+        let settings = ConfigFilesSettings {
+            backup_dir: root.join("backups").to_string_lossy().to_string(),
+            default_config_path: root.to_string_lossy().to_string(),
+            data_dir: root.join("data").to_string_lossy().to_string(),
+            backup_retention: retention,
+            max_diff_size: 1024 * 1024,
+            max_content_size: 1024 * 1024,
+            secrets: Default::default(),
+            git: Default::default(),
+            drift: Default::default(),
+            target_policy: Default::default(),
+        };
+        (BackupCatalog::new(&settings), settings, root)
+    }
+
+    fn backup_version(settings: &ConfigFilesSettings, catalog: &BackupCatalog, file: &Path, content: &str) -> String {
+        fs::write(file, content).unwrap();
+        let location = BackupLocation::new(&settings.backup_dir);
+        let entry = location.backup(file, Path::new("app.conf")).unwrap();
+        catalog.record("config-id", &[entry], &HashSet::new()).unwrap()[0].id.clone()
+    }
+
+    #[test]
+    fn test_record_list_and_restore() {
+        let (catalog, settings, root) = temp_catalog("restore", BackupRetention::default());
+        fs::create_dir_all(&root).unwrap();
+        let file = root.join("app.conf");
+
+        let first = backup_version(&settings, &catalog, &file, "v1");
+        let backups = catalog.list("config-id").unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].size, 2);
+        assert_eq!(backups[0].sha256, file_sha256(&file).unwrap());
+        assert!(catalog.list("other-id").unwrap().is_empty());
+
+        fs::write(&file, "v2").unwrap();
+        let restored = catalog.restore("config-id", &first, &HashSet::new()).unwrap().unwrap();
+        assert_eq!(restored.id, first);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "v1");
+
+        // 恢复前的内容作为新备份登记
+        let backups = catalog.list("config-id").unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].backup_path).unwrap(), "v2");
+
+        assert!(catalog.restore("other-id", &first, &HashSet::new()).unwrap().is_none());
+
+        // 备份文件被篡改时拒绝恢复
+        fs::write(&backups[1].backup_path, "tampered").unwrap();
+        assert!(catalog.restore("config-id", &first, &HashSet::new()).is_err());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_retention_keeps_last_versions() {
+        let retention = BackupRetention { keep_last: Some(2), max_age_days: None, max_total_size: None };
+        let (catalog, settings, root) = temp_catalog("retention", retention);
+        fs::create_dir_all(&root).unwrap();
+        let file = root.join("app.conf");
+
+        backup_version(&settings, &catalog, &file, "v1");
+        backup_version(&settings, &catalog, &file, "v2");
+        backup_version(&settings, &catalog, &file, "v3");
+
+        let backups = catalog.list("config-id").unwrap();
+        let contents: Vec<String> = backups
+            .iter()
+            .map(|backup| fs::read_to_string(&backup.backup_path).unwrap())
+            .collect();
+        assert_eq!(contents, vec!["v3", "v2"]);
+
+        // 被清理的备份文件和空目录一并删除
+        let dirs = fs::read_dir(&settings.backup_dir).unwrap().filter(|entry| entry.as_ref().unwrap().path().is_dir()).count();
+        assert_eq!(dirs, 2);
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_retention_total_size_spares_protected() {
+        let retention = BackupRetention { keep_last: None, max_age_days: None, max_total_size: Some(4) };
+        let (catalog, settings, root) = temp_catalog("size", retention);
+        fs::create_dir_all(&root).unwrap();
+        let file = root.join("app.conf");
+
+        backup_version(&settings, &catalog, &file, "aaa");
+        let protected: HashSet<String> = catalog
+            .list("config-id")
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.backup_path)
+            .collect();
+
+        for content in ["bbb", "ccc"] {
+            fs::write(&file, content).unwrap();
+            let location = BackupLocation::new(&settings.backup_dir);
+            let entry = location.backup(&file, Path::new("app.conf")).unwrap();
+            catalog.record("config-id", &[entry], &protected).unwrap();
+        }
+
+        // 超出总大小限制时删除较旧的备份，但受保护的备份不会被删除
+        let backups = catalog.list("config-id").unwrap();
+        let contents: Vec<String> = backups
+            .iter()
+            .map(|backup| fs::read_to_string(&backup.backup_path).unwrap())
+            .collect();
+        assert_eq!(contents, vec!["ccc", "aaa"]);
+        assert!(protected.contains(&backups[1].backup_path));
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
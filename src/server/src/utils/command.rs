@@ -1,74 +1,461 @@
 use anyhow::Result;
-use log::{info, error};
-use std::process::{Command, Output};
-
-/// 执行命令行命令
-pub fn execute_command(command: &str) -> Result<String> {
-    info!("执行命令: {}", command);
-    
-    let output = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .args(&["/C", command])
-            .output()?
-    } else {
-        Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()?
-    };
-    
-    process_output(output)
+use log::{info, error, warn};
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::watch;
+
+// 默认每个输出流最多保留 1 MiB
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+// 单行输出的上限，超过时按上限分段交给回调，避免没有换行的输出占用无限内存
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+// 结束进程后等待输出流关闭的最长时间
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+type LineCallback = Box<dyn FnMut(&str) + Send>;
+
+/// 命令的执行方式
+#[derive(Debug, Clone)]
+pub enum CommandMode {
+    // 交给系统 shell 执行（Windows 上为 cmd /C，其他平台为 sh -c）
+    Shell(String),
+    // 直接执行程序，第一个元素为程序，其余为参数
+    Argv(Vec<String>),
 }
 
-/// 处理命令输出
-fn process_output(output: Output) -> Result<String> {
-    if output.status.success() {
-        let result = String::from_utf8_lossy(&output.stdout).to_string();
-        Ok(result)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr).to_string();
-        error!("命令执行失败: {}", error);
-        Err(anyhow::anyhow!("命令执行失败: {}", error))
-    }
-}
-
-/// 异步执行命令行命令
-#[cfg(feature = "async")]
-pub async fn execute_command_async(command: &str) -> Result<String> {
-    use tokio::process::Command as TokioCommand;
-    
-    info!("异步执行命令: {}", command);
-    
-    let output = if cfg!(target_os = "windows") {
-        TokioCommand::new("cmd")
-            .args(&["/C", command])
-            .output()
-            .await?
-    } else {
-        TokioCommand::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-            .await?
+/// 命令执行结果
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    // 退出码，被信号结束时为 None
+    pub exit_code: Option<i32>,
+    // 结束进程的信号（仅 Unix）
+    pub signal: Option<i32>,
+    pub duration: Duration,
+    pub stdout: String,
+    pub stderr: String,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub timed_out: bool,
+    pub cancelled: bool,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0) && !self.timed_out && !self.cancelled
+    }
+
+    /// 成功时返回标准输出，否则返回包含标准错误的错误信息
+    pub fn into_result(self) -> Result<String> {
+        if self.success() {
+            return Ok(self.stdout);
+        }
+
+        let reason = if self.timed_out {
+            format!("命令执行超时 ({:?})", self.duration)
+        } else if self.cancelled {
+            "命令已取消".to_string()
+        } else if let Some(signal) = self.signal {
+            format!("命令被信号 {} 结束: {}", signal, self.stderr)
+        } else {
+            format!("命令执行失败: {}", self.stderr)
+        };
+        error!("{}", reason);
+        Err(anyhow::anyhow!(reason))
+    }
+}
+
+/// 异步命令执行器
+///
+/// 子进程以独立进程组启动，超时或取消时结束整个进程树；
+/// 执行中的 `run` 被丢弃时同样结束整个进程树。
+pub struct CommandRunner {
+    mode: CommandMode,
+    working_dir: Option<PathBuf>,
+    envs: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    max_output_bytes: usize,
+    on_stdout_line: Option<LineCallback>,
+    on_stderr_line: Option<LineCallback>,
+    cancel: Option<watch::Receiver<bool>>,
+}
+
+impl CommandRunner {
+    pub fn new(mode: CommandMode) -> Self {
+        CommandRunner {
+            mode,
+            working_dir: None,
+            envs: Vec::new(),
+            timeout: None,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            on_stdout_line: None,
+            on_stderr_line: None,
+            cancel: None,
+        }
+    }
+
+    pub fn shell(command: &str) -> Self {
+        Self::new(CommandMode::Shell(command.to_string()))
+    }
+
+    pub fn argv<S: AsRef<str>>(args: &[S]) -> Self {
+        Self::new(CommandMode::Argv(args.iter().map(|arg| arg.as_ref().to_string()).collect()))
+    }
+
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 每个输出流最多保留的字节数，超出部分只会传给逐行回调
+    ///
+    /// 目前只有测试需要调整上限
+    #[cfg(test)]
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    pub fn on_stdout_line(mut self, callback: impl FnMut(&str) + Send + 'static) -> Self {
+        self.on_stdout_line = Some(Box::new(callback));
+        self
+    }
+
+    pub fn on_stderr_line(mut self, callback: impl FnMut(&str) + Send + 'static) -> Self {
+        self.on_stderr_line = Some(Box::new(callback));
+        self
+    }
+
+    /// 取消信号，收到 `true` 时结束进程树
+    pub fn cancel_signal(mut self, cancel: watch::Receiver<bool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn build_command(&self) -> Result<Command> {
+        let mut command = match &self.mode {
+            CommandMode::Shell(script) if cfg!(target_os = "windows") => {
+                let mut command = Command::new("cmd");
+                command.args(["/C", script]);
+                command
+            }
+            CommandMode::Shell(script) => {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(script);
+                command
+            }
+            CommandMode::Argv(args) => {
+                let (program, args) = args
+                    .split_first()
+                    .ok_or_else(|| anyhow::anyhow!("命令不能为空"))?;
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+        };
+
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+        command.envs(self.envs.iter().map(|(key, value)| (key, value)));
+
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        // 让子进程成为独立进程组的组长，便于结束整个进程树
+        #[cfg(unix)]
+        command.process_group(0);
+
+        Ok(command)
+    }
+
+    /// 执行命令并等待结束
+    pub async fn run(mut self) -> Result<CommandOutput> {
+        match &self.mode {
+            CommandMode::Shell(script) => info!("执行命令: {}", script),
+            CommandMode::Argv(args) => info!("执行命令: {:?}", args),
+        }
+
+        let mut command = self.build_command()?;
+        let started = Instant::now();
+        let mut child = command.spawn()?;
+        let pid = child.id();
+        // 运行中的 future 被丢弃时结束整个进程树，而不只是直接子进程
+        let mut guard = ProcessTreeGuard { pid };
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let max_output_bytes = self.max_output_bytes;
+        let stdout_callback = self.on_stdout_line.take();
+        let stderr_callback = self.on_stderr_line.take();
+
+        let wait_all = async {
+            tokio::join!(
+                child.wait(),
+                read_stream(stdout, max_output_bytes, stdout_callback),
+                read_stream(stderr, max_output_bytes, stderr_callback),
+            )
+        };
+        tokio::pin!(wait_all);
+
+        let timeout = async {
+            match self.timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        let cancel = async {
+            match self.cancel.as_mut() {
+                Some(cancel) => cancelled(cancel).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let mut timed_out = false;
+        let mut was_cancelled = false;
+        let (status, stdout, stderr) = tokio::select! {
+            result = &mut wait_all => result,
+            _ = timeout => {
+                timed_out = true;
+                terminate(pid);
+                wait_after_kill(&mut wait_all).await?
+            }
+            _ = cancel => {
+                was_cancelled = true;
+                terminate(pid);
+                wait_after_kill(&mut wait_all).await?
+            }
+        };
+        guard.disarm();
+        let status = status?;
+        let (stdout, stdout_truncated) = stdout?;
+        let (stderr, stderr_truncated) = stderr?;
+
+        Ok(CommandOutput {
+            exit_code: status.code(),
+            signal: exit_signal(&status),
+            duration: started.elapsed(),
+            stdout,
+            stderr,
+            stdout_truncated,
+            stderr_truncated,
+            timed_out,
+            cancelled: was_cancelled,
+        })
+    }
+}
+
+type StreamResult = std::io::Result<(String, bool)>;
+
+// 结束进程树后等待进程退出和输出流关闭
+async fn wait_after_kill<F>(wait_all: &mut std::pin::Pin<&mut F>) -> Result<(std::io::Result<ExitStatus>, StreamResult, StreamResult)>
+where
+    F: std::future::Future<Output = (std::io::Result<ExitStatus>, StreamResult, StreamResult)>,
+{
+    tokio::time::timeout(KILL_GRACE_PERIOD, wait_all.as_mut())
+        .await
+        .map_err(|_| anyhow::anyhow!("进程被结束后未能在 {:?} 内退出", KILL_GRACE_PERIOD))
+}
+
+// 被丢弃时结束进程树，正常结束后解除
+struct ProcessTreeGuard {
+    pid: Option<u32>,
+}
+
+impl ProcessTreeGuard {
+    fn disarm(&mut self) {
+        self.pid = None;
+    }
+}
+
+impl Drop for ProcessTreeGuard {
+    fn drop(&mut self) {
+        if self.pid.is_some() {
+            terminate(self.pid.take());
+        }
+    }
+}
+
+fn terminate(pid: Option<u32>) {
+    if let Some(pid) = pid
+        && let Err(err) = kill_process_tree(pid)
+    {
+        warn!("结束进程树失败，PID: {}: {}", pid, err);
+    }
+}
+
+// 逐行读取输出流，调用回调并保留不超过上限的内容
+async fn read_stream<R: AsyncRead + Unpin>(
+    stream: Option<R>,
+    max_bytes: usize,
+    mut callback: Option<LineCallback>,
+) -> StreamResult {
+    let Some(stream) = stream else {
+        return Ok((String::new(), false));
     };
-    
-    process_output(output)
+
+    let mut reader = BufReader::new(stream);
+    let mut captured = String::new();
+    let mut truncated = false;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if (&mut reader).take(MAX_LINE_BYTES as u64).read_until(b'\n', &mut line).await? == 0 {
+            break;
+        }
+        let text = String::from_utf8_lossy(&line);
+
+        if let Some(callback) = callback.as_mut() {
+            callback(text.trim_end_matches(['\r', '\n']));
+        }
+
+        let remaining = max_bytes.saturating_sub(captured.len());
+        if text.len() <= remaining {
+            captured.push_str(&text);
+        } else {
+            // 按字符边界截断
+            let mut end = remaining;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            captured.push_str(&text[..end]);
+            truncated = true;
+        }
+    }
+
+    Ok((captured, truncated))
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// 等待取消信号，信号发送方已释放时永不返回
+pub async fn cancelled(receiver: &mut watch::Receiver<bool>) {
+    while !*receiver.borrow_and_update() {
+        if receiver.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// 执行命令行命令，成功时返回标准输出
+#[allow(dead_code)]
+pub async fn execute_command(command: &str) -> Result<String> {
+    CommandRunner::shell(command).run().await?.into_result()
 }
 
 /// 执行 PowerShell 命令
-pub fn execute_powershell(script: &str) -> Result<String> {
+#[allow(dead_code)]
+pub async fn execute_powershell(script: &str) -> Result<String> {
     info!("执行 PowerShell 脚本");
-    
-    let output = Command::new("powershell")
-        .arg("-Command")
-        .arg(script)
-        .output()?;
-        
-    process_output(output)
+
+    CommandRunner::argv(&["powershell", "-Command", script])
+        .run()
+        .await?
+        .into_result()
+}
+
+/// 为 shell 命令引用参数，使参数中的特殊字符不会被 shell 解释
+pub fn quote_shell_arg(value: &str) -> String {
+    // 只包含安全字符的参数无需引用
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@+,".contains(c));
+    if is_safe {
+        return value.to_string();
+    }
+
+    if cfg!(target_os = "windows") {
+        quote_cmd_arg(value)
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// 按 cmd 的规则引用参数
+///
+/// 双引号写作 `""`；`%` 放在引号外并用 `^` 转义，避免 `%VAR%` 被 cmd 展开。
+/// 程序按 CommandLineToArgvW 的规则解析参数，紧接在引号之前的反斜杠会转义引号，
+/// 因此引号（包括为 `%` 结束引用的引号和末尾的引号）之前的反斜杠需要加倍
+pub fn quote_cmd_arg(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in value.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        let before_quote = c == '"' || c == '%';
+        quoted.extend(std::iter::repeat_n('\\', if before_quote { backslashes * 2 } else { backslashes }));
+        backslashes = 0;
+        match c {
+            '"' => quoted.push_str("\"\""),
+            '%' => quoted.push_str("\"^%\""),
+            c => quoted.push(c),
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// 结束进程及其所有子进程
+pub fn kill_process_tree(pid: u32) -> Result<()> {
+    info!("结束进程树，PID: {}", pid);
+
+    #[cfg(unix)]
+    {
+        // 子进程以独立进程组启动，向整个进程组发送 SIGKILL
+        let ret = unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    {
+        let output = std::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .output()?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(anyhow::anyhow!("结束进程树失败: {}", error));
+        }
+        Ok(())
+    }
 }
 
 /// 执行 WMI 查询
-pub fn execute_wmi_query(query: &str) -> Result<String> {
+#[allow(dead_code)]
+pub async fn execute_wmi_query(query: &str) -> Result<String> {
     let command = format!("wmic path {} /format:list", query);
-    execute_command(&command)
+    execute_command(&command).await
 }
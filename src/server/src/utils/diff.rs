@@ -0,0 +1,145 @@
+use anyhow::Result;
+use serde::Serialize;
+use similar::TextDiff;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+// 判断二进制内容时检查的前缀长度
+const BINARY_CHECK_BYTES: usize = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// 单个文件的差异
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub change: ChangeKind,
+    // 统一格式的文本差异，二进制文件或超出大小限制时为空
+    pub diff: Option<String>,
+    pub binary: bool,
+    pub too_large: bool,
+    // 内容不允许通过接口返回（例如加密的配置文件）
+    pub redacted: bool,
+}
+
+impl FileDiff {
+    /// 去掉差异中的文件内容，只保留变化类型
+    pub fn redact(&mut self) {
+        self.diff = None;
+        self.redacted = true;
+    }
+}
+
+/// 生成统一格式（unified）的文本差异
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+/// 内容包含 NUL 字节或不是合法 UTF-8 时视为二进制
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(BINARY_CHECK_BYTES).any(|byte| *byte == 0) || std::str::from_utf8(content).is_err()
+}
+
+// 分块比较两个文件的内容，避免一次读入大文件
+fn files_equal(a: &Path, b: &Path) -> Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+
+    let mut a = fs::File::open(a)?;
+    let mut b = fs::File::open(b)?;
+    let mut buffer_a = [0u8; 8192];
+    let mut buffer_b = [0u8; 8192];
+    loop {
+        let read = a.read(&mut buffer_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buffer_b[..read])?;
+        if buffer_a[..read] != buffer_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// 比较两个位置的文件，`old` 或 `new` 不存在时视为新增或删除，内容相同时返回 None
+///
+/// 任一文件超过 `max_size` 字节时只报告变化，不生成文本差异
+pub fn diff_files(old: Option<&Path>, new: Option<&Path>, path: &str, max_size: u64) -> Result<Option<FileDiff>> {
+    let change = match (old, new) {
+        (None, None) => return Ok(None),
+        (Some(old), Some(new)) if files_equal(old, new)? => return Ok(None),
+        (None, Some(_)) => ChangeKind::Added,
+        (Some(_), None) => ChangeKind::Removed,
+        (Some(_), Some(_)) => ChangeKind::Modified,
+    };
+
+    for file in [old, new].into_iter().flatten() {
+        if fs::metadata(file)?.len() > max_size {
+            let mut file_diff = empty_diff(path, change);
+            file_diff.too_large = true;
+            return Ok(Some(file_diff));
+        }
+    }
+
+    let old_content = old.map(fs::read).transpose()?.unwrap_or_default();
+    let new_content = new.map(fs::read).transpose()?.unwrap_or_default();
+    Ok(Some(text_diff(path, change, &old_content, &new_content)))
+}
+
+/// 比较内存中的两份内容，规则与 [`diff_files`] 相同
+pub fn diff_contents(old: Option<&[u8]>, new: Option<&[u8]>, path: &str, max_size: u64) -> Option<FileDiff> {
+    let change = match (old, new) {
+        (None, None) => return None,
+        (Some(old), Some(new)) if old == new => return None,
+        (None, Some(_)) => ChangeKind::Added,
+        (Some(_), None) => ChangeKind::Removed,
+        (Some(_), Some(_)) => ChangeKind::Modified,
+    };
+
+    let old = old.unwrap_or_default();
+    let new = new.unwrap_or_default();
+    if old.len() as u64 > max_size || new.len() as u64 > max_size {
+        let mut file_diff = empty_diff(path, change);
+        file_diff.too_large = true;
+        return Some(file_diff);
+    }
+    Some(text_diff(path, change, old, new))
+}
+
+fn empty_diff(path: &str, change: ChangeKind) -> FileDiff {
+    FileDiff {
+        path: path.to_string(),
+        change,
+        diff: None,
+        binary: false,
+        too_large: false,
+        redacted: false,
+    }
+}
+
+// 二进制内容只报告变化，文本内容生成统一格式差异
+fn text_diff(path: &str, change: ChangeKind, old: &[u8], new: &[u8]) -> FileDiff {
+    let mut file_diff = empty_diff(path, change);
+    if is_binary(old) || is_binary(new) {
+        file_diff.binary = true;
+        return file_diff;
+    }
+
+    // 上面已经确认内容是合法的 UTF-8
+    let old_text = String::from_utf8_lossy(old);
+    let new_text = String::from_utf8_lossy(new);
+    file_diff.diff = Some(unified_diff(&old_text, &new_text, &format!("a/{}", path), &format!("b/{}", path)));
+    file_diff
+}
@@ -0,0 +1,224 @@
+use anyhow::Result;
+
+/// 带注释的 JSON（JSONC）语法树，只记录各节点在原文中的位置，便于在保留格式的前提下修改文本
+///
+/// 支持 `//` 和 `/* */` 注释以及对象、数组中的尾随逗号
+#[derive(Debug, Clone)]
+pub struct Node {
+    // 值在原文中的字节范围
+    pub start: usize,
+    pub end: usize,
+    // 对象的成员，其他类型的值为 None
+    pub members: Option<Vec<Member>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub key: String,
+    // 键（含引号）的起始位置
+    pub key_start: usize,
+    pub value: Node,
+}
+
+/// 解析错误，行号和列号从 1 开始
+#[derive(Debug, thiserror::Error)]
+#[error("第 {line} 行第 {column} 列: {message}")]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    /// 根据字节位置计算行号和列号，列号按字符计
+    pub fn at(text: &str, pos: usize, message: &str) -> Self {
+        let mut pos = pos.min(text.len());
+        while !text.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        let before = &text[..pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        ParseError { line, column, message: message.to_string() }
+    }
+}
+
+/// 解析 JSONC 文本，`allow_comments` 为 false 时按严格的 JSON 处理
+pub fn parse(text: &str, allow_comments: bool) -> Result<Node, ParseError> {
+    let mut parser = Parser { text, bytes: text.as_bytes(), pos: 0, allow_comments };
+    parser.skip_trivia()?;
+    let node = parser.parse_value()?;
+    parser.skip_trivia()?;
+    if parser.pos < parser.bytes.len() {
+        return Err(parser.error("值之后存在多余内容"));
+    }
+    Ok(node)
+}
+
+/// 字节位置所在行的缩进
+pub fn line_indent(text: &str, pos: usize) -> &str {
+    let line_start = text[..pos].rfind('\n').map(|index| index + 1).unwrap_or(0);
+    let line = &text[line_start..];
+    let width = line.len() - line.trim_start_matches([' ', '\t']).len();
+    &line[..width]
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    allow_comments: bool,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::at(self.text, self.pos, message)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    // 跳过空白和注释
+    fn skip_trivia(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                // UTF-8 BOM
+                Some(0xEF) if self.pos == 0 && self.text.starts_with('\u{feff}') => self.pos += 3,
+                Some(b'/') if self.allow_comments => match self.bytes.get(self.pos + 1) {
+                    Some(b'/') => {
+                        while self.peek().is_some_and(|byte| byte != b'\n') {
+                            self.pos += 1;
+                        }
+                    }
+                    Some(b'*') => {
+                        let Some(end) = self.text[self.pos + 2..].find("*/") else {
+                            return Err(self.error("注释没有结束"));
+                        };
+                        self.pos += end + 4;
+                    }
+                    _ => return Err(self.error("无效的字符 '/'")),
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Node, ParseError> {
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => {
+                let start = self.pos;
+                self.parse_string()?;
+                Ok(Node { start, end: self.pos, members: None })
+            }
+            Some(_) => self.parse_literal(),
+            None => Err(self.error("缺少值")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut members = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b'}') => break,
+                Some(b'"') => {}
+                _ => return Err(self.error("应为字符串形式的键或 '}'")),
+            }
+            let key_start = self.pos;
+            let key = self.parse_string()?;
+            self.skip_trivia()?;
+            if self.peek() != Some(b':') {
+                return Err(self.error("键之后应为 ':'"));
+            }
+            self.pos += 1;
+            self.skip_trivia()?;
+            let value = self.parse_value()?;
+            members.push(Member { key, key_start, value });
+
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_trivia()?;
+                    if self.peek() == Some(b'}') && !self.allow_comments {
+                        return Err(self.error("对象中不允许尾随逗号"));
+                    }
+                }
+                Some(b'}') => break,
+                _ => return Err(self.error("应为 ',' 或 '}'")),
+            }
+        }
+        self.pos += 1;
+        Ok(Node { start, end: self.pos, members: Some(members) })
+    }
+
+    fn parse_array(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(b']') {
+                break;
+            }
+            self.parse_value()?;
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    self.skip_trivia()?;
+                    if self.peek() == Some(b']') && !self.allow_comments {
+                        return Err(self.error("数组中不允许尾随逗号"));
+                    }
+                }
+                Some(b']') => break,
+                _ => return Err(self.error("应为 ',' 或 ']'")),
+            }
+        }
+        self.pos += 1;
+        Ok(Node { start, end: self.pos, members: None })
+    }
+
+    // 解析字符串并返回解码后的内容
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(b'\n') | None => return Err(self.error("字符串没有结束")),
+                Some(_) => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        serde_json::from_str(&self.text[start..self.pos]).map_err(|e| {
+            self.pos = start;
+            self.error(&format!("无效的字符串: {}", e))
+        })
+    }
+
+    // 数字、true、false、null
+    fn parse_literal(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'+' | b'.'))
+        {
+            self.pos += 1;
+        }
+        let literal = &self.text[start..self.pos];
+        let valid = matches!(literal, "true" | "false" | "null")
+            || (!literal.is_empty() && serde_json::from_str::<serde_json::Number>(literal).is_ok());
+        if !valid {
+            self.pos = start;
+            return Err(self.error("无效的值"));
+        }
+        Ok(Node { start, end: self.pos, members: None })
+    }
+}
@@ -0,0 +1,384 @@
+use anyhow::Result;
+use std::path::Path;
+use toml_edit::{DocumentMut, TableLike};
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+use crate::models::config_file::{ConfigFormat, MergeStrategy};
+use crate::utils::jsonc::{self, Member, Node};
+use crate::utils::syntax;
+
+/// 支持结构化合并的配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    // JSON 和带注释的 JSON
+    Json,
+    Toml,
+    Yaml,
+    // INI 和 gitconfig
+    Ini,
+}
+
+/// 按文件名和扩展名判断配置文件格式
+pub fn detect_format(path: &Path) -> Option<FileFormat> {
+    syntax::detect_format(path).and_then(merge_format)
+}
+
+/// 记录中设置的格式对应的合并方式，纯文本不支持合并
+pub fn merge_format(format: ConfigFormat) -> Option<FileFormat> {
+    match format {
+        ConfigFormat::Json | ConfigFormat::Jsonc => Some(FileFormat::Json),
+        ConfigFormat::Toml => Some(FileFormat::Toml),
+        ConfigFormat::Yaml => Some(FileFormat::Yaml),
+        ConfigFormat::Ini | ConfigFormat::Gitconfig => Some(FileFormat::Ini),
+        ConfigFormat::Text => None,
+    }
+}
+
+/// 将源文件中的托管设置合并到目标文件的现有内容中，目标文件中的其他设置保持不变
+///
+/// `deep_merge` 递归合并嵌套的对象或表，`keys_only` 只按顶层键整体替换。
+/// INI 文件按节和键合并，两种策略的效果相同
+pub fn merge(format: FileFormat, source: &str, target: &str, strategy: MergeStrategy) -> Result<String> {
+    if strategy == MergeStrategy::Overwrite || target.trim().is_empty() {
+        return Ok(source.to_string());
+    }
+    let deep = strategy == MergeStrategy::DeepMerge;
+    match format {
+        FileFormat::Json => merge_json(source, target, deep),
+        FileFormat::Toml => merge_toml(source, target, deep),
+        FileFormat::Yaml => merge_yaml(source, target, deep),
+        FileFormat::Ini => Ok(merge_ini(source, target)),
+    }
+}
+
+// JSON 直接修改目标文件的文本，未涉及的内容（包括注释和缩进）原样保留
+fn merge_json(source: &str, target: &str, deep: bool) -> Result<String> {
+    let source_root = jsonc::parse(source, true).map_err(|e| anyhow::anyhow!("源文件不是有效的 JSON: {}", e))?;
+    let target_root = jsonc::parse(target, true).map_err(|e| anyhow::anyhow!("目标文件不是有效的 JSON: {}", e))?;
+    if source_root.members.is_none() || target_root.members.is_none() {
+        return Err(anyhow::anyhow!("JSON 根节点必须是对象"));
+    }
+
+    let mut edits = Vec::new();
+    merge_json_object(source, &source_root, target, &target_root, deep, &mut edits);
+
+    // 从后往前替换，前面的位置不受影响
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut output = target.to_string();
+    for (start, end, text) in edits.into_iter().rev() {
+        output.replace_range(start..end, &text);
+    }
+    Ok(output)
+}
+
+fn merge_json_object(
+    source: &str,
+    source_object: &Node,
+    target: &str,
+    target_object: &Node,
+    deep: bool,
+    edits: &mut Vec<(usize, usize, String)>,
+) {
+    let source_members = source_object.members.as_deref().unwrap_or_default();
+    let target_members = target_object.members.as_deref().unwrap_or_default();
+
+    let mut inserted = Vec::new();
+    for member in source_members {
+        // 键重复时以最后一个为准，与常见的 JSON 解析器一致
+        match target_members.iter().rev().find(|existing| existing.key == member.key) {
+            Some(existing) if deep && existing.value.members.is_some() && member.value.members.is_some() => {
+                merge_json_object(source, &member.value, target, &existing.value, deep, edits);
+            }
+            Some(existing) => {
+                let text = reindent(
+                    &source[member.value.start..member.value.end],
+                    jsonc::line_indent(source, member.key_start),
+                    jsonc::line_indent(target, existing.key_start),
+                    (&indent_unit(source), &indent_unit(target)),
+                );
+                if target[existing.value.start..existing.value.end] != text {
+                    edits.push((existing.value.start, existing.value.end, text));
+                }
+            }
+            None => inserted.push(member),
+        }
+    }
+    if inserted.is_empty() {
+        return;
+    }
+
+    match target_members.last() {
+        // 新成员追加在最后一个成员之后，沿用其缩进和换行方式
+        Some(last) => {
+            let indent = jsonc::line_indent(target, last.key_start);
+            let multiline = target[..last.key_start].trim_end_matches([' ', '\t']).ends_with('\n');
+            let mut text = String::new();
+            for member in inserted {
+                if multiline {
+                    text.push_str(",\n");
+                    text.push_str(indent);
+                } else {
+                    text.push_str(", ");
+                }
+                text.push_str(&member_text(source, member, target, indent));
+            }
+            edits.push((last.value.end, last.value.end, text));
+        }
+        None => {
+            let outer = jsonc::line_indent(target, target_object.start);
+            let inner = format!("{}{}", outer, indent_unit(target));
+            let members = inserted
+                .iter()
+                .map(|member| format!("{}{}", inner, member_text(source, member, target, &inner)))
+                .collect::<Vec<_>>()
+                .join(",\n");
+            let body = (target_object.start + 1, target_object.end - 1);
+            if target[body.0..body.1].trim().is_empty() {
+                edits.push((body.0, body.1, format!("\n{}\n{}", members, outer)));
+            } else {
+                // 对象中只有注释时，新成员插入在注释之前
+                edits.push((body.0, body.0, format!("\n{}", members)));
+            }
+        }
+    }
+}
+
+// 以目标位置的缩进输出源文件中的成员
+fn member_text(source: &str, member: &Member, target: &str, indent: &str) -> String {
+    let key = serde_json::to_string(&member.key).unwrap_or_default();
+    let value = reindent(
+        &source[member.value.start..member.value.end],
+        jsonc::line_indent(source, member.key_start),
+        indent,
+        (&indent_unit(source), &indent_unit(target)),
+    );
+    format!("{}: {}", key, value)
+}
+
+// 将多行文本除首行外的缩进从源文件的层级换算为目标位置的层级
+//
+// `from`/`to` 是成员所在行的缩进，`unit` 为两边文件各自的一级缩进
+fn reindent(text: &str, from: &str, to: &str, unit: (&str, &str)) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            let content = line.trim_start_matches([' ', '\t']);
+            let leading = &line[..line.len() - content.len()];
+            match leading.strip_prefix(from) {
+                Some(extra) if index > 0 && !unit.0.is_empty() => {
+                    let depth = extra.len() / unit.0.len();
+                    let remainder = extra.len() % unit.0.len();
+                    format!("{}{}{}{}", to, unit.1.repeat(depth), " ".repeat(remainder), content)
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 文件中最小的非零缩进，作为一级缩进的宽度
+fn indent_unit(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .filter(|indent| !indent.is_empty())
+        .min_by_key(|indent| indent.len())
+        .unwrap_or("    ")
+        .to_string()
+}
+
+// TOML 使用保留格式的文档模型，目标文件中的注释和顺序保持不变
+fn merge_toml(source: &str, target: &str, deep: bool) -> Result<String> {
+    let source: DocumentMut = source.parse().map_err(|e| anyhow::anyhow!("源文件不是有效的 TOML: {}", e))?;
+    let mut target: DocumentMut = target.parse().map_err(|e| anyhow::anyhow!("目标文件不是有效的 TOML: {}", e))?;
+    merge_toml_table(target.as_table_mut(), source.as_table(), deep);
+    Ok(target.to_string())
+}
+
+fn merge_toml_table(target: &mut dyn TableLike, source: &dyn TableLike, deep: bool) {
+    for (key, item) in source.iter() {
+        if deep
+            && let Some(existing) = target.get_mut(key)
+            && let (Some(existing), Some(item)) = (existing.as_table_like_mut(), item.as_table_like())
+        {
+            merge_toml_table(existing, item, deep);
+            continue;
+        }
+        match target.get_mut(key) {
+            // 原地替换已有的值，保留键前的注释和值两侧的空白
+            Some(existing) => {
+                let mut replacement = item.clone();
+                if let (Some(old), Some(new)) = (existing.as_value(), replacement.as_value_mut()) {
+                    *new.decor_mut() = old.decor().clone();
+                }
+                *existing = replacement;
+            }
+            None => {
+                target.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+// YAML 合并后重新输出，键的顺序保持不变，但注释无法保留
+fn merge_yaml(source: &str, target: &str, deep: bool) -> Result<String> {
+    let source = YamlLoader::load_from_str(source).map_err(|e| anyhow::anyhow!("源文件不是有效的 YAML: {}", e))?;
+    let mut documents =
+        YamlLoader::load_from_str(target).map_err(|e| anyhow::anyhow!("目标文件不是有效的 YAML: {}", e))?;
+    let (Some(Yaml::Hash(source)), Some(Yaml::Hash(merged))) = (source.first(), documents.first_mut()) else {
+        return Err(anyhow::anyhow!("YAML 根节点必须是映射"));
+    };
+    merge_yaml_hash(merged, source, deep);
+
+    let mut output = String::new();
+    YamlEmitter::new(&mut output)
+        .dump(&documents[0])
+        .map_err(|e| anyhow::anyhow!("无法输出 YAML: {}", e))?;
+    // 输出总是以文档开始标记开头，目标文件原本没有时去掉
+    if !target.trim_start().starts_with("---") {
+        output = output.trim_start_matches("---").trim_start_matches('\n').to_string();
+    }
+    output.push('\n');
+    Ok(output)
+}
+
+fn merge_yaml_hash(target: &mut yaml_rust2::yaml::Hash, source: &yaml_rust2::yaml::Hash, deep: bool) {
+    for (key, value) in source {
+        match (target.get_mut(key), value) {
+            (Some(Yaml::Hash(existing)), Yaml::Hash(value)) if deep => merge_yaml_hash(existing, value, deep),
+            // 已有的键原地替换，保持原来的位置
+            (Some(existing), value) => *existing = value.clone(),
+            (None, value) => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum IniLine {
+    Header(String),
+    Entry(String),
+    Other,
+}
+
+fn classify_ini_line(line: &str) -> IniLine {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+        return IniLine::Other;
+    }
+    if let Some(rest) = trimmed.strip_prefix('[')
+        && let Some(end) = rest.find(']')
+    {
+        return IniLine::Header(rest[..end].trim().to_string());
+    }
+    // gitconfig 中没有值的布尔键整行即为键名
+    let key = trimmed.split('=').next().unwrap_or(trimmed).trim();
+    IniLine::Entry(key.to_string())
+}
+
+// INI 按行合并：已有的键原地替换为源文件中的行，缺少的键追加到对应节的末尾
+fn merge_ini(source: &str, target: &str) -> String {
+    // 源文件中的 (节, 键, 行)，以及各节的标题行
+    let mut entries: Vec<(String, String, &str)> = Vec::new();
+    let mut headers: Vec<(String, &str)> = Vec::new();
+    let mut section = String::new();
+    for line in source.lines() {
+        match classify_ini_line(line) {
+            IniLine::Header(name) => {
+                headers.push((name.clone(), line.trim()));
+                section = name;
+            }
+            IniLine::Entry(key) => entries.push((section.clone(), key, line.trim())),
+            IniLine::Other => {}
+        }
+    }
+
+    let mut lines: Vec<String> = target.split('\n').map(str::to_string).collect();
+    let mut sections = Vec::with_capacity(lines.len());
+    let mut section = String::new();
+    for line in &lines {
+        if let IniLine::Header(name) = classify_ini_line(line) {
+            section = name;
+        }
+        sections.push(section.clone());
+    }
+    let default_indent = lines
+        .iter()
+        .find(|line| matches!(classify_ini_line(line), IniLine::Entry(_)))
+        .map(|line| jsonc::line_indent(line, 0).to_string())
+        .unwrap_or_default();
+
+    let mut pending: Vec<(String, Vec<&str>)> = Vec::new();
+    for (section, key, text) in entries {
+        let existing = (0..lines.len()).find(|&index| {
+            sections[index] == section
+                && matches!(classify_ini_line(&lines[index]), IniLine::Entry(ref name) if name.eq_ignore_ascii_case(&key))
+        });
+        match existing {
+            Some(index) => {
+                let indent = jsonc::line_indent(&lines[index], 0);
+                lines[index] = format!("{}{}", indent, text);
+            }
+            None => match pending.iter_mut().find(|(name, _)| *name == section) {
+                Some((_, texts)) => texts.push(text),
+                None => pending.push((section, vec![text])),
+            },
+        }
+    }
+
+    // 先计算所有插入位置，再从后往前插入
+    let mut insertions: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut appended = Vec::new();
+    for (section, texts) in pending {
+        let has_section = section.is_empty() || sections.iter().zip(&lines).any(|(name, line)| {
+            *name == section && matches!(classify_ini_line(line), IniLine::Header(_))
+        });
+        if !has_section {
+            let header = headers
+                .iter()
+                .find(|(name, _)| *name == section)
+                .map(|(_, header)| header.to_string())
+                .unwrap_or_else(|| format!("[{}]", section));
+            if !appended.is_empty() {
+                appended.push(String::new());
+            }
+            appended.push(header);
+            appended.extend(texts.into_iter().map(str::to_string));
+            continue;
+        }
+
+        let last_entry = (0..lines.len())
+            .rev()
+            .find(|&index| sections[index] == section && matches!(classify_ini_line(&lines[index]), IniLine::Entry(_)));
+        let (position, indent) = match last_entry {
+            Some(index) => (index + 1, jsonc::line_indent(&lines[index], 0).to_string()),
+            None if section.is_empty() => (0, String::new()),
+            None => {
+                let header = (0..lines.len()).find(|&index| sections[index] == section).unwrap_or(0);
+                (header + 1, default_indent.clone())
+            }
+        };
+        insertions.push((position, texts.into_iter().map(|text| format!("{}{}", indent, text)).collect()));
+    }
+    insertions.sort_by_key(|(position, _)| *position);
+    for (position, texts) in insertions.into_iter().rev() {
+        lines.splice(position..position, texts);
+    }
+
+    if !appended.is_empty() {
+        // 保留文件末尾的换行
+        let trailing_newline = lines.last().is_some_and(|line| line.is_empty());
+        if trailing_newline {
+            lines.pop();
+        }
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.extend(appended);
+        if trailing_newline {
+            lines.push(String::new());
+        }
+    }
+    lines.join("\n")
+}
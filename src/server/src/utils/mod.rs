@@ -1,5 +1,11 @@
 pub mod path;
 pub mod command;
+pub mod template;
+pub mod diff;
+pub mod jsonc;
+pub mod merge;
+pub mod syntax;
+pub mod platform;
 
 #[cfg(test)]
 mod tests;
@@ -1,50 +1,190 @@
 use std::path::{Path, PathBuf};
 use std::env;
 
-/// 解析路径中的环境变量
-pub fn expand_env_vars(path: &str) -> String {
-    let mut result = path.to_string();
-    
-    // 替换常见的 Windows 环境变量
-    if result.contains("%USERPROFILE%") {
-        if let Ok(userprofile) = env::var("USERPROFILE") {
-            result = result.replace("%USERPROFILE%", &userprofile);
+/// 路径或命令中引用了未定义的环境变量
+#[derive(Debug, thiserror::Error)]
+#[error("环境变量未定义: {}", .missing.join(", "))]
+pub struct UndefinedVariableError {
+    pub missing: Vec<String>,
+}
+
+/// 当前用户的主目录
+pub fn home_dir() -> Option<PathBuf> {
+    let names: &[&str] = if cfg!(windows) { &["USERPROFILE", "HOME"] } else { &["HOME", "USERPROFILE"] };
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 未设置对应环境变量时使用的常用目录，各平台分别对应 XDG 目录和 AppData 目录
+pub fn well_known_dir(name: &str) -> Option<PathBuf> {
+    let home = home_dir()?;
+    let from_env = |name: &str| env::var(name).ok().filter(|value| !value.is_empty()).map(PathBuf::from);
+    let dir = match name.to_ascii_uppercase().as_str() {
+        "HOME" | "USERPROFILE" => home,
+        // 配置目录：Windows 为 %APPDATA%，其他平台为 XDG 配置目录
+        "XDG_CONFIG_HOME" | "APPDATA" => {
+            if cfg!(windows) {
+                from_env("APPDATA").unwrap_or_else(|| home.join("AppData").join("Roaming"))
+            } else if cfg!(target_os = "macos") {
+                home.join("Library").join("Application Support")
+            } else {
+                from_env("XDG_CONFIG_HOME").unwrap_or_else(|| home.join(".config"))
+            }
         }
-    }
-    
-    if result.contains("%APPDATA%") {
-        if let Ok(appdata) = env::var("APPDATA") {
-            result = result.replace("%APPDATA%", &appdata);
+        // 数据目录：Windows 为 %LOCALAPPDATA%，其他平台为 XDG 数据目录
+        "XDG_DATA_HOME" | "XDG_STATE_HOME" | "LOCALAPPDATA" => {
+            if cfg!(windows) {
+                from_env("LOCALAPPDATA").unwrap_or_else(|| home.join("AppData").join("Local"))
+            } else if cfg!(target_os = "macos") {
+                home.join("Library").join("Application Support")
+            } else if name.eq_ignore_ascii_case("XDG_STATE_HOME") {
+                home.join(".local").join("state")
+            } else {
+                from_env("XDG_DATA_HOME").unwrap_or_else(|| home.join(".local").join("share"))
+            }
         }
-    }
-    
-    if result.contains("%LOCALAPPDATA%") {
-        if let Ok(localappdata) = env::var("LOCALAPPDATA") {
-            result = result.replace("%LOCALAPPDATA%", &localappdata);
+        "XDG_CACHE_HOME" => {
+            if cfg!(windows) {
+                from_env("LOCALAPPDATA").unwrap_or_else(|| home.join("AppData").join("Local"))
+            } else if cfg!(target_os = "macos") {
+                home.join("Library").join("Caches")
+            } else {
+                home.join(".cache")
+            }
         }
+        _ => return None,
+    };
+    Some(dir)
+}
+
+// 环境变量的值，未设置时使用常用目录
+fn lookup(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .or_else(|| well_known_dir(name).map(|dir| dir.to_string_lossy().to_string()))
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// 展开路径或命令参数中的环境变量
+///
+/// 支持 `%VAR%`、`$VAR`、`${VAR}`、`${VAR:-默认值}` 以及开头的 `~`。
+/// 未设置的 XDG 目录和 AppData 目录按当前平台的约定补全；
+/// `$$` 和 `%%` 表示字面量。其他未定义的变量会一并通过 [`UndefinedVariableError`] 报告
+pub fn expand_env_vars(path: &str) -> anyhow::Result<String> {
+    let mut missing = Vec::new();
+    let result = expand_into(path, &mut missing);
+    if !missing.is_empty() {
+        let mut seen = std::collections::HashSet::new();
+        missing.retain(|name| seen.insert(name.clone()));
+        return Err(UndefinedVariableError { missing }.into());
     }
-    
-    if result.contains("%PROGRAMFILES%") {
-        if let Ok(programfiles) = env::var("PROGRAMFILES") {
-            result = result.replace("%PROGRAMFILES%", &programfiles);
+    Ok(result)
+}
+
+fn expand_into(input: &str, missing: &mut Vec<String>) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+
+    // 开头的 ~ 只在后面是路径分隔符或结尾时表示主目录
+    if let Some(after) = rest.strip_prefix('~')
+        && (after.is_empty() || after.starts_with(['/', '\\']))
+    {
+        match home_dir() {
+            Some(home) => result.push_str(&home.to_string_lossy()),
+            None => missing.push("HOME".to_string()),
         }
+        rest = after;
     }
-    
-    if result.contains("%PROGRAMFILES(X86)%") {
-        if let Ok(programfilesx86) = env::var("PROGRAMFILES(X86)") {
-            result = result.replace("%PROGRAMFILES(X86)%", &programfilesx86);
+
+    while let Some(index) = rest.find(['%', '$']) {
+        result.push_str(&rest[..index]);
+        let marker = rest[index..].chars().next().unwrap_or('$');
+        let after = &rest[index + 1..];
+
+        // 连续两个标记表示字面量
+        if after.starts_with(marker) {
+            result.push(marker);
+            rest = &after[1..];
+            continue;
+        }
+
+        if marker == '%' {
+            match after.find('%') {
+                Some(end) if end > 0 && !after[..end].contains(['/', '\\', ' ']) => {
+                    let name = &after[..end];
+                    match lookup(name) {
+                        Some(value) => result.push_str(&value),
+                        None => missing.push(name.to_string()),
+                    }
+                    rest = &after[end + 1..];
+                }
+                // 没有成对的 % 时按字面量处理
+                _ => {
+                    result.push('%');
+                    rest = after;
+                }
+            }
+            continue;
         }
+
+        if let Some(braced) = after.strip_prefix('{') {
+            let Some(end) = braced.find('}') else {
+                result.push('$');
+                rest = after;
+                continue;
+            };
+            let expression = &braced[..end];
+            let (name, default) = match expression.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expression, None),
+            };
+            match (lookup(name), default) {
+                (Some(value), _) => result.push_str(&value),
+                (None, Some(default)) => result.push_str(&expand_into(default, missing)),
+                (None, None) => missing.push(name.to_string()),
+            }
+            rest = &braced[end + 1..];
+            continue;
+        }
+
+        let length = after.find(|c: char| !is_name_char(c)).unwrap_or(after.len());
+        if length == 0 || after.starts_with(|c: char| c.is_ascii_digit()) {
+            // $ 后面不是变量名时按字面量处理
+            result.push('$');
+            rest = after;
+            continue;
+        }
+        let name = &after[..length];
+        match lookup(name) {
+            Some(value) => result.push_str(&value),
+            None => missing.push(name.to_string()),
+        }
+        rest = &after[length..];
     }
-    
+
+    result.push_str(rest);
     result
 }
 
-/// 确保目录存在，如果不存在则创建
-pub fn ensure_dir_exists(path: &Path) -> std::io::Result<()> {
-    if !path.exists() {
-        std::fs::create_dir_all(path)?;
+/// 解析以 `/` 分隔的相对路径，不能是绝对路径，也不能包含 `..` 或反斜杠
+pub fn safe_relative(name: &str) -> anyhow::Result<PathBuf> {
+    let path = Path::new(name);
+    let safe = !name.contains('\\')
+        && path
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_) | std::path::Component::CurDir));
+    let relative: PathBuf = path.components().collect();
+    if !safe || relative.as_os_str().is_empty() {
+        return Err(anyhow::anyhow!("不安全的相对路径: {}", name));
     }
-    Ok(())
+    Ok(relative)
 }
 
 /// 获取相对于应用程序的绝对路径
@@ -54,6 +194,7 @@ pub fn get_absolute_path(relative_path: &str) -> PathBuf {
 }
 
 /// 检查路径是否为绝对路径
+#[allow(dead_code)]
 pub fn is_absolute_path(path: &str) -> bool {
     Path::new(path).is_absolute()
 }
@@ -0,0 +1,39 @@
+use once_cell::sync::Lazy;
+use std::fs;
+
+// 当前 Linux 发行版的标识，读取一次后缓存
+static DISTRO_IDS: Lazy<Vec<String>> = Lazy::new(|| {
+    ["/etc/os-release", "/usr/lib/os-release"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|content| parse_os_release(&content))
+        .unwrap_or_default()
+});
+
+/// 当前操作系统：windows、linux、macos 等，与 `std::env::consts::OS` 相同
+pub fn current_os() -> &'static str {
+    std::env::consts::OS
+}
+
+/// 当前 Linux 发行版的 ID 及其 ID_LIKE 中的各项，越具体的越靠前；其他系统为空
+pub fn distro_ids() -> &'static [String] {
+    if current_os() == "linux" { &DISTRO_IDS } else { &[] }
+}
+
+/// 解析 os-release 文件，返回 ID 和 ID_LIKE 中的发行版标识
+pub fn parse_os_release(content: &str) -> Vec<String> {
+    let value = |key: &str| {
+        content.lines().find_map(|line| {
+            let (name, value) = line.trim().split_once('=')?;
+            (name == key).then(|| value.trim().trim_matches(['"', '\'']).to_ascii_lowercase())
+        })
+    };
+    let mut ids: Vec<String> = value("ID").into_iter().collect();
+    for id in value("ID_LIKE").unwrap_or_default().split_whitespace() {
+        if !ids.iter().any(|existing| existing == id) {
+            ids.push(id.to_string());
+        }
+    }
+    ids.retain(|id| !id.is_empty());
+    ids
+}
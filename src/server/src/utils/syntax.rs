@@ -0,0 +1,246 @@
+use serde::Serialize;
+use std::path::Path;
+use toml_edit::DocumentMut;
+use yaml_rust2::YamlLoader;
+use crate::models::config_file::ConfigFormat;
+use crate::utils::jsonc::{self, ParseError};
+
+/// 单个文件的语法错误，行号和列号从 1 开始
+#[derive(Debug, Clone, Serialize)]
+pub struct SyntaxError {
+    pub path: String,
+    pub format: ConfigFormat,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// 部署前的语法校验未通过，列出所有有错误的文件
+#[derive(Debug, thiserror::Error)]
+#[error("源文件格式错误: {}", describe(.errors))]
+pub struct InvalidFormatError {
+    pub errors: Vec<SyntaxError>,
+}
+
+fn describe(errors: &[SyntaxError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{} 第 {} 行第 {} 列: {}", error.path, error.line, error.column, error.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// 按文件名和扩展名识别配置文件格式
+///
+/// `.json` 按 JSONC 识别，许多程序（如 VS Code）的 JSON 配置允许注释
+pub fn detect_format(path: &Path) -> Option<ConfigFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if matches!(name.as_str(), ".gitconfig" | "gitconfig") {
+        return Some(ConfigFormat::Gitconfig);
+    }
+    // 仓库中的 .git/config 和 XDG 目录中的 git/config
+    let parent = path.parent().and_then(Path::file_name);
+    if name == "config" && parent.is_some_and(|parent| parent == ".git" || parent == "git") {
+        return Some(ConfigFormat::Gitconfig);
+    }
+    if name == ".editorconfig" {
+        return Some(ConfigFormat::Ini);
+    }
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "json" | "jsonc" | "code-workspace" => Some(ConfigFormat::Jsonc),
+        "toml" => Some(ConfigFormat::Toml),
+        "yaml" | "yml" => Some(ConfigFormat::Yaml),
+        "ini" | "cfg" | "conf" => Some(ConfigFormat::Ini),
+        "gitconfig" => Some(ConfigFormat::Gitconfig),
+        _ => None,
+    }
+}
+
+/// 校验内容是否符合格式的语法，`text` 格式总是通过
+pub fn validate(format: ConfigFormat, content: &[u8]) -> Result<(), ParseError> {
+    if format == ConfigFormat::Text {
+        return Ok(());
+    }
+    let text = std::str::from_utf8(content).map_err(|e| {
+        let valid = std::str::from_utf8(&content[..e.valid_up_to()]).unwrap_or_default();
+        ParseError::at(valid, valid.len(), "不是有效的 UTF-8 文本")
+    })?;
+    match format {
+        ConfigFormat::Json => jsonc::parse(text, false).map(|_| ()),
+        ConfigFormat::Jsonc => jsonc::parse(text, true).map(|_| ()),
+        ConfigFormat::Toml => validate_toml(text),
+        ConfigFormat::Yaml => validate_yaml(text),
+        ConfigFormat::Ini => validate_ini(text),
+        ConfigFormat::Gitconfig => validate_gitconfig(text),
+        ConfigFormat::Text => Ok(()),
+    }
+}
+
+fn validate_toml(text: &str) -> Result<(), ParseError> {
+    text.parse::<DocumentMut>().map(|_| ()).map_err(|e| {
+        let pos = e.span().map(|span| span.start).unwrap_or_default();
+        ParseError::at(text, pos, e.message().trim())
+    })
+}
+
+fn validate_yaml(text: &str) -> Result<(), ParseError> {
+    YamlLoader::load_from_str(text).map(|_| ()).map_err(|e| ParseError {
+        line: e.marker().line(),
+        column: e.marker().col() + 1,
+        message: e.info().to_string(),
+    })
+}
+
+// 行内字节位置对应的错误
+fn line_error(index: usize, line: &str, offset: usize, message: &str) -> ParseError {
+    let column = ParseError::at(line, offset, message).column;
+    ParseError { line: index + 1, column, message: message.to_string() }
+}
+
+fn is_comment(rest: &str) -> bool {
+    rest.is_empty() || rest.starts_with([';', '#'])
+}
+
+// 各行的内容及其开头空白之后的位置，去掉开头的 UTF-8 BOM
+fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str, usize)> {
+    text.strip_prefix('\u{feff}')
+        .unwrap_or(text)
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index, line, line.len() - line.trim_start().len()))
+}
+
+// INI 的写法差异很大，只检查节标题是否完整以及键名是否为空
+fn validate_ini(text: &str) -> Result<(), ParseError> {
+    for (index, line, start) in content_lines(text) {
+        let rest = line[start..].trim_end();
+        if is_comment(rest) {
+            continue;
+        }
+        if let Some(inner) = rest.strip_prefix('[') {
+            let Some(end) = inner.find(']') else {
+                return Err(line_error(index, line, start + rest.len(), "节标题缺少 ']'"));
+            };
+            if inner[..end].trim().is_empty() {
+                return Err(line_error(index, line, start + 1, "节名称不能为空"));
+            }
+            let after = inner[end + 1..].trim_start();
+            if !is_comment(after) {
+                return Err(line_error(index, line, start + rest.len() - after.len(), "节标题之后存在多余内容"));
+            }
+        } else if rest.starts_with(['=', ':']) {
+            return Err(line_error(index, line, start, "缺少键名"));
+        }
+    }
+    Ok(())
+}
+
+// gitconfig 按 git-config 的语法检查：节标题、变量名、引号和转义序列
+fn validate_gitconfig(text: &str) -> Result<(), ParseError> {
+    let mut in_section = false;
+    // 上一行以 `\` 结尾时值延续到下一行，以及延续时是否处于引号中
+    let mut continued = None;
+    let mut last = (0, "");
+    for (index, line, start) in content_lines(text) {
+        last = (index, line);
+        if let Some(quoted) = continued {
+            continued = scan_value(index, line, 0, quoted)?;
+            continue;
+        }
+        let mut pos = start;
+        if is_comment(line[pos..].trim_end()) {
+            continue;
+        }
+        if line[pos..].starts_with('[') {
+            pos = parse_section_header(index, line, pos)?;
+            in_section = true;
+            pos = line.len() - line[pos..].trim_start().len();
+            if is_comment(line[pos..].trim_end()) {
+                continue;
+            }
+        }
+        if !in_section {
+            return Err(line_error(index, line, pos, "变量必须位于节中"));
+        }
+
+        let name_end = line[pos..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .map_or(line.len(), |end| pos + end);
+        if !line[pos..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Err(line_error(index, line, pos, "无效的变量名"));
+        }
+        let after = line[name_end..].trim_start();
+        let after_pos = line.len() - after.len();
+        // 没有值的变量表示布尔值 true
+        if is_comment(after.trim_end()) {
+            continue;
+        }
+        if !after.starts_with('=') {
+            return Err(line_error(index, line, after_pos, "变量名之后应为 '='"));
+        }
+        continued = scan_value(index, line, after_pos + 1, false)?;
+    }
+    match continued {
+        Some(true) => Err(line_error(last.0, last.1, last.1.len(), "引号没有结束")),
+        _ => Ok(()),
+    }
+}
+
+// 解析节标题 `[section]` 或 `[section "subsection"]`，返回 `]` 之后的位置
+fn parse_section_header(index: usize, line: &str, start: usize) -> Result<usize, ParseError> {
+    let name_start = start + 1;
+    let name_end = line[name_start..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '.'))
+        .map_or(line.len(), |end| name_start + end);
+    if name_end == name_start {
+        return Err(line_error(index, line, name_start, "节名称无效"));
+    }
+    let mut pos = name_end;
+    if line[pos..].starts_with([' ', '\t']) {
+        pos = line.len() - line[pos..].trim_start().len();
+        if !line[pos..].starts_with('"') {
+            return Err(line_error(index, line, pos, "子节名称必须用引号括起"));
+        }
+        let mut chars = line[pos + 1..].char_indices();
+        let mut closed = None;
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => {
+                    closed = Some(pos + 1 + offset + 1);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        pos = closed.ok_or_else(|| line_error(index, line, pos, "子节名称的引号没有结束"))?;
+    }
+    if !line[pos..].starts_with(']') {
+        return Err(line_error(index, line, pos, "节标题缺少 ']'"));
+    }
+    Ok(pos + 1)
+}
+
+// 检查值中的引号和转义序列；以 `\` 结尾时返回延续到下一行时是否处于引号中
+fn scan_value(index: usize, line: &str, start: usize, mut quoted: bool) -> Result<Option<bool>, ParseError> {
+    let mut chars = line[start..].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                None => return Ok(Some(quoted)),
+                Some((_, 'n' | 't' | 'b' | '"' | '\\')) => {}
+                Some(_) => return Err(line_error(index, line, start + offset, "无效的转义序列")),
+            },
+            '"' => quoted = !quoted,
+            ';' | '#' if !quoted => return Ok(None),
+            _ => {}
+        }
+    }
+    if quoted {
+        return Err(line_error(index, line, line.len(), "引号没有结束"));
+    }
+    Ok(None)
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::env;
+
+/// 模板变量上下文
+///
+/// 模板使用 `{{name}}` 引用变量，`{{env.NAME}}` 引用环境变量。
+/// 内置变量包括 `arch`、`os`、`home`、`hostname` 和 `username`。
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    variables: HashMap<String, String>,
+}
+
+impl Default for TemplateContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplateContext {
+    pub fn new() -> Self {
+        let mut variables = HashMap::new();
+        variables.insert("arch".to_string(), env::consts::ARCH.to_string());
+        variables.insert("os".to_string(), env::consts::OS.to_string());
+        if let Some(home) = env::var("HOME").ok().or_else(|| env::var("USERPROFILE").ok()) {
+            variables.insert("home".to_string(), home);
+        }
+        if let Some(hostname) = sysinfo::System::host_name() {
+            variables.insert("hostname".to_string(), hostname);
+        }
+        if let Some(username) = env::var("USER").ok().or_else(|| env::var("USERNAME").ok()) {
+            variables.insert("username".to_string(), username);
+        }
+
+        TemplateContext { variables }
+    }
+
+    /// 设置变量，已存在的同名变量会被覆盖
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        self.variables.insert(name.to_string(), value.into());
+    }
+
+    /// 批量设置变量
+    pub fn extend<'a>(&mut self, variables: impl IntoIterator<Item = (&'a String, &'a String)>) {
+        for (name, value) in variables {
+            self.set(name, value.clone());
+        }
+    }
+
+    /// 查找变量的值，`env.` 前缀的变量从环境变量中读取
+    pub fn lookup(&self, name: &str) -> Option<String> {
+        match name.strip_prefix("env.") {
+            Some(env_name) => env::var(env_name).ok(),
+            None => self.variables.get(name).cloned(),
+        }
+    }
+
+    /// 渲染模板，引用未定义的变量时返回错误
+    pub fn render(&self, template: &str) -> Result<String> {
+        self.render_with(template, |value| value.to_string())
+    }
+
+    /// 渲染模板，每个替换值先经过 `escape` 处理后再插入
+    pub fn render_with(&self, template: &str, escape: impl Fn(&str) -> String) -> Result<String> {
+        let mut result = String::with_capacity(template.len());
+        for token in tokenize(template)? {
+            match token {
+                Token::Text(text) => result.push_str(text),
+                Token::Variable(name) => {
+                    let value = self
+                        .lookup(name)
+                        .ok_or_else(|| anyhow::anyhow!("未定义的模板变量: {}", name))?;
+                    result.push_str(&escape(&value));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// 获取模板中引用但未定义的变量，按出现顺序去重
+    pub fn missing_variables(&self, template: &str) -> Result<Vec<String>> {
+        let mut missing: Vec<String> = Vec::new();
+        for name in referenced_variables(template)? {
+            if self.lookup(&name).is_none() && !missing.contains(&name) {
+                missing.push(name);
+            }
+        }
+        Ok(missing)
+    }
+
+    /// 校验模板中引用的变量均已定义，`deferred` 中的变量会在执行时才提供
+    pub fn validate(&self, template: &str, deferred: &[&str]) -> Result<()> {
+        for name in referenced_variables(template)? {
+            if !deferred.contains(&name.as_str()) && self.lookup(&name).is_none() {
+                return Err(anyhow::anyhow!("未定义的模板变量: {}", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 获取模板中引用的所有变量名
+pub fn referenced_variables(template: &str) -> Result<Vec<String>> {
+    Ok(tokenize(template)?
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Variable(name) => Some(name.to_string()),
+            Token::Text(_) => None,
+        })
+        .collect())
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+// 将模板拆分为普通文本和变量引用
+fn tokenize(template: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| anyhow::anyhow!("模板缺少结束标记 '}}}}': {}", template))?;
+        let name = after[..end].trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("模板变量名不能为空: {}", template));
+        }
+        tokens.push(Token::Variable(name));
+        rest = &after[end + 2..];
+    }
+
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    Ok(tokens)
+}
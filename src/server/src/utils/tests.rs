@@ -5,47 +5,70 @@ mod path_tests {
     
     #[test]
     fn test_expand_env_vars() {
-        // 保存当前环境变量
-        let original_userprofile = env::var("USERPROFILE").ok();
-        let original_appdata = env::var("APPDATA").ok();
-        
-        // 设置测试环境变量
+        // 使用测试专用的变量名，不修改 USERPROFILE 等其他测试依赖的进程级环境变量
         unsafe {
-            env::set_var("USERPROFILE", "C:\\Users\\TestUser");
-            env::set_var("APPDATA", "C:\\Users\\TestUser\\AppData\\Roaming");
+            env::set_var("PATH_TEST_PROFILE", "C:\\Users\\TestUser");
+            env::set_var("PATH_TEST_APPDATA", "C:\\Users\\TestUser\\AppData\\Roaming");
         }
         
         // 测试环境变量替换
-        let path_with_vars = "%USERPROFILE%\\Documents\\config.json";
-        let expanded_path = expand_env_vars(path_with_vars);
+        let path_with_vars = "%PATH_TEST_PROFILE%\\Documents\\config.json";
+        let expanded_path = expand_env_vars(path_with_vars).unwrap();
         assert_eq!(expanded_path, "C:\\Users\\TestUser\\Documents\\config.json");
         
-        let path_with_multiple_vars = "%APPDATA%\\Code\\%USERPROFILE%\\settings.json";
-        let expanded_path = expand_env_vars(path_with_multiple_vars);
+        let path_with_multiple_vars = "%PATH_TEST_APPDATA%\\Code\\%PATH_TEST_PROFILE%\\settings.json";
+        let expanded_path = expand_env_vars(path_with_multiple_vars).unwrap();
         assert_eq!(expanded_path, "C:\\Users\\TestUser\\AppData\\Roaming\\Code\\C:\\Users\\TestUser\\settings.json");
         
         // 测试不包含环境变量的路径
         let path_without_vars = "C:\\Program Files\\App\\config.json";
-        let expanded_path = expand_env_vars(path_without_vars);
+        let expanded_path = expand_env_vars(path_without_vars).unwrap();
         assert_eq!(expanded_path, path_without_vars);
-        
-        // 恢复原始环境变量
+    }
+    
+    #[test]
+    fn test_expand_unix_style_variables() {
         unsafe {
-            if let Some(val) = original_userprofile {
-                env::set_var("USERPROFILE", val);
-            } else {
-                env::remove_var("USERPROFILE");
-            }
-            
-            if let Some(val) = original_appdata {
-                env::set_var("APPDATA", val);
-            } else {
-                env::remove_var("APPDATA");
-            }
+            env::set_var("PATH_TEST_EXPAND_VAR", "/opt/tools");
         }
+        assert_eq!(expand_env_vars("$PATH_TEST_EXPAND_VAR/bin").unwrap(), "/opt/tools/bin");
+        assert_eq!(expand_env_vars("${PATH_TEST_EXPAND_VAR}_x").unwrap(), "/opt/tools_x");
+        assert_eq!(expand_env_vars("%PATH_TEST_EXPAND_VAR%\\bin").unwrap(), "/opt/tools\\bin");
+        assert_eq!(expand_env_vars("${PATH_TEST_UNDEFINED_VAR:-/tmp/$PATH_TEST_EXPAND_VAR}").unwrap(), "/tmp//opt/tools");
+
+        // 字面量和不构成变量引用的字符保持不变
+        assert_eq!(expand_env_vars("cost $$5 and 50%% or 50% off").unwrap(), "cost $5 and 50% or 50% off");
+        assert_eq!(expand_env_vars("a$ b$1").unwrap(), "a$ b$1");
+        assert_eq!(expand_env_vars("dir/~name").unwrap(), "dir/~name");
     }
-    
+
     #[test]
+    fn test_expand_reports_all_undefined_variables() {
+        let error = expand_env_vars("$PATH_TEST_MISSING_A/%PATH_TEST_MISSING_B%/${PATH_TEST_MISSING_A}").unwrap_err();
+        let missing = &error.downcast_ref::<UndefinedVariableError>().unwrap().missing;
+        assert_eq!(missing, &vec!["PATH_TEST_MISSING_A".to_string(), "PATH_TEST_MISSING_B".to_string()]);
+    }
+
+    #[test]
+    fn test_expand_home_and_well_known_dirs() {
+        let home = home_dir().unwrap();
+        assert_eq!(expand_env_vars("~").unwrap(), home.to_string_lossy());
+        assert_eq!(
+            expand_env_vars("~/.gitconfig").unwrap(),
+            format!("{}/.gitconfig", home.to_string_lossy())
+        );
+
+        // 未设置的 XDG 目录按平台约定补全
+        let config_dir = well_known_dir("XDG_CONFIG_HOME").unwrap();
+        assert!(config_dir.starts_with(&home) || env::var("XDG_CONFIG_HOME").is_ok() || env::var("APPDATA").is_ok());
+        if env::var("XDG_CACHE_HOME").is_err() && cfg!(target_os = "linux") {
+            assert_eq!(expand_env_vars("${XDG_CACHE_HOME}/app").unwrap(), format!("{}/.cache/app", home.to_string_lossy()));
+        }
+        assert!(well_known_dir("NOT_A_WELL_KNOWN_DIR").is_none());
+    }
+
+    #[test]
+    #[cfg(windows)]
     fn test_is_absolute_path() {
         // Windows 绝对路径
         assert!(is_absolute_path("C:\\Users\\Test"));
@@ -58,6 +81,7 @@ mod path_tests {
     }
     
     #[test]
+    #[cfg(windows)]
     fn test_to_absolute_path() {
         // 绝对路径应该保持不变
         let abs_path = "C:\\Users\\Test\\file.txt";
@@ -77,38 +101,466 @@ mod path_tests {
 #[cfg(test)]
 mod command_tests {
     use super::super::command::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     
-    #[test]
-    fn test_execute_command() {
+    #[tokio::test]
+    async fn test_execute_command() {
         // 测试简单的 echo 命令
         if cfg!(target_os = "windows") {
-            let result = execute_command("echo Hello, World!");
+            let result = execute_command("echo Hello, World!").await;
             assert!(result.is_ok());
             let output = result.unwrap();
             assert!(output.contains("Hello, World!"));
         } else {
-            let result = execute_command("echo 'Hello, World!'");
+            let result = execute_command("echo 'Hello, World!'").await;
             assert!(result.is_ok());
             let output = result.unwrap();
             assert!(output.contains("Hello, World!"));
         }
     }
     
-    #[test]
-    fn test_execute_command_error() {
+    #[tokio::test]
+    async fn test_execute_command_error() {
         // 测试不存在的命令
-        let result = execute_command("non_existent_command_123");
+        let result = execute_command("non_existent_command_123").await;
         assert!(result.is_err());
     }
     
-    #[test]
-    fn test_execute_powershell() {
+    #[tokio::test]
+    async fn test_execute_powershell() {
         // 仅在 Windows 上测试 PowerShell
         if cfg!(target_os = "windows") {
-            let result = execute_powershell("Write-Output 'Hello from PowerShell'");
+            let result = execute_powershell("Write-Output 'Hello from PowerShell'").await;
             assert!(result.is_ok());
             let output = result.unwrap();
             assert!(output.contains("Hello from PowerShell"));
         }
     }
+    
+    // 以下测试依赖 Unix 工具
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_argv_env_and_working_dir() {
+        let dir = std::env::temp_dir();
+        let output = CommandRunner::argv(&["sh", "-c", "echo \"$RUNNER_TEST_VAR\"; pwd; exit 3"])
+            .env("RUNNER_TEST_VAR", "a b; c")
+            .current_dir(&dir)
+            .run()
+            .await
+            .unwrap();
+        
+        assert_eq!(output.exit_code, Some(3));
+        assert_eq!(output.signal, None);
+        assert!(!output.success());
+        let mut lines = output.stdout.lines();
+        assert_eq!(lines.next(), Some("a b; c"));
+        assert_eq!(
+            std::fs::canonicalize(lines.next().unwrap()).unwrap(),
+            std::fs::canonicalize(&dir).unwrap()
+        );
+        assert!(output.into_result().is_err());
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_streams_lines_and_bounds_output() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let collected = lines.clone();
+        
+        let output = CommandRunner::shell("for i in 1 2 3 4 5; do echo line$i; done; echo oops >&2")
+            .max_output_bytes(12)
+            .on_stdout_line(move |line| collected.lock().unwrap().push(line.to_string()))
+            .run()
+            .await
+            .unwrap();
+        
+        assert!(output.success());
+        // 逐行回调收到全部输出，保留的内容受上限约束
+        assert_eq!(lines.lock().unwrap().len(), 5);
+        assert_eq!(output.stdout, "line1\nline2\n");
+        assert!(output.stdout_truncated);
+        assert_eq!(output.stderr, "oops\n");
+        assert!(!output.stderr_truncated);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_splits_overlong_lines() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let collected = lines.clone();
+
+        // 没有换行的输出按单行上限分段交给回调
+        let output = CommandRunner::shell("head -c 200000 /dev/zero | tr '\\0' a")
+            .max_output_bytes(16)
+            .on_stdout_line(move |line| collected.lock().unwrap().push(line.len()))
+            .run()
+            .await
+            .unwrap();
+
+        assert!(output.success());
+        let lines = lines.lock().unwrap();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|len| *len <= 64 * 1024));
+        assert_eq!(lines.iter().sum::<usize>(), 200000);
+        assert_eq!(output.stdout, "a".repeat(16));
+        assert!(output.stdout_truncated);
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_timeout_kills_process_tree() {
+        let output = CommandRunner::shell("sleep 30 & sleep 30; echo done")
+            .timeout(Duration::from_millis(200))
+            .run()
+            .await
+            .unwrap();
+        
+        assert!(output.timed_out);
+        assert!(output.duration < Duration::from_secs(10));
+        assert_eq!(output.signal, Some(libc::SIGKILL));
+        assert!(!output.stdout.contains("done"));
+        assert!(output.into_result().is_err());
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_cancel_signal() {
+        let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        let runner = tokio::spawn(CommandRunner::shell("sleep 30").cancel_signal(cancel_rx).run());
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancel_tx.send_replace(true);
+        
+        let output = tokio::time::timeout(Duration::from_secs(10), runner)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(output.cancelled);
+        assert!(!output.success());
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dropped_runner_kills_process_group() {
+        let marker = std::env::temp_dir().join(format!("command_drop_test_{}", uuid::Uuid::new_v4()));
+        let script = format!("(sleep 1; touch {}) & wait", marker.display());
+        let runner = tokio::spawn(CommandRunner::shell(&script).run());
+        
+        // 丢弃执行中的命令，后台的孙进程也应被结束
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        runner.abort();
+        let _ = runner.await;
+        
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+}
+
+#[cfg(test)]
+mod template_tests {
+    use super::super::template::*;
+
+    #[test]
+    fn test_render_variables() {
+        let mut context = TemplateContext::new();
+        context.set("version", "1.2.3");
+        context.set("install_path", "/opt/app");
+
+        let rendered = context
+            .render("https://example.com/app-{{version}}-{{ arch }}.tar.gz -C {{install_path}}")
+            .unwrap();
+        assert_eq!(
+            rendered,
+            format!("https://example.com/app-1.2.3-{}.tar.gz -C /opt/app", std::env::consts::ARCH)
+        );
+        assert_eq!(context.render("{{os}}").unwrap(), std::env::consts::OS);
+
+        // 不含模板的字符串原样返回
+        assert_eq!(context.render("%APPDATA%\\Code").unwrap(), "%APPDATA%\\Code");
+    }
+
+    #[test]
+    fn test_render_env_variable() {
+        unsafe {
+            std::env::set_var("TEMPLATE_TEST_VAR", "from-env");
+        }
+        let context = TemplateContext::new();
+        assert_eq!(context.render("{{env.TEMPLATE_TEST_VAR}}/bin").unwrap(), "from-env/bin");
+        assert!(context.render("{{env.TEMPLATE_TEST_UNDEFINED_VAR}}").is_err());
+    }
+
+    #[test]
+    fn test_undefined_and_malformed_templates() {
+        let context = TemplateContext::new();
+        assert!(context.render("{{undefined}}").is_err());
+        assert!(context.render("{{version").is_err());
+        assert!(context.render("{{ }}").is_err());
+
+        // 延后提供的变量不视为未定义
+        assert!(context.validate("--location {{install_path}}", &["install_path"]).is_ok());
+        assert!(context.validate("--location {{install_path}}", &[]).is_err());
+
+        assert_eq!(
+            referenced_variables("{{version}}-{{ env.HOME }}").unwrap(),
+            vec!["version".to_string(), "env.HOME".to_string()]
+        );
+    }
+}
+
+#[cfg(test)]
+mod quote_tests {
+    use super::super::command::*;
+
+    #[tokio::test]
+    async fn test_quote_shell_arg() {
+        assert_eq!(quote_shell_arg("--location"), "--location");
+        assert_eq!(quote_shell_arg("1.2.3"), "1.2.3");
+
+        // cmd 中双引号写作 ""，% 在引号外转义
+        assert_eq!(quote_cmd_arg("C:\\Program Files"), "\"C:\\Program Files\"");
+        assert_eq!(quote_cmd_arg("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote_cmd_arg("%PATH% & 100%"), "\"\"^%\"PATH\"^%\" & 100\"^%\"\"");
+
+        // 引号之前的反斜杠加倍，其他位置的反斜杠保持原样
+        assert_eq!(quote_cmd_arg("C:\\Program Files\\"), "\"C:\\Program Files\\\\\"");
+        assert_eq!(quote_cmd_arg("a\\\"b"), "\"a\\\\\"\"b\"");
+        assert_eq!(quote_cmd_arg("C:\\dir\\%x%"), "\"C:\\dir\\\\\"^%\"x\"^%\"\"");
+        assert_eq!(quote_cmd_arg("\\\\server\\share"), "\"\\\\server\\share\"");
+
+        if cfg!(target_os = "windows") {
+            assert_eq!(quote_shell_arg("C:\\Program Files"), "\"C:\\Program Files\"");
+        } else {
+            assert_eq!(quote_shell_arg("/opt/my app"), "'/opt/my app'");
+            assert_eq!(quote_shell_arg("it's; rm -rf /"), "'it'\\''s; rm -rf /'");
+            assert_eq!(quote_shell_arg(""), "''");
+
+            // 引用后的参数被 shell 原样传递
+            let output = execute_command(&format!("printf %s {}", quote_shell_arg("$(echo hi); `id`")))
+                .await
+                .unwrap();
+            assert_eq!(output, "$(echo hi); `id`");
+        }
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::super::diff::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff("a\nb\nc\n", "a\nB\nc\n", "a/file", "b/file");
+        assert!(diff.starts_with("--- a/file\n+++ b/file\n"));
+        assert!(diff.contains("-b\n"));
+        assert!(diff.contains("+B\n"));
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary("普通文本\n".as_bytes()));
+        assert!(is_binary(b"abc\0def"));
+        assert!(is_binary(&[0xff, 0xfe, 0x41]));
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let root = std::env::temp_dir().join(format!("diff_tests_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let old = root.join("old.txt");
+        let new = root.join("new.txt");
+        std::fs::write(&old, "same\n").unwrap();
+        std::fs::write(&new, "same\n").unwrap();
+
+        assert!(diff_files(Some(&old), Some(&new), "file", 1024).unwrap().is_none());
+        assert_eq!(diff_files(None, Some(&new), "file", 1024).unwrap().unwrap().change, ChangeKind::Added);
+        assert_eq!(diff_files(Some(&old), None, "file", 1024).unwrap().unwrap().change, ChangeKind::Removed);
+
+        std::fs::write(&new, "changed\n").unwrap();
+        let change = diff_files(Some(&old), Some(&new), "file", 1024).unwrap().unwrap();
+        assert_eq!(change.change, ChangeKind::Modified);
+        assert!(change.diff.unwrap().contains("+changed"));
+
+        // 超出大小限制时只报告变化
+        let change = diff_files(Some(&old), Some(&new), "file", 4).unwrap().unwrap();
+        assert!(change.too_large);
+        assert!(change.diff.is_none());
+
+        std::fs::write(&new, [0u8, 159, 146, 150]).unwrap();
+        let change = diff_files(Some(&old), Some(&new), "file", 1024).unwrap().unwrap();
+        assert!(change.binary);
+        assert!(change.diff.is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
+
+#[cfg(test)]
+mod jsonc_tests {
+    use super::super::jsonc::*;
+
+    #[test]
+    fn test_parse_jsonc_with_comments_and_trailing_commas() {
+        let text = "// 设置\n{\n    \"a\": 1, /* 行内 */\n    \"b\": {\"c\": [1, 2,],},\n}\n";
+        let root = parse(text, true).unwrap();
+        let members = root.members.unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[1].key, "b");
+        assert_eq!(&text[members[0].value.start..members[0].value.end], "1");
+        assert!(members[1].value.members.is_some());
+
+        // 严格模式下注释和尾随逗号都是错误
+        assert!(parse(text, false).is_err());
+        assert!(parse("{\"a\": 1,}", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let error = parse("{\n  \"a\": 1\n  \"b\": 2\n}", true).unwrap_err();
+        assert_eq!((error.line, error.column), (3, 3));
+        let error = parse("{\"a\": tru}", true).unwrap_err();
+        assert_eq!((error.line, error.column), (1, 7));
+    }
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use super::super::merge::*;
+    use crate::models::config_file::MergeStrategy;
+    use std::path::Path;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(Path::new("settings.json")), Some(FileFormat::Json));
+        assert_eq!(detect_format(Path::new("a/config.YML")), Some(FileFormat::Yaml));
+        assert_eq!(detect_format(Path::new(".gitconfig")), Some(FileFormat::Ini));
+        assert_eq!(detect_format(Path::new("Cargo.toml")), Some(FileFormat::Toml));
+        assert_eq!(detect_format(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn test_merge_jsonc_preserves_comments_and_local_keys() {
+        let target = "{\n    // 本机设置\n    \"window.zoomLevel\": 1,\n    \"editor\": {\n        \"fontSize\": 12,\n        \"local\": true\n    }\n}\n";
+        let source = "{\n  \"editor\": {\n    \"fontSize\": 14,\n    \"tabSize\": 2\n  },\n  \"files.eol\": \"\\n\"\n}";
+
+        let merged = merge(FileFormat::Json, source, target, MergeStrategy::DeepMerge).unwrap();
+        assert_eq!(
+            merged,
+            "{\n    // 本机设置\n    \"window.zoomLevel\": 1,\n    \"editor\": {\n        \"fontSize\": 14,\n        \"local\": true,\n        \"tabSize\": 2\n    },\n    \"files.eol\": \"\\n\"\n}\n"
+        );
+
+        // 只按顶层键替换时，嵌套对象整体使用源文件的内容
+        let merged = merge(FileFormat::Json, source, target, MergeStrategy::KeysOnly).unwrap();
+        assert!(merged.contains("// 本机设置"));
+        assert!(merged.contains("\"editor\": {\n        \"fontSize\": 14,\n        \"tabSize\": 2\n    }"));
+        assert!(!merged.contains("local"));
+
+        assert_eq!(merge(FileFormat::Json, source, target, MergeStrategy::Overwrite).unwrap(), source);
+        assert_eq!(merge(FileFormat::Json, source, "{}", MergeStrategy::DeepMerge).unwrap(), "{\n    \"editor\": {\n        \"fontSize\": 14,\n        \"tabSize\": 2\n    },\n    \"files.eol\": \"\\n\"\n}");
+        assert!(merge(FileFormat::Json, source, "[1]", MergeStrategy::DeepMerge).is_err());
+    }
+
+    #[test]
+    fn test_merge_toml_preserves_comments() {
+        let target = "# 本机\nname = \"local\"\n\n[editor]\n# 字号\nsize = 12\nlocal = true\n";
+        let source = "[editor]\nsize = 14\ntheme = \"dark\"\n";
+        let merged = merge(FileFormat::Toml, source, target, MergeStrategy::DeepMerge).unwrap();
+        assert!(merged.starts_with("# 本机\nname = \"local\"\n"));
+        assert!(merged.contains("# 字号\nsize = 14"));
+        assert!(merged.contains("local = true"));
+        assert!(merged.contains("theme = \"dark\""));
+
+        let merged = merge(FileFormat::Toml, source, target, MergeStrategy::KeysOnly).unwrap();
+        assert!(!merged.contains("local = true"));
+        assert!(merged.contains("name = \"local\""));
+    }
+
+    #[test]
+    fn test_merge_yaml() {
+        let target = "name: local\neditor:\n  size: 12\n  local: true\n";
+        let source = "editor:\n  size: 14\n";
+        let merged = merge(FileFormat::Yaml, source, target, MergeStrategy::DeepMerge).unwrap();
+        assert_eq!(merged, "name: local\neditor:\n  size: 14\n  local: true\n");
+    }
+
+    #[test]
+    fn test_merge_ini_keeps_layout() {
+        let target = "[user]\n\tname = Local\n\temail = local@example.com\n; 本机\n\n[core]\n\teditor = vim\n";
+        let source = "[user]\nemail = me@example.com\nsigningkey = ABC\n\n[alias]\nst = status\n";
+        let merged = merge(FileFormat::Ini, source, target, MergeStrategy::DeepMerge).unwrap();
+        assert_eq!(
+            merged,
+            "[user]\n\tname = Local\n\temail = me@example.com\n\tsigningkey = ABC\n; 本机\n\n[core]\n\teditor = vim\n\n[alias]\nst = status\n"
+        );
+    }
+}
+
+#[cfg(test)]
+mod platform_tests {
+    use crate::utils::platform::parse_os_release;
+
+    #[test]
+    fn test_parse_os_release() {
+        let content = "NAME=\"Linux Mint\"\nID=linuxmint\nID_LIKE=\"ubuntu debian\"\nVERSION_ID=\"21.3\"\n";
+        assert_eq!(parse_os_release(content), vec!["linuxmint", "ubuntu", "debian"]);
+        assert_eq!(parse_os_release("ID=Arch\n"), vec!["arch"]);
+        assert!(parse_os_release("NAME=Unknown\n").is_empty());
+    }
+}
+
+#[cfg(test)]
+mod syntax_tests {
+    use super::super::syntax::*;
+    use crate::models::config_file::ConfigFormat;
+    use std::path::Path;
+
+    fn position(format: ConfigFormat, text: &str) -> (usize, usize) {
+        let error = validate(format, text.as_bytes()).unwrap_err();
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(Path::new("settings.json")), Some(ConfigFormat::Jsonc));
+        assert_eq!(detect_format(Path::new("/home/user/.config/git/config")), Some(ConfigFormat::Gitconfig));
+        assert_eq!(detect_format(Path::new(".gitconfig")), Some(ConfigFormat::Gitconfig));
+        assert_eq!(detect_format(Path::new(".editorconfig")), Some(ConfigFormat::Ini));
+        assert_eq!(detect_format(Path::new("/etc/app/config")), None);
+    }
+
+    #[test]
+    fn test_validate_reports_line_and_column() {
+        let jsonc = "{\n  // 注释\n  \"a\": 1,\n}\n";
+        assert!(validate(ConfigFormat::Jsonc, jsonc.as_bytes()).is_ok());
+        assert_eq!(position(ConfigFormat::Json, jsonc), (2, 3));
+        assert_eq!(position(ConfigFormat::Jsonc, "{\n  \"a\": 1\n  \"b\": 2\n}"), (3, 3));
+
+        assert!(validate(ConfigFormat::Toml, b"[a]\nb = 1\n").is_ok());
+        assert_eq!(position(ConfigFormat::Toml, "[a]\nb = \n"), (2, 5));
+
+        assert!(validate(ConfigFormat::Yaml, b"a:\n  b: 1\n").is_ok());
+        assert_eq!(position(ConfigFormat::Yaml, "a: 1\nb: c: d\n"), (2, 5));
+
+        assert!(validate(ConfigFormat::Ini, b"; comment\n[core]\nkey = value\nflag\n").is_ok());
+        assert_eq!(position(ConfigFormat::Ini, "[core]\n  = value\n"), (2, 3));
+        assert_eq!(position(ConfigFormat::Ini, "[core\n"), (1, 6));
+
+        assert_eq!(position(ConfigFormat::Json, "{\"a\": \"\u{4e2d}\u{6587}\" x}"), (1, 12));
+        let error = validate(ConfigFormat::Toml, b"a = 1\nb = \"\xff\"").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 6));
+    }
+
+    #[test]
+    fn test_validate_gitconfig() {
+        let valid = "[user]\n\tname = \"Test User\" ; 注释\n[remote \"origin\"]\n\turl = git@example.com:a/b.git\n[core]\n\tbare\n\tpager = less \\\n\t\t-R\n";
+        assert!(validate(ConfigFormat::Gitconfig, valid.as_bytes()).is_ok());
+
+        assert_eq!(position(ConfigFormat::Gitconfig, "name = a\n"), (1, 1));
+        assert_eq!(position(ConfigFormat::Gitconfig, "[user]\n\tname = \"a\n"), (2, 11));
+        assert_eq!(position(ConfigFormat::Gitconfig, "[user]\n\t1name = a\n"), (2, 2));
+        assert_eq!(position(ConfigFormat::Gitconfig, "[remote origin]\n"), (1, 9));
+        assert_eq!(position(ConfigFormat::Gitconfig, "[user]\n\tname = a\\q\n"), (2, 10));
+        assert!(validate(ConfigFormat::Text, b"anything {").is_ok());
+    }
 }